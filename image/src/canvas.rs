@@ -2,7 +2,7 @@ use std::io::Write;
 
 use crate::color::{self, RGBAColor};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
@@ -55,7 +55,7 @@ impl Canvas {
 
 impl Canvas {
     pub fn to_ppm(&self) -> String {
-        fn row_to_ppi(row: &Vec<color::RGBAColor>) -> String {
+        fn row_to_ppi(row: &[color::RGBAColor]) -> String {
             let mut s = String::new();
             let mut char_count = 0;
 
@@ -80,7 +80,7 @@ impl Canvas {
 
         let (height, width) = (self.height, self.width);
         let ppi_header = format!("P3\n{} {}\n255\n", width, height);
-        let pixels_strings: Vec<String> = self.pixels.iter().map(row_to_ppi).collect();
+        let pixels_strings: Vec<String> = self.pixels.iter().map(|row| row_to_ppi(row)).collect();
         format!("{}{}", ppi_header, pixels_strings.join(""))
     }
 
@@ -89,4 +89,123 @@ impl Canvas {
         let mut file = std::fs::File::create(to).unwrap();
         let _ = file.write_all(file_content.as_bytes());
     }
+
+    /// Read a canvas from the contents of a plain (P3) or binary (P6) PPM file
+    pub fn from_ppm(bytes: &[u8]) -> Result<Self, PpmError> {
+        let mut reader = PpmReader { bytes, position: 0 };
+
+        let magic = reader.next_token().ok_or(PpmError::UnexpectedEnd)?;
+        let binary = match magic {
+            b"P3" => false,
+            b"P6" => true,
+            _ => return Err(PpmError::UnsupportedFormat),
+        };
+
+        let width = reader.next_number()?;
+        let height = reader.next_number()?;
+        let max_value = reader.next_number()?;
+        if max_value == 0 || max_value > 255 {
+            return Err(PpmError::InvalidValue);
+        }
+        let scale = |value: usize| match value <= max_value {
+            true => Ok((value * 255 / max_value) as u8),
+            false => Err(PpmError::InvalidValue),
+        };
+
+        // In binary files, a single whitespace character separates the header from the pixels
+        let body = bytes.get(reader.position + 1..).unwrap_or_default();
+        // Every channel takes at least one byte, so the size is checked before the pixels are
+        // allocated
+        let samples = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(3))
+            .ok_or(PpmError::UnexpectedEnd)?;
+        if samples > body.len() {
+            return Err(PpmError::UnexpectedEnd);
+        }
+
+        let mut canvas = Canvas::with_size(height, width);
+        let mut pixels = body.iter();
+
+        for row in 0..height {
+            for col in 0..width {
+                let mut channel = || match binary {
+                    true => pixels
+                        .next()
+                        .ok_or(PpmError::UnexpectedEnd)
+                        .and_then(|&value| scale(value as usize)),
+                    false => reader.next_number().and_then(scale),
+                };
+                let color = RGBAColor::from((channel()?, channel()?, channel()?));
+                canvas.set_pixel_color((row, col), color);
+            }
+        }
+
+        Ok(canvas)
+    }
+}
+
+/// Reasons why a PPM file could not be read
+#[derive(Debug, PartialEq, Eq)]
+pub enum PpmError {
+    /// Only P3 and P6 files are supported
+    UnsupportedFormat,
+    /// The file ended before all of the pixels were read
+    UnexpectedEnd,
+    /// Some value in the file is not a valid number
+    InvalidValue,
+}
+
+impl std::fmt::Display for PpmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PpmError::UnsupportedFormat => write!(f, "only P3 and P6 ppm files are supported"),
+            PpmError::UnexpectedEnd => write!(f, "the ppm file ended unexpectedly"),
+            PpmError::InvalidValue => write!(f, "the ppm file contains an invalid value"),
+        }
+    }
+}
+
+impl std::error::Error for PpmError {}
+
+/// Splits the header (and plain pixels) of a PPM file into whitespace separated tokens
+struct PpmReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PpmReader<'a> {
+    fn next_token(&mut self) -> Option<&'a [u8]> {
+        // Skip whitespace and comments, which go until the end of the line
+        while let Some(&byte) = self.bytes.get(self.position) {
+            match byte {
+                b'#' => {
+                    while self.bytes.get(self.position).is_some_and(|&b| b != b'\n') {
+                        self.position += 1;
+                    }
+                }
+                _ if byte.is_ascii_whitespace() => self.position += 1,
+                _ => break,
+            }
+        }
+
+        let start = self.position;
+        while self
+            .bytes
+            .get(self.position)
+            .is_some_and(|byte| !byte.is_ascii_whitespace())
+        {
+            self.position += 1;
+        }
+
+        (start < self.position).then(|| &self.bytes[start..self.position])
+    }
+
+    fn next_number(&mut self) -> Result<usize, PpmError> {
+        let token = self.next_token().ok_or(PpmError::UnexpectedEnd)?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or(PpmError::InvalidValue)
+    }
 }
@@ -1,3 +1,5 @@
+use std::ops::{Add, Mul, Sub};
+
 use crate::utils;
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -52,3 +54,131 @@ impl From<(f64, f64, f64)> for RGBAColor {
         Self::from((rp, gp, bp, 1.0))
     }
 }
+
+/// Color whose channels are stored as real numbers
+///
+/// While shading, colors get added and multiplied together many times, so the channels are not
+/// limited to the 0 - 1 range. They are only clamped when converted into an `RGBAColor`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Color {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+}
+
+impl Color {
+    pub fn new(red: f64, green: f64, blue: f64) -> Self {
+        Self { red, green, blue }
+    }
+
+    pub fn black() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn white() -> Self {
+        Self::new(1.0, 1.0, 1.0)
+    }
+}
+
+impl PartialEq for Color {
+    fn eq(&self, other: &Self) -> bool {
+        utils::approx(self.red, other.red)
+            && utils::approx(self.green, other.green)
+            && utils::approx(self.blue, other.blue)
+    }
+}
+
+impl<A: Into<f64>> From<(A, A, A)> for Color {
+    fn from((red, green, blue): (A, A, A)) -> Self {
+        Self::new(red.into(), green.into(), blue.into())
+    }
+}
+
+impl From<Color> for RGBAColor {
+    fn from(Color { red, green, blue }: Color) -> Self {
+        Self::from((
+            red.clamp(0.0, 1.0),
+            green.clamp(0.0, 1.0),
+            blue.clamp(0.0, 1.0),
+        ))
+    }
+}
+
+impl From<RGBAColor> for Color {
+    fn from(
+        RGBAColor {
+            red, green, blue, ..
+        }: RGBAColor,
+    ) -> Self {
+        Self::new(
+            red as f64 / 255.0,
+            green as f64 / 255.0,
+            blue as f64 / 255.0,
+        )
+    }
+}
+
+impl Add for Color {
+    type Output = Color;
+    fn add(self, rhs: Self) -> Self::Output {
+        Color::new(
+            self.red + rhs.red,
+            self.green + rhs.green,
+            self.blue + rhs.blue,
+        )
+    }
+}
+
+impl Sub for Color {
+    type Output = Color;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Color::new(
+            self.red - rhs.red,
+            self.green - rhs.green,
+            self.blue - rhs.blue,
+        )
+    }
+}
+
+// Hadamard product, used to blend the color of a light with the color of a surface
+impl Mul for Color {
+    type Output = Color;
+    fn mul(self, rhs: Self) -> Self::Output {
+        Color::new(
+            self.red * rhs.red,
+            self.green * rhs.green,
+            self.blue * rhs.blue,
+        )
+    }
+}
+
+impl Mul<f64> for Color {
+    type Output = Color;
+    fn mul(self, rhs: f64) -> Self::Output {
+        Color::new(self.red * rhs, self.green * rhs, self.blue * rhs)
+    }
+}
+
+#[cfg(test)]
+mod test_color {
+    use super::*;
+
+    #[test]
+    fn color_arithmetic() {
+        let c1 = Color::from((0.9, 0.6, 0.75));
+        let c2 = Color::from((0.7, 0.1, 0.25));
+        assert_eq!(c1 + c2, Color::from((1.6, 0.7, 1.0)));
+        assert_eq!(c1 - c2, Color::from((0.2, 0.5, 0.5)));
+        assert_eq!(c1 * 2.0, Color::from((1.8, 1.2, 1.5)));
+
+        let c1 = Color::from((1.0, 0.2, 0.4));
+        let c2 = Color::from((0.9, 1.0, 0.1));
+        assert_eq!(c1 * c2, Color::from((0.9, 0.2, 0.04)));
+    }
+
+    #[test]
+    fn clamp_to_rgba() {
+        let c = Color::from((1.5, -0.5, 1.0));
+        assert_eq!(RGBAColor::from(c), RGBAColor::from((255, 0, 255)));
+    }
+}
@@ -0,0 +1,293 @@
+use crate::color::Color;
+
+/// Image with colors that can go past white, as read from Radiance HDR files
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+
+    pixels: Vec<Vec<Color>>,
+}
+
+impl HdrImage {
+    pub fn with_size(height: usize, width: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![vec![Color::black(); width]; height],
+        }
+    }
+
+    pub fn set_pixel_color(&mut self, (row, col): (usize, usize), color: Color) -> &mut Self {
+        self.pixels[row][col] = color;
+        self
+    }
+
+    pub fn get_color_at(&self, (row, col): (usize, usize)) -> Option<&Color> {
+        self.pixels.get(row).and_then(|r| r.get(col))
+    }
+
+    /// Read an image from the contents of a Radiance HDR (RGBE) file, with flat or run length
+    /// encoded scanlines. Only the usual orientation, `-Y height +X width`, is supported
+    pub fn from_hdr(bytes: &[u8]) -> Result<Self, HdrError> {
+        let mut lines = HeaderLines { bytes, position: 0 };
+
+        let magic = lines.next().ok_or(HdrError::UnexpectedEnd)?;
+        if magic != b"#?RADIANCE" && magic != b"#?RGBE" {
+            return Err(HdrError::UnsupportedFormat);
+        }
+        // Variables such as the format or the exposure go until an empty line
+        loop {
+            let line = lines.next().ok_or(HdrError::UnexpectedEnd)?;
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with(b"FORMAT=") && line != b"FORMAT=32-bit_rle_rgbe" {
+                return Err(HdrError::UnsupportedFormat);
+            }
+        }
+
+        let resolution = lines.next().ok_or(HdrError::UnexpectedEnd)?;
+        let resolution = std::str::from_utf8(resolution).map_err(|_| HdrError::InvalidValue)?;
+        let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (
+                height.parse().map_err(|_| HdrError::InvalidValue)?,
+                width.parse().map_err(|_| HdrError::InvalidValue)?,
+            ),
+            _ => return Err(HdrError::UnsupportedFormat),
+        };
+
+        // The size comes from the header, so it is checked against the length of the data before
+        // the pixels are allocated
+        let mut data = bytes.get(lines.position..).unwrap_or_default();
+        if width == 0 {
+            return Err(HdrError::InvalidValue);
+        }
+        let needed = min_scanline_size(width)
+            .and_then(|size| size.checked_mul(height))
+            .ok_or(HdrError::InvalidValue)?;
+        if needed > data.len() {
+            return Err(HdrError::UnexpectedEnd);
+        }
+
+        let mut image = HdrImage::with_size(height, width);
+        for row in 0..height {
+            let (scanline, rest) = read_scanline(data, width)?;
+            data = rest;
+            for (col, rgbe) in scanline.iter().enumerate() {
+                image.set_pixel_color((row, col), decode(*rgbe));
+            }
+        }
+
+        Ok(image)
+    }
+}
+
+/// Reasons why a Radiance HDR file could not be read
+#[derive(Debug, PartialEq, Eq)]
+pub enum HdrError {
+    /// The file is not a Radiance HDR file, or it uses a format or orientation that is not
+    /// supported
+    UnsupportedFormat,
+    /// The file ended before all of the pixels were read
+    UnexpectedEnd,
+    /// Some value in the file is not valid
+    InvalidValue,
+}
+
+impl std::fmt::Display for HdrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HdrError::UnsupportedFormat => write!(f, "only rgbe hdr files are supported"),
+            HdrError::UnexpectedEnd => write!(f, "the hdr file ended unexpectedly"),
+            HdrError::InvalidValue => write!(f, "the hdr file contains an invalid value"),
+        }
+    }
+}
+
+impl std::error::Error for HdrError {}
+
+/// Splits the text header of an HDR file into lines
+struct HeaderLines<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for HeaderLines<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.bytes.get(self.position..)?;
+        let end = rest.iter().position(|&byte| byte == b'\n')?;
+        self.position += end + 1;
+        Some(&rest[..end])
+    }
+}
+
+/// Fewest bytes a scanline of the width can take. Run length encoded ones need at least a run of
+/// 2 bytes for every 127 pixels of each channel, after their 4 first bytes
+fn min_scanline_size(width: usize) -> Option<usize> {
+    match (8..0x8000).contains(&width) {
+        true => Some(4 + 8 * width.div_ceil(127)),
+        false => width.checked_mul(4),
+    }
+}
+
+/// Read the pixels of a scanline, returning the data after it
+fn read_scanline(data: &[u8], width: usize) -> Result<(Vec<[u8; 4]>, &[u8]), HdrError> {
+    // Run length encoded scanlines start with 2, 2 and their width. Each channel is encoded apart
+    let encoded = (8..0x8000).contains(&width)
+        && data.get(..2) == Some(&[2, 2])
+        && data.get(2).is_some_and(|&byte| byte & 0x80 == 0);
+    if !encoded {
+        let size = width.checked_mul(4).ok_or(HdrError::InvalidValue)?;
+        let bytes = data.get(..size).ok_or(HdrError::UnexpectedEnd)?;
+        let pixels = bytes
+            .chunks_exact(4)
+            .map(|rgbe| [rgbe[0], rgbe[1], rgbe[2], rgbe[3]])
+            .collect();
+        return Ok((pixels, &data[size..]));
+    }
+
+    if (data[2] as usize) << 8 | *data.get(3).ok_or(HdrError::UnexpectedEnd)? as usize != width {
+        return Err(HdrError::InvalidValue);
+    }
+    let mut pixels = vec![[0; 4]; width];
+    let mut position = 4;
+    let mut next = || {
+        let byte = data.get(position).ok_or(HdrError::UnexpectedEnd);
+        position += 1;
+        byte.copied()
+    };
+    for channel in 0..4 {
+        let mut col = 0;
+        while col < width {
+            // Counts over 128 repeat the next byte, the others are followed by that many bytes
+            let count = next()? as usize;
+            let (length, run) = match count > 128 {
+                true => (count - 128, true),
+                false => (count, false),
+            };
+            if length == 0 || col + length > width {
+                return Err(HdrError::InvalidValue);
+            }
+            let repeated = match run {
+                true => Some(next()?),
+                false => None,
+            };
+            for pixel in &mut pixels[col..col + length] {
+                pixel[channel] = match repeated {
+                    Some(byte) => byte,
+                    None => next()?,
+                };
+            }
+            col += length;
+        }
+    }
+
+    Ok((pixels, &data[position..]))
+}
+
+/// Color of a pixel, whose channels share the exponent in the last byte
+fn decode([red, green, blue, exponent]: [u8; 4]) -> Color {
+    if exponent == 0 {
+        return Color::black();
+    }
+    let scale = 2f64.powi(exponent as i32 - 136);
+    Color::new(
+        red as f64 * scale,
+        green as f64 * scale,
+        blue as f64 * scale,
+    )
+}
+
+#[cfg(test)]
+mod test_hdr {
+    use super::*;
+
+    fn file(resolution: &str, data: &[u8]) -> Vec<u8> {
+        let header = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\nEXPOSURE=1.0\n\n{resolution}\n");
+        [header.as_bytes(), data].concat()
+    }
+
+    #[test]
+    fn flat_scanlines() {
+        // 1 is 128 * 2^-7, 0.5 is 128 * 2^-8 and 4 is 128 * 2^-5
+        let data = [
+            [128, 64, 0, 129],
+            [0, 0, 0, 0],
+            [128, 128, 128, 128],
+            [128, 32, 255, 131],
+        ]
+        .concat();
+        let image = HdrImage::from_hdr(&file("-Y 2 +X 2", &data)).unwrap();
+
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.get_color_at((0, 0)), Some(&Color::new(1.0, 0.5, 0.0)));
+        assert_eq!(image.get_color_at((0, 1)), Some(&Color::black()));
+        assert_eq!(image.get_color_at((1, 0)), Some(&Color::new(0.5, 0.5, 0.5)));
+        assert_eq!(
+            image.get_color_at((1, 1)),
+            Some(&Color::new(4.0, 1.0, 255.0 / 32.0))
+        );
+        assert_eq!(image.get_color_at((2, 0)), None);
+    }
+
+    #[test]
+    fn run_length_encoded_scanlines() {
+        // Each channel of a 10 pixel wide scanline, as a run or as literal bytes
+        let mut data = vec![2, 2, 0, 10];
+        data.extend([128 + 10, 128]);
+        data.extend([128 + 5, 64, 5, 1, 2, 3, 4, 5]);
+        data.extend([128 + 10, 0]);
+        data.extend([128 + 10, 129]);
+        let image = HdrImage::from_hdr(&file("-Y 1 +X 10", &data)).unwrap();
+
+        assert_eq!(image.get_color_at((0, 0)), Some(&Color::new(1.0, 0.5, 0.0)));
+        assert_eq!(image.get_color_at((0, 4)), Some(&Color::new(1.0, 0.5, 0.0)));
+        assert_eq!(
+            image.get_color_at((0, 9)),
+            Some(&Color::new(1.0, 5.0 / 128.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn invalid_files() {
+        assert_eq!(
+            HdrImage::from_hdr(b"P3\n1 1\n255\n0 0 0\n"),
+            Err(HdrError::UnsupportedFormat)
+        );
+        assert_eq!(
+            HdrImage::from_hdr(&file("+Y 1 +X 1", &[0, 0, 0, 0])),
+            Err(HdrError::UnsupportedFormat)
+        );
+        assert_eq!(
+            HdrImage::from_hdr(&file("-Y 1 +X two", &[0, 0, 0, 0])),
+            Err(HdrError::InvalidValue)
+        );
+        assert_eq!(
+            HdrImage::from_hdr(&file("-Y 2 +X 1", &[0, 0, 0, 0])),
+            Err(HdrError::UnexpectedEnd)
+        );
+        assert_eq!(
+            HdrImage::from_hdr(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n"),
+            Err(HdrError::UnsupportedFormat)
+        );
+    }
+
+    #[test]
+    fn sizes_larger_than_the_data() {
+        assert_eq!(
+            HdrImage::from_hdr(&file("-Y 99999999999 +X 99999999999", &[0, 0, 0, 0])),
+            Err(HdrError::InvalidValue)
+        );
+        assert_eq!(
+            HdrImage::from_hdr(&file("-Y 99999999999 +X 16", &[2, 2, 0, 16])),
+            Err(HdrError::UnexpectedEnd)
+        );
+        assert_eq!(
+            HdrImage::from_hdr(&file("-Y 99999999999 +X 0", &[])),
+            Err(HdrError::InvalidValue)
+        );
+    }
+}
@@ -1,5 +1,6 @@
 pub mod canvas;
 pub mod color;
+pub mod hdr;
 mod utils;
 
 #[cfg(test)]
@@ -66,6 +67,72 @@ mod test_pic {
         assert_eq!(expected, p.to_ppm());
     }
 
+    #[test]
+    fn read_plain_ppm() {
+        let ppm = indoc! {"
+            P3
+            # A comment in the header
+            2 2
+            100
+            100 0 0   0 50 0
+            0 0 100   100 100 100
+        "};
+        let p = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!((p.height, p.width), (2, 2));
+        assert_eq!(p.get_color_at((0, 0)), Some(&RGBAColor::from((255, 0, 0))));
+        assert_eq!(p.get_color_at((0, 1)), Some(&RGBAColor::from((0, 127, 0))));
+        assert_eq!(p.get_color_at((1, 0)), Some(&RGBAColor::from((0, 0, 255))));
+
+        // Writing and reading a canvas gives back the same canvas
+        assert_eq!(Canvas::from_ppm(p.to_ppm().as_bytes()).unwrap(), p);
+    }
+
+    #[test]
+    fn read_binary_ppm() {
+        let mut ppm = b"P6\n2 1\n255\n".to_vec();
+        ppm.extend([255, 0, 0, 10, 20, 30]);
+        let p = Canvas::from_ppm(&ppm).unwrap();
+        assert_eq!(p.get_color_at((0, 0)), Some(&RGBAColor::from((255, 0, 0))));
+        assert_eq!(p.get_color_at((0, 1)), Some(&RGBAColor::from((10, 20, 30))));
+    }
+
+    #[test]
+    fn read_invalid_ppm() {
+        use canvas::PpmError;
+        assert_eq!(
+            Canvas::from_ppm(b"P5 1 1 255 0"),
+            Err(PpmError::UnsupportedFormat)
+        );
+        assert_eq!(
+            Canvas::from_ppm(b"P3 2 1 255 0 0 0"),
+            Err(PpmError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Canvas::from_ppm(b"P3 1 1 255 0 red 0"),
+            Err(PpmError::InvalidValue)
+        );
+
+        // Values above the maximum of the header
+        assert_eq!(
+            Canvas::from_ppm(b"P3 1 1 100 0 101 0"),
+            Err(PpmError::InvalidValue)
+        );
+        assert_eq!(
+            Canvas::from_ppm(b"P6 1 1 100\n\x00\xc8\x00"),
+            Err(PpmError::InvalidValue)
+        );
+
+        // Sizes that do not fit in the file are rejected before allocating the pixels
+        assert_eq!(
+            Canvas::from_ppm(b"P6 999999999 999999999 255\n\x00\x00\x00"),
+            Err(PpmError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Canvas::from_ppm(format!("P3 {} 2 255 0 0 0", usize::MAX).as_bytes()),
+            Err(PpmError::UnexpectedEnd)
+        );
+    }
+
     #[test]
     fn save_ppm() {
         let mut p = Canvas::with_size(1080, 1920);
@@ -76,6 +143,9 @@ mod test_pic {
                 p.set_pixel_color((row, col), RGBAColor::from((red, (red + blue) / 2.0, blue)));
             }
         }
-        p.save_as_ppm("../test_outputs/grad.ppm".to_string());
+        let path = std::env::temp_dir().join(format!("image_test_grad_{}.ppm", std::process::id()));
+        p.save_as_ppm(path.to_string_lossy().into_owned());
+        assert!(std::fs::metadata(&path).unwrap().len() > 0);
+        std::fs::remove_file(&path).unwrap();
     }
 }
@@ -6,6 +6,13 @@ pub(crate) fn rgb_percentage_to_u8(f: f64) -> Option<u8> {
     Some((f * 256.0).round() as u8)
 }
 
+const EPSILON: f64 = 0.0001;
+
+/// Two channels are considered equal if they are closer than some epsilon
+pub(crate) fn approx(x: f64, y: f64) -> bool {
+    (x - y).abs() < EPSILON
+}
+
 #[cfg(test)]
 mod test_utils {
     use super::*;
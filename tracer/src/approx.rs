@@ -1,4 +1,4 @@
-const EPSILON: f64 = 0.0001;
+pub const EPSILON: f64 = 0.0001;
 
 /// Helper function to check equality of floating point integers
 ///
@@ -0,0 +1,343 @@
+use std::f64::consts;
+
+use image::color::Color;
+
+use crate::{
+    point::{coord::Coord, vector::Vector},
+    ray::Ray,
+    sampling::{orthonormal_basis, rng::Rng},
+};
+
+/// Number of points used on an area light when none is given
+pub const DEFAULT_AREA_SAMPLES: usize = 16;
+
+/// Surface of an area light
+#[derive(Debug, Clone, PartialEq)]
+pub enum AreaShape {
+    /// Parallelogram with a corner at `corner` and sides `u` and `v`
+    Rectangle {
+        corner: Coord,
+        u: Vector,
+        v: Vector,
+    },
+    Disk {
+        center: Coord,
+        normal: Vector,
+        radius: f64,
+    },
+    Sphere {
+        center: Coord,
+        radius: f64,
+    },
+}
+
+/// Light source with a size, which casts soft shadows
+///
+/// It works like a group of point lights spread over its surface that share its intensity, so it
+/// has no falloff either. Points that see only part of the light are in its penumbra. The path
+/// tracer can also find it with the rays it samples from the BSDFs, see `intersect` and `pdf`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AreaLight {
+    pub shape: AreaShape,
+    pub intensity: Color,
+    /// Number of points of the surface used to light each point, rounded down to a square grid
+    pub samples: usize,
+}
+
+impl AreaLight {
+    pub fn new(shape: AreaShape, intensity: Color) -> Self {
+        Self {
+            shape,
+            intensity,
+            samples: DEFAULT_AREA_SAMPLES,
+        }
+    }
+
+    pub fn rectangle(corner: Coord, u: Vector, v: Vector, intensity: Color) -> Self {
+        Self::new(AreaShape::Rectangle { corner, u, v }, intensity)
+    }
+
+    pub fn disk(center: Coord, normal: Vector, radius: f64, intensity: Color) -> Self {
+        let normal = normal.normalize();
+        Self::new(
+            AreaShape::Disk {
+                center,
+                normal,
+                radius,
+            },
+            intensity,
+        )
+    }
+
+    pub fn sphere(center: Coord, radius: f64, intensity: Color) -> Self {
+        Self::new(AreaShape::Sphere { center, radius }, intensity)
+    }
+
+    /// Points of the surface that light `from`, one in each cell of a grid over the surface at a
+    /// random place inside it (stratified sampling). The same point always gets the same ones
+    pub fn points(&self, from: &Coord) -> Vec<Coord> {
+        let side = self.grid_side();
+        let mut rng = Rng::for_point(from);
+
+        let mut points = Vec::with_capacity(side * side);
+        for row in 0..side {
+            for col in 0..side {
+                let s = (col as f64 + rng.next_f64()) / side as f64;
+                let t = (row as f64 + rng.next_f64()) / side as f64;
+                points.push(self.point_at(s, t, from));
+            }
+        }
+        points
+    }
+
+    /// Number of points returned by `points`
+    pub fn point_count(&self) -> usize {
+        self.grid_side() * self.grid_side()
+    }
+
+    fn grid_side(&self) -> usize {
+        (self.samples.max(1) as f64).sqrt() as usize
+    }
+
+    /// Distance along the ray to the surface, from either side. Spheres are hit on their near
+    /// side, or on the far one from inside
+    pub fn intersect(&self, ray: &Ray) -> Option<f64> {
+        match &self.shape {
+            AreaShape::Rectangle { corner, u, v } => {
+                let normal = u.cross_product(v);
+                let time = plane_time(ray, corner, &normal)?;
+                // Coordinates of the hit along the sides
+                let offset = corner.clone().vector_to(&ray.position_at(time));
+                let area2 = normal.dot(&normal);
+                let s = offset.cross_product(v).dot(&normal) / area2;
+                let t = u.cross_product(&offset).dot(&normal) / area2;
+                ((0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t)).then_some(time)
+            }
+            AreaShape::Disk {
+                center,
+                normal,
+                radius,
+            } => {
+                let time = plane_time(ray, center, normal)?;
+                let offset = center.clone().vector_to(&ray.position_at(time));
+                (offset.magnitude() <= *radius).then_some(time)
+            }
+            AreaShape::Sphere { center, radius } => {
+                let offset = center.clone().vector_to(&ray.origin);
+                let a = ray.dir.dot(&ray.dir);
+                let b = ray.dir.dot(&offset);
+                let c = offset.dot(&offset) - radius * radius;
+                let discriminant = b * b - a * c;
+                if a == 0.0 || discriminant < 0.0 {
+                    return None;
+                }
+                let root = discriminant.sqrt();
+                [(-b - root) / a, (-b + root) / a]
+                    .into_iter()
+                    .find(|&time| time > 0.0)
+            }
+        }
+    }
+
+    /// Density with which one of the `points` lights `from` along the direction, per unit of
+    /// solid angle. 0 when the direction misses the surface
+    pub fn pdf(&self, from: &Coord, direction: &Vector) -> f64 {
+        let direction = direction.clone().normalize();
+        let Some(distance) = self.intersect(&Ray::new(from.clone(), direction.clone())) else {
+            return 0.0;
+        };
+        let point = from.add_vector(&(direction.clone() * distance));
+
+        // The points are spread evenly over the area they are chosen from
+        let (area, normal) = match &self.shape {
+            AreaShape::Rectangle { u, v, .. } => {
+                let normal = u.cross_product(v);
+                (normal.magnitude(), normal.normalize())
+            }
+            AreaShape::Disk { normal, radius, .. } => {
+                (consts::PI * radius * radius, normal.clone().normalize())
+            }
+            AreaShape::Sphere { center, radius } => {
+                let distance = center.clone().vector_to(from).magnitude();
+                let cos_min = match distance > *radius {
+                    true => radius / distance,
+                    false => -1.0,
+                };
+                let cap = 2.0 * consts::PI * radius * radius * (1.0 - cos_min);
+                (cap, center.clone().vector_to(&point).normalize())
+            }
+        };
+        let cos = direction.dot(&normal).abs();
+        match cos > 0.0 && area > 0.0 {
+            true => distance * distance / (cos * area),
+            false => 0.0,
+        }
+    }
+
+    /// Point of the surface for some coordinates between 0 and 1. Spheres only use the part that
+    /// can be seen from `from`
+    fn point_at(&self, s: f64, t: f64, from: &Coord) -> Coord {
+        match &self.shape {
+            AreaShape::Rectangle { corner, u, v } => {
+                corner.add_vector(&(u.clone() * s + v.clone() * t))
+            }
+            AreaShape::Disk {
+                center,
+                normal,
+                radius,
+            } => {
+                let (x, y) = concentric_disk(s, t);
+                let (tangent, bitangent) = orthonormal_basis(normal);
+                center.add_vector(&((tangent * x + bitangent * y) * *radius))
+            }
+            AreaShape::Sphere { center, radius } => {
+                let to_from = center.clone().vector_to(from);
+                let distance = to_from.magnitude();
+                // The visible cap, or the whole sphere from inside of it
+                let (axis, cos_min) = match distance > *radius {
+                    true => (to_from.normalize(), radius / distance),
+                    false => (Vector::new(0.0, 0.0, 1.0), -1.0),
+                };
+                let z = cos_min + s * (1.0 - cos_min);
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let angle = 2.0 * consts::PI * t;
+                let (tangent, bitangent) = orthonormal_basis(&axis);
+                let direction =
+                    tangent * (r * angle.cos()) + bitangent * (r * angle.sin()) + axis * z;
+                center.add_vector(&(direction * *radius))
+            }
+        }
+    }
+}
+
+/// Time at which the ray crosses the plane through the point with the normal, if ahead of it
+fn plane_time(ray: &Ray, point: &Coord, normal: &Vector) -> Option<f64> {
+    let denominator = ray.dir.dot(normal);
+    if denominator == 0.0 {
+        return None;
+    }
+    let time = ray.origin.clone().vector_to(point).dot(normal) / denominator;
+    (time > 0.0).then_some(time)
+}
+
+/// Map a point of the unit square to the unit disk keeping the cells of a grid compact
+/// (Shirley and Chiu, 1997)
+fn concentric_disk(s: f64, t: f64) -> (f64, f64) {
+    let (a, b) = (2.0 * s - 1.0, 2.0 * t - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (r, angle) = match a.abs() > b.abs() {
+        true => (a, consts::FRAC_PI_4 * (b / a)),
+        false => (b, consts::FRAC_PI_2 - consts::FRAC_PI_4 * (a / b)),
+    };
+    (r * angle.cos(), r * angle.sin())
+}
+
+#[cfg(test)]
+mod test_area_light {
+    use super::*;
+    use crate::approx::approx;
+
+    #[test]
+    fn points_cover_the_rectangle() {
+        let mut light = AreaLight::rectangle(
+            Coord::new(-1.0, 2.0, -1.0),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 2.0),
+            Color::white(),
+        );
+        light.samples = 10;
+        let points = light.points(&Coord::new(0.0, 0.0, 0.0));
+        assert_eq!(points.len(), 9);
+
+        // One point in each cell of the 3x3 grid
+        for (i, point) in points.iter().enumerate() {
+            let (col, row) = ((i % 3) as f64, (i / 3) as f64);
+            assert!(approx(point.y, 2.0));
+            assert!(point.x >= -1.0 + col * 2.0 / 3.0 && point.x <= -1.0 + (col + 1.0) * 2.0 / 3.0);
+            assert!(point.z >= -1.0 + row * 2.0 / 3.0 && point.z <= -1.0 + (row + 1.0) * 2.0 / 3.0);
+        }
+        assert_eq!(points, light.points(&Coord::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn points_on_disk_and_sphere() {
+        let center = Coord::new(0.0, 3.0, 0.0);
+        let disk = AreaLight::disk(
+            center.clone(),
+            Vector::new(0.0, -2.0, 0.0),
+            0.5,
+            Color::white(),
+        );
+        for point in disk.points(&Coord::new(0.0, 0.0, 0.0)) {
+            let offset = center.clone().vector_to(&point);
+            assert!(approx(offset.y, 0.0) && offset.magnitude() <= 0.5 + 1e-9);
+        }
+
+        let sphere = AreaLight::sphere(center.clone(), 0.5, Color::white());
+        let from = Coord::new(0.0, 0.0, 0.0);
+        for point in sphere.points(&from) {
+            let offset = center.clone().vector_to(&point);
+            assert!(approx(offset.magnitude(), 0.5));
+            // On the side that faces the point
+            assert!(point.y < 3.0);
+        }
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let from = Coord::new(0.0, 0.0, 0.0);
+        let rectangle = AreaLight::rectangle(
+            Coord::new(-1.0, 2.0, -0.5),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.5, 0.5, 1.0),
+            Color::white(),
+        );
+        let disk = AreaLight::disk(
+            Coord::new(1.0, -1.0, 2.0),
+            Vector::new(-1.0, 1.0, -1.0),
+            1.5,
+            Color::white(),
+        );
+        for light in [rectangle, disk] {
+            // Mean of the density over uniform directions, times the solid angle of the sphere
+            let mut rng = Rng::new(5);
+            let samples = 200000;
+            let total: f64 = (0..samples)
+                .map(|_| {
+                    let z = 1.0 - 2.0 * rng.next_f64();
+                    let angle = 2.0 * consts::PI * rng.next_f64();
+                    let r = (1.0 - z * z).sqrt();
+                    let direction = Vector::new(r * angle.cos(), r * angle.sin(), z);
+                    light.pdf(&from, &direction) * 4.0 * consts::PI
+                })
+                .sum();
+            let total = total / samples as f64;
+            assert!((total - 1.0).abs() < 0.02, "{light:?} {total}");
+        }
+
+        // The inverse of the density of the points adds up to the solid angle they cover: the
+        // cone around the sphere, or every direction from inside of it
+        let cone = 2.0 * consts::PI * (1.0 - (1.0 - 1.0 / 9.0f64).sqrt());
+        for (center, solid_angle) in [(3.0, cone), (0.5, 4.0 * consts::PI)] {
+            let mut light = AreaLight::sphere(Coord::new(0.0, 0.0, center), 1.0, Color::white());
+            light.samples = 10000;
+            let points = light.points(&from);
+            let total: f64 = points
+                .iter()
+                .map(|point| {
+                    let to_point = from.clone().vector_to(point);
+                    let time = light.intersect(&Ray::new(from.clone(), to_point.clone()));
+                    assert!(approx(time.unwrap(), 1.0));
+                    1.0 / light.pdf(&from, &to_point)
+                })
+                .sum();
+            let total = total / points.len() as f64;
+            assert!(
+                (total / solid_angle - 1.0).abs() < 1e-3,
+                "{total} {solid_angle}"
+            );
+        }
+    }
+}
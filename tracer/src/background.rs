@@ -0,0 +1,97 @@
+use std::f64::consts;
+
+use environment::EnvironmentMap;
+use image::color::Color;
+use sky::Sky;
+
+use crate::{
+    point::vector::Vector,
+    sampling::{rng::Rng, uniform_cone},
+};
+
+pub mod environment;
+pub mod sky;
+
+/// Light coming from far away in every direction, seen by the rays that miss every shape
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    /// Same color in every direction
+    Color(Color),
+    /// Blend from the color straight down to the one straight up
+    Gradient { bottom: Color, top: Color },
+    /// Equirectangular image around the world, see `EnvironmentMap`
+    Map(EnvironmentMap),
+    /// Daylight sky for a position of the sun, see `Sky`
+    Sky(Sky),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Color(Color::black())
+    }
+}
+
+impl Background {
+    /// Light arriving from the direction
+    pub fn radiance(&self, direction: &Vector) -> Color {
+        match self {
+            Background::Color(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let t = (direction.clone().normalize().y + 1.0) / 2.0;
+                *bottom * (1.0 - t) + *top * t
+            }
+            Background::Map(map) => map.radiance(direction),
+            Background::Sky(sky) => sky.radiance(direction),
+        }
+    }
+
+    /// Random direction towards the background, with its density per unit of solid angle. Maps
+    /// choose their brighter pixels more often, the others every direction alike. `None` for
+    /// backgrounds that give no light
+    pub fn sample(&self, rng: &mut Rng) -> Option<(Vector, f64)> {
+        match self {
+            Background::Color(color) if *color == Color::black() => None,
+            Background::Map(map) => map.sample(rng),
+            _ => Some((
+                uniform_cone(&Vector::new(0.0, 1.0, 0.0), -1.0, rng),
+                1.0 / (4.0 * consts::PI),
+            )),
+        }
+    }
+
+    /// Density with which `sample` chooses the direction
+    pub fn pdf(&self, direction: &Vector) -> f64 {
+        match self {
+            Background::Color(color) if *color == Color::black() => 0.0,
+            Background::Map(map) => map.pdf(direction),
+            _ => 1.0 / (4.0 * consts::PI),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_background {
+    use super::*;
+
+    #[test]
+    fn constant_and_gradient() {
+        let background = Background::default();
+        assert_eq!(
+            background.radiance(&Vector::new(0.0, 1.0, 0.0)),
+            Color::black()
+        );
+        assert!(background.sample(&mut Rng::new(1)).is_none());
+
+        let background = Background::Gradient {
+            bottom: Color::new(1.0, 1.0, 1.0),
+            top: Color::new(0.2, 0.4, 1.0),
+        };
+        let radiance = |x, y, z| background.radiance(&Vector::new(x, y, z));
+        assert_eq!(radiance(0.0, 1.0, 0.0), Color::new(0.2, 0.4, 1.0));
+        assert_eq!(radiance(0.0, -2.0, 0.0), Color::white());
+        assert_eq!(radiance(1.0, 0.0, 1.0), Color::new(0.6, 0.7, 1.0));
+
+        let (direction, pdf) = background.sample(&mut Rng::new(1)).unwrap();
+        assert_eq!(pdf, background.pdf(&direction));
+    }
+}
@@ -0,0 +1,217 @@
+use std::{f64::consts, sync::Arc};
+
+use image::{
+    color::Color,
+    hdr::{HdrError, HdrImage},
+};
+
+use crate::{
+    point::vector::Vector,
+    sampling::{luminance, rng::Rng, Distribution},
+};
+
+/// HDR image wrapped around the world with the equirectangular (latitude-longitude) projection.
+/// The top row is straight up, and the center of the image looks towards +z
+///
+/// Directions are chosen in proportion to the luminance of their pixels, so a small bright sun in
+/// the image lights the scene without much noise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentMap {
+    /// Only set by `new`, as the distributions are built from it
+    image: Arc<HdrImage>,
+    /// Multiplies the colors of the image
+    pub intensity: f64,
+    /// Turn around the vertical axis, in radians
+    pub rotation: f64,
+    /// Chooses the rows, in proportion to the light of the solid angle they cover
+    rows: Distribution,
+    /// Chooses the pixels of each row
+    columns: Vec<Distribution>,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: HdrImage) -> Self {
+        let columns: Vec<Distribution> = (0..image.height)
+            .map(|row| {
+                let weights: Vec<f64> = (0..image.width)
+                    .map(|col| image.get_color_at((row, col)).map_or(0.0, luminance))
+                    .collect();
+                Distribution::new(&weights)
+            })
+            .collect();
+        // Rows near the poles cover a smaller part of the sphere
+        let rows: Vec<f64> = (0..image.height)
+            .map(|row| {
+                let sin = ((row as f64 + 0.5) / image.height as f64 * consts::PI).sin();
+                let light: f64 = (0..image.width)
+                    .map(|col| image.get_color_at((row, col)).map_or(0.0, luminance))
+                    .sum();
+                light * sin
+            })
+            .collect();
+
+        Self {
+            image: Arc::new(image),
+            intensity: 1.0,
+            rotation: 0.0,
+            rows: Distribution::new(&rows),
+            columns,
+        }
+    }
+
+    pub fn image(&self) -> &HdrImage {
+        &self.image
+    }
+
+    /// Read the map from the contents of a Radiance HDR file
+    pub fn from_hdr(bytes: &[u8]) -> Result<Self, HdrError> {
+        HdrImage::from_hdr(bytes).map(Self::new)
+    }
+
+    pub fn radiance(&self, direction: &Vector) -> Color {
+        match self.pixel(direction) {
+            Some((row, col, _)) => {
+                self.image
+                    .get_color_at((row, col))
+                    .copied()
+                    .unwrap_or_default()
+                    * self.intensity
+            }
+            None => Color::black(),
+        }
+    }
+
+    /// Random direction, chosen in proportion to the light that comes from it
+    pub fn sample(&self, rng: &mut Rng) -> Option<(Vector, f64)> {
+        if self.image.width == 0 || self.image.height == 0 {
+            return None;
+        }
+        let (v, row, row_pdf) = self.rows.sample(rng.next_f64());
+        let (u, _, col_pdf) = self.columns[row].sample(rng.next_f64());
+
+        let (theta, phi) = (v * consts::PI, (u - 0.5) * 2.0 * consts::PI + self.rotation);
+        let sin = theta.sin();
+        if sin <= 0.0 || row_pdf * col_pdf == 0.0 {
+            return None;
+        }
+        let direction = Vector::new(sin * phi.sin(), theta.cos(), sin * phi.cos());
+        Some((
+            direction,
+            row_pdf * col_pdf / (2.0 * consts::PI * consts::PI * sin),
+        ))
+    }
+
+    /// Density with which `sample` chooses the direction
+    pub fn pdf(&self, direction: &Vector) -> f64 {
+        let Some((row, col, sin)) = self.pixel(direction) else {
+            return 0.0;
+        };
+        if sin <= 0.0 {
+            return 0.0;
+        }
+        self.rows.pdf(row) * self.columns[row].pdf(col) / (2.0 * consts::PI * consts::PI * sin)
+    }
+
+    /// Row and column of the pixel in the direction, with the sine of its angle to the vertical
+    fn pixel(&self, direction: &Vector) -> Option<(usize, usize, f64)> {
+        let (width, height) = (self.image.width, self.image.height);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let direction = direction.clone().normalize();
+        let theta = direction.y.clamp(-1.0, 1.0).acos();
+        let phi = direction.x.atan2(direction.z) - self.rotation;
+        let u = (phi / (2.0 * consts::PI) + 0.5).rem_euclid(1.0);
+        let v = theta / consts::PI;
+
+        let row = ((v * height as f64) as usize).min(height - 1);
+        let col = ((u * width as f64) as usize).min(width - 1);
+        Some((row, col, theta.sin()))
+    }
+}
+
+#[cfg(test)]
+mod test_environment {
+    use super::*;
+
+    /// Dim sky with a small bright sun, up and towards +x
+    fn sky() -> EnvironmentMap {
+        let mut image = HdrImage::with_size(16, 32);
+        for row in 0..16 {
+            for col in 0..32 {
+                image.set_pixel_color((row, col), Color::new(0.2, 0.3, 0.5));
+            }
+        }
+        image.set_pixel_color((4, 24), Color::new(500.0, 450.0, 400.0));
+        EnvironmentMap::new(image)
+    }
+
+    #[test]
+    fn directions_of_the_pixels() {
+        let mut map = sky();
+        assert_eq!((map.image().width, map.image().height), (32, 16));
+        let radiance = |map: &EnvironmentMap, x, y, z| map.radiance(&Vector::new(x, y, z));
+        assert_eq!(radiance(&map, 0.0, 1.0, 0.0), Color::new(0.2, 0.3, 0.5));
+
+        // Center of the pixel of the sun
+        let (theta, phi) = (
+            4.5 / 16.0 * consts::PI,
+            (24.5 / 32.0 - 0.5) * 2.0 * consts::PI,
+        );
+        let sun = Vector::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            theta.sin() * phi.cos(),
+        );
+        assert!(sun.x > 0.0 && sun.y > 0.0);
+        assert_eq!(map.radiance(&sun), Color::new(500.0, 450.0, 400.0));
+
+        // Turning the map turns the sun
+        map.rotation = consts::FRAC_PI_2;
+        map.intensity = 2.0;
+        assert_eq!(map.radiance(&sun), Color::new(0.4, 0.6, 1.0));
+        let turned = Vector::new(sun.z, sun.y, -sun.x);
+        assert_eq!(map.radiance(&turned), Color::new(1000.0, 900.0, 800.0));
+    }
+
+    #[test]
+    fn samples_follow_the_light() {
+        let map = sky();
+        let mut rng = Rng::new(3);
+        let samples: Vec<(Vector, f64)> = (0..2000).filter_map(|_| map.sample(&mut rng)).collect();
+        assert_eq!(samples.len(), 2000);
+        for (direction, pdf) in &samples {
+            assert!((map.pdf(direction) / pdf - 1.0).abs() < 1e-6);
+        }
+
+        // Most of the light, and so of the samples, comes from the sun
+        let bright = samples
+            .iter()
+            .filter(|(direction, _)| map.radiance(direction).red > 1.0)
+            .count();
+        assert!(bright > 1500, "{bright}");
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let mut map = sky();
+        map.rotation = consts::FRAC_PI_2;
+        // The density times the sine is constant over each pixel, so summing it over a grid finer
+        // than the pixels integrates it exactly
+        let (rows, cols) = (64, 128);
+        let (d_theta, d_phi) = (consts::PI / rows as f64, 2.0 * consts::PI / cols as f64);
+        let total: f64 = (0..rows * cols)
+            .map(|i| {
+                let theta = ((i / cols) as f64 + 0.5) * d_theta;
+                let phi = ((i % cols) as f64 + 0.5) * d_phi;
+                let direction = Vector::new(
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                    theta.sin() * phi.cos(),
+                );
+                map.pdf(&direction) * theta.sin() * d_theta * d_phi
+            })
+            .sum();
+        assert!((total - 1.0).abs() < 1e-9, "{total}");
+    }
+}
@@ -0,0 +1,259 @@
+use std::f64::consts;
+
+use image::color::Color;
+
+use crate::{light::directional::DirectionalLight, point::vector::Vector};
+
+/// Illuminance of the sun at the top of the atmosphere, in klx
+const SOLAR_ILLUMINANCE: f64 = 128.0;
+
+/// Clear daytime sky, with the analytic model of Preetham, Shirley and Smits ("A Practical
+/// Analytic Model for Daylight", 1999)
+///
+/// It gives the light of the sky only, the sun itself comes from the matching directional light
+/// of `sun`. The model is made for the sky above the horizon, below it the ground reflects some
+/// of the light of the sky at the horizon.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sky {
+    /// Angle of the sun above the horizon, in radians. Past the horizon the sky stays as it is at
+    /// sunset and the sun gives no light
+    pub sun_elevation: f64,
+    /// Angle of the sun around the vertical axis, from +z towards +x, in radians
+    pub sun_azimuth: f64,
+    /// Haze in the air, from 2 for a very clear sky to 10 for a hazy one
+    pub turbidity: f64,
+    /// Scales the light of the sky and of the sun, which the model gives in kcd/m²
+    pub intensity: f64,
+    /// Fraction of the light of the horizon that the ground reflects
+    pub ground: Color,
+}
+
+impl Sky {
+    pub fn new(sun_elevation: f64, sun_azimuth: f64, turbidity: f64) -> Self {
+        Self {
+            sun_elevation,
+            sun_azimuth,
+            turbidity,
+            ..Default::default()
+        }
+    }
+
+    /// Unit vector pointing at the sun
+    pub fn sun_direction(&self) -> Vector {
+        let (elevation, azimuth) = (self.sun_elevation, self.sun_azimuth);
+        Vector::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos(),
+        )
+    }
+
+    /// Directional light of the sun, dimmed and reddened by the air it goes through
+    pub fn sun(&self) -> DirectionalLight {
+        let direction = self.sun_direction().negate();
+        if self.sun_elevation <= 0.0 {
+            return DirectionalLight::new(direction, Color::black());
+        }
+
+        // Relative length of the path through the air (Kasten and Young), and its transmittance
+        // for wavelengths in the red, green and blue, from Rayleigh and aerosol scattering
+        let zenith = 90.0 - self.sun_elevation.to_degrees();
+        let air_mass = 1.0 / (zenith.to_radians().cos() + 0.15 * (93.885 - zenith).powf(-1.253));
+        let beta = 0.04608 * self.turbidity.max(1.0) - 0.04586;
+        let transmittance = |wavelength: f64| {
+            let rayleigh = 0.008735 * wavelength.powf(-4.08);
+            let aerosol = beta * wavelength.powf(-1.3);
+            (-air_mass * (rayleigh + aerosol)).exp()
+        };
+
+        let intensity = SOLAR_ILLUMINANCE / consts::PI * self.intensity;
+        DirectionalLight::new(
+            direction,
+            Color::new(
+                transmittance(0.68),
+                transmittance(0.55),
+                transmittance(0.44),
+            ) * intensity,
+        )
+    }
+
+    /// Light of the sky coming from the direction
+    pub fn radiance(&self, direction: &Vector) -> Color {
+        let direction = direction.clone().normalize();
+        let sun = self.sun_direction();
+        // The sun cannot go below the horizon in the model
+        let sun_zenith = (consts::FRAC_PI_2 - self.sun_elevation).clamp(0.0, consts::FRAC_PI_2);
+        let model = Model::new(self.turbidity, sun_zenith);
+
+        let theta = direction.y.max(0.0).acos();
+        let gamma = direction.dot(&sun).clamp(-1.0, 1.0).acos();
+        let color = model.color(theta, gamma) * self.intensity;
+        match direction.y < 0.0 {
+            true => color * self.ground,
+            false => color,
+        }
+    }
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self {
+            sun_elevation: consts::FRAC_PI_4,
+            sun_azimuth: 0.0,
+            turbidity: 3.0,
+            intensity: 0.05,
+            ground: Color::new(0.3, 0.3, 0.3),
+        }
+    }
+}
+
+/// Coefficients of the model for a turbidity and a position of the sun
+struct Model {
+    /// Perez coefficients of the luminance and the two chromaticities
+    luminance: [f64; 5],
+    x: [f64; 5],
+    y: [f64; 5],
+    /// Luminance and chromaticities straight up
+    zenith: (f64, f64, f64),
+    sun_zenith: f64,
+}
+
+impl Model {
+    fn new(turbidity: f64, sun_zenith: f64) -> Self {
+        let t = turbidity.clamp(1.0, 20.0);
+        let luminance = [
+            0.1787 * t - 1.4630,
+            -0.3554 * t + 0.4275,
+            -0.0227 * t + 5.3251,
+            0.1206 * t - 2.5771,
+            -0.0670 * t + 0.3703,
+        ];
+        let x = [
+            -0.0193 * t - 0.2592,
+            -0.0665 * t + 0.0008,
+            -0.0004 * t + 0.2125,
+            -0.0641 * t - 0.8989,
+            -0.0033 * t + 0.0452,
+        ];
+        let y = [
+            -0.0167 * t - 0.2608,
+            -0.0950 * t + 0.0092,
+            -0.0079 * t + 0.2102,
+            -0.0441 * t - 1.6537,
+            -0.0109 * t + 0.0529,
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (consts::PI - 2.0 * sun_zenith);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let s = [sun_zenith.powi(3), sun_zenith.powi(2), sun_zenith, 1.0];
+        let polynomial = |coefficients: [[f64; 4]; 3]| {
+            let row = |i: usize| (0..4).map(|j| coefficients[i][j] * s[j]).sum::<f64>();
+            t * t * row(0) + t * row(1) + row(2)
+        };
+        let zenith_x = polynomial([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = polynomial([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        Self {
+            luminance,
+            x,
+            y,
+            zenith: (zenith_luminance.max(0.0), zenith_x, zenith_y),
+            sun_zenith,
+        }
+    }
+
+    /// Linear sRGB color of the direction at angle `theta` from the zenith and `gamma` from the
+    /// sun
+    fn color(&self, theta: f64, gamma: f64) -> Color {
+        // Distribution of the sky relative to its value at the zenith
+        let relative = |[a, b, c, d, e]: [f64; 5]| {
+            let perez = |theta: f64, gamma: f64| {
+                (1.0 + a * (b / theta.cos().max(0.01)).exp())
+                    * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+            };
+            perez(theta, gamma) / perez(0.0, self.sun_zenith)
+        };
+        let (zenith_luminance, zenith_x, zenith_y) = self.zenith;
+        let luminance = zenith_luminance * relative(self.luminance);
+        let x = zenith_x * relative(self.x);
+        let y = zenith_y * relative(self.y);
+        if y <= 0.0 {
+            return Color::black();
+        }
+
+        let (cx, cy, cz) = (x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+        Color::new(
+            (3.2406 * cx - 1.5372 * cy - 0.4986 * cz).max(0.0),
+            (-0.9689 * cx + 1.8758 * cy + 0.0415 * cz).max(0.0),
+            (0.0557 * cx - 0.2040 * cy + 1.0570 * cz).max(0.0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_sky {
+    use super::*;
+    use crate::{light::Light, point::coord::Coord};
+
+    fn up() -> Vector {
+        Vector::new(0.0, 1.0, 0.0)
+    }
+
+    #[test]
+    fn blue_sky_brighter_around_the_sun() {
+        let sky = Sky::new(0.5, 1.0, 2.5);
+        let zenith = sky.radiance(&up());
+        assert!(
+            zenith.blue > zenith.green && zenith.green > zenith.red,
+            "{zenith:?}"
+        );
+
+        // Close to the sun, and in the opposite side of the sky
+        let sun = sky.sun_direction();
+        let near = sky.radiance(&Vector::new(sun.x, sun.y + 0.1, sun.z));
+        let away = sky.radiance(&Vector::new(-sun.x, sun.y, -sun.z));
+        assert!(near.green > 2.0 * away.green, "{near:?} {away:?}");
+
+        // The ground reflects some of the horizon
+        let horizon = sky.radiance(&Vector::new(0.0, 0.0, -1.0));
+        let ground = sky.radiance(&Vector::new(0.0, -1.0, -1.0));
+        assert!(
+            ground.red > 0.0 && ground.red < horizon.red,
+            "{ground:?} {horizon:?}"
+        );
+    }
+
+    #[test]
+    fn haze_whitens_the_sky() {
+        let saturation = |turbidity| {
+            let color = Sky::new(0.8, 0.0, turbidity).radiance(&up());
+            color.blue / color.red
+        };
+        assert!(saturation(2.0) > saturation(5.0));
+        assert!(saturation(5.0) > saturation(10.0));
+    }
+
+    #[test]
+    fn sun_matches_the_sky() {
+        let sky = Sky::new(consts::FRAC_PI_6, consts::FRAC_PI_2, 3.0);
+        let sun = sky.sun();
+        assert_eq!(sun.direction, Vector::new(-(3f64.sqrt()) / 2.0, -0.5, 0.0));
+        let samples = sun.samples(&Coord::new(0.0, 0.0, 0.0));
+        assert_eq!(samples[0].direction, sky.sun_direction());
+
+        // The sun is dimmer and redder when it is low, and gone under the horizon
+        let intensity = |elevation: f64| Sky::new(elevation, 0.0, 3.0).sun().intensity;
+        let (noon, evening) = (intensity(1.2), intensity(0.1));
+        assert!(evening.green < noon.green);
+        assert!(evening.red / evening.blue > noon.red / noon.blue);
+        assert_eq!(intensity(-0.1), Color::black());
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{matrix::square4::Matrix4x4, point::coord::Coord, ray::Ray};
+
+/// Axis aligned box that contains some shape, used to quickly discard rays that cannot hit it
+///
+/// A box with `min` greater than `max` in some axis is empty, and a box that goes to infinity
+/// (for planes and open cylinders) contains the whole space once it is transformed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundingBox {
+    pub min: Coord,
+    pub max: Coord,
+}
+
+impl Default for BoundingBox {
+    /// An empty box, adding points or other boxes to it will make it grow
+    fn default() -> Self {
+        Self {
+            min: Coord::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Coord::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+}
+
+impl BoundingBox {
+    pub fn new(min: Coord, max: Coord) -> Self {
+        Self { min, max }
+    }
+
+    /// Box that contains the whole space
+    pub fn infinite() -> Self {
+        Self {
+            min: Coord::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            max: Coord::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        }
+    }
+
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Coord>) -> Self {
+        let mut bounds = Self::default();
+        points.into_iter().for_each(|point| bounds.add_point(point));
+        bounds
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [
+            self.min.x, self.min.y, self.min.z, self.max.x, self.max.y, self.max.z,
+        ]
+        .iter()
+        .all(|value| value.is_finite())
+    }
+
+    pub fn add_point(&mut self, point: &Coord) {
+        self.min = Coord::new(
+            self.min.x.min(point.x),
+            self.min.y.min(point.y),
+            self.min.z.min(point.z),
+        );
+        self.max = Coord::new(
+            self.max.x.max(point.x),
+            self.max.y.max(point.y),
+            self.max.z.max(point.z),
+        );
+    }
+
+    /// Smallest box that contains both boxes
+    pub fn merge(&self, other: &BoundingBox) -> Self {
+        let mut bounds = self.clone();
+        bounds.add_point(&other.min);
+        bounds.add_point(&other.max);
+        bounds
+    }
+
+    /// Same box, grown by `by` in every direction
+    pub fn expand(&self, by: f64) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+        Self {
+            min: Coord::new(self.min.x - by, self.min.y - by, self.min.z - by),
+            max: Coord::new(self.max.x + by, self.max.y + by, self.max.z + by),
+        }
+    }
+
+    /// Box that contains this one after transforming it, found by transforming its 8 corners
+    pub fn transform(&self, matrix: &Matrix4x4) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+        // An infinite corner would turn into NaN, so infinite boxes stay infinite
+        if !self.is_finite() {
+            return Self::infinite();
+        }
+
+        let corners = [
+            (self.min.x, self.min.y, self.min.z),
+            (self.min.x, self.min.y, self.max.z),
+            (self.min.x, self.max.y, self.min.z),
+            (self.min.x, self.max.y, self.max.z),
+            (self.max.x, self.min.y, self.min.z),
+            (self.max.x, self.min.y, self.max.z),
+            (self.max.x, self.max.y, self.min.z),
+            (self.max.x, self.max.y, self.max.z),
+        ]
+        .map(|corner| *matrix * Coord::from(corner));
+        Self::from_points(&corners)
+    }
+
+    pub fn centroid(&self) -> Coord {
+        Coord::new(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+            (self.min.z + self.max.z) / 2.0,
+        )
+    }
+
+    /// Distance from the point to the nearest point of the box, 0 inside of it
+    pub fn distance(&self, point: &Coord) -> f64 {
+        if self.is_empty() {
+            return f64::INFINITY;
+        }
+        let outside = |value: f64, min: f64, max: f64| (min - value).max(value - max).max(0.0);
+        let (dx, dy, dz) = (
+            outside(point.x, self.min.x, self.max.x),
+            outside(point.y, self.min.y, self.max.y),
+            outside(point.z, self.min.z, self.max.z),
+        );
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let (dx, dy, dz) = (
+            self.max.x - self.min.x,
+            self.max.y - self.min.y,
+            self.max.z - self.min.z,
+        );
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    /// Whether the line of the ray goes through the box, at any time (even negative ones), as
+    /// every intersection of the ray is needed to know which shapes it is inside of
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+
+        let axes = [
+            (ray.origin.x, ray.dir.x, self.min.x, self.max.x),
+            (ray.origin.y, ray.dir.y, self.min.y, self.max.y),
+            (ray.origin.z, ray.dir.z, self.min.z, self.max.z),
+        ];
+
+        let mut tmin = f64::NEG_INFINITY;
+        let mut tmax = f64::INFINITY;
+        for (origin, dir, min, max) in axes {
+            // A ray parallel to the slab is either always inside it, or never
+            if dir == 0.0 {
+                if origin < min || origin > max {
+                    return false;
+                }
+                continue;
+            }
+
+            let t1 = (min - origin) / dir;
+            let t2 = (max - origin) / dir;
+            tmin = tmin.max(t1.min(t2));
+            tmax = tmax.min(t1.max(t2));
+        }
+
+        tmin <= tmax
+    }
+}
+
+#[cfg(test)]
+mod test_bounding_box {
+    use super::*;
+    use crate::transformations::{Axis, TransformationMatrix};
+    use std::f64::consts;
+
+    #[test]
+    fn grow_with_points_and_boxes() {
+        let mut bounds = BoundingBox::default();
+        assert!(bounds.is_empty());
+
+        bounds.add_point(&Coord::from((-5, 2, 0)));
+        bounds.add_point(&Coord::from((7, 0, -3)));
+        assert_eq!(bounds.min, Coord::from((-5, 0, -3)));
+        assert_eq!(bounds.max, Coord::from((7, 2, 0)));
+
+        let other = BoundingBox::new(Coord::from((8, -7, -2)), Coord::from((14, 4, 8)));
+        let merged = bounds.merge(&other);
+        assert_eq!(merged.min, Coord::from((-5, -7, -3)));
+        assert_eq!(merged.max, Coord::from((14, 4, 8)));
+        assert_eq!(merged.centroid(), Coord::from((4.5, -1.5, 2.5)));
+    }
+
+    #[test]
+    fn transformed_box() {
+        let mut transformation = TransformationMatrix::default();
+        transformation
+            .rotate(Axis::Y, consts::FRAC_PI_4)
+            .rotate(Axis::X, consts::FRAC_PI_4);
+        let bounds = BoundingBox::new(Coord::from((-1, -1, -1)), Coord::from((1, 1, 1)))
+            .transform(&transformation.matrix);
+        assert_eq!(bounds.min, Coord::new(-consts::SQRT_2, -1.7071, -1.7071));
+        assert_eq!(bounds.max, Coord::new(consts::SQRT_2, 1.7071, 1.7071));
+
+        let infinite = BoundingBox::new(
+            Coord::new(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            Coord::new(f64::INFINITY, 0.0, f64::INFINITY),
+        );
+        assert!(!infinite.transform(&transformation.matrix).is_finite());
+    }
+
+    #[test]
+    fn surface_area() {
+        let bounds = BoundingBox::new(Coord::from((0, 0, 0)), Coord::from((1, 2, 3)));
+        assert_eq!(bounds.surface_area(), 22.0);
+        assert_eq!(BoundingBox::default().surface_area(), 0.0);
+    }
+
+    #[test]
+    fn distance_to_point() {
+        let bounds = BoundingBox::new(Coord::from((0, 0, 0)), Coord::from((1, 2, 3)));
+        assert_eq!(bounds.distance(&Coord::from((0.5, 1.0, 1.0))), 0.0);
+        assert_eq!(bounds.distance(&Coord::from((0, 5, 1))), 3.0);
+        assert_eq!(bounds.distance(&Coord::from((4, 6, 3))), 5.0);
+        assert_eq!(
+            BoundingBox::default().distance(&Coord::from((0, 0, 0))),
+            f64::INFINITY
+        );
+    }
+
+    #[test]
+    fn ray_intersects_box() {
+        let bounds = BoundingBox::new(Coord::from((5, -2, 0)), Coord::from((11, 4, 7)));
+        let cases = [
+            ((15, 1, 2), (-1, 0, 0), true),
+            ((-5, -1, 4), (1, 0, 0), true),
+            ((7, 6, 5), (0, -1, 0), true),
+            ((9, -5, 6), (0, 1, 0), true),
+            ((8, 2, 12), (0, 0, -1), true),
+            ((6, 0, -5), (0, 0, 1), true),
+            ((8, 1, 3), (0, 0, 1), true),
+            ((9, -1, -8), (2, 4, 6), false),
+            ((8, 3, -4), (6, 2, 4), false),
+            ((9, -1, -2), (4, 6, 2), false),
+            ((4, 0, 9), (0, 0, -1), false),
+            ((8, 6, -1), (0, -1, 0), false),
+            ((12, 5, 4), (-1, 0, 0), false),
+        ];
+
+        for (origin, dir, expected) in cases {
+            let ray = Ray::from((origin, dir));
+            assert_eq!(bounds.intersects(&ray), expected, "{origin:?} {dir:?}");
+        }
+
+        // Boxes are hit even when they are behind the ray
+        let ray = Ray::from(((8, 1, 20), (0, 0, 1)));
+        assert!(bounds.intersects(&ray));
+
+        assert!(BoundingBox::infinite().intersects(&ray));
+        assert!(!BoundingBox::default().intersects(&ray));
+    }
+}
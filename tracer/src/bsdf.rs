@@ -0,0 +1,238 @@
+use conductor::Conductor;
+use dielectric::Dielectric;
+use enum_dispatch::enum_dispatch;
+use image::color::Color;
+use lambertian::Lambertian;
+use principled::Principled;
+
+use crate::{
+    point::vector::Vector,
+    sampling::{orthonormal_basis, rng::Rng},
+};
+
+pub mod conductor;
+pub mod dielectric;
+pub mod lambertian;
+pub mod microfacet;
+pub mod principled;
+
+/// Physically based model of how a surface scatters light (bidirectional scattering distribution
+/// function), used by the path tracer
+///
+/// Directions are unit vectors in the local shading frame (see `Frame`), where the outward normal
+/// of the surface is +z, and both point away from the surface: `wo` towards the viewer and `wi`
+/// towards where the light comes from.
+#[derive(Debug, Clone, PartialEq)]
+#[enum_dispatch(Bsdf)]
+pub enum Bsdfs {
+    Lambertian(Lambertian),
+    Conductor(Conductor),
+    Dielectric(Dielectric),
+    Principled(Principled),
+}
+
+#[enum_dispatch]
+pub trait Bsdf {
+    /// Fraction of the light arriving from `wi` that leaves towards `wo`, per unit of solid angle.
+    /// It does not include the cosine with the normal
+    fn evaluate(&self, wo: &Vector, wi: &Vector) -> Color;
+
+    /// Random incoming direction for `wo`, chosen roughly in proportion to the light it brings.
+    /// `None` when the sampled direction carries no light
+    fn sample(&self, wo: &Vector, rng: &mut Rng) -> Option<BsdfSample>;
+
+    /// Probability density with which `sample` chooses `wi`, per unit of solid angle
+    fn pdf(&self, wo: &Vector, wi: &Vector) -> f64;
+}
+
+/// Direction chosen by `Bsdf::sample`, with the value and density of the BSDF for it
+#[derive(Debug, Clone)]
+pub struct BsdfSample {
+    pub wi: Vector,
+    pub value: Color,
+    pub pdf: f64,
+}
+
+impl BsdfSample {
+    /// How much of the light from `wi` reaches `wo`, divided by the probability of choosing it.
+    /// This is what the throughput of a path gets multiplied by
+    pub fn weight(&self) -> Color {
+        self.value * (self.wi.z.abs() / self.pdf)
+    }
+}
+
+/// Orthonormal basis around the normal of a surface, to move directions in and out of the local
+/// shading frame of the BSDFs
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub tangent: Vector,
+    pub bitangent: Vector,
+    pub normal: Vector,
+}
+
+impl Frame {
+    pub fn new(normal: &Vector) -> Self {
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        Self {
+            tangent,
+            bitangent,
+            normal: normal.clone(),
+        }
+    }
+
+    pub fn to_local(&self, v: &Vector) -> Vector {
+        Vector::new(
+            v.dot(&self.tangent),
+            v.dot(&self.bitangent),
+            v.dot(&self.normal),
+        )
+    }
+
+    pub fn to_world(&self, v: &Vector) -> Vector {
+        self.tangent.clone() * v.x + self.bitangent.clone() * v.y + self.normal.clone() * v.z
+    }
+}
+
+/// Whether both directions are on the same side of the surface
+fn same_hemisphere(wo: &Vector, wi: &Vector) -> bool {
+    wo.z * wi.z > 0.0
+}
+
+#[cfg(test)]
+mod test_bsdf {
+    use super::*;
+    use std::f64::consts;
+
+    /// Every model with a few different parameters
+    fn models() -> Vec<Bsdfs> {
+        let mut models: Vec<Bsdfs> = vec![Lambertian::new(Color::white()).into()];
+        for roughness in [0.05, 0.3, 0.7, 1.0] {
+            models.push(Conductor::gold(roughness).into());
+            models.push(Conductor::copper(roughness).into());
+            models.push(Conductor::aluminium(roughness).into());
+            models.push(Dielectric::new(1.5, roughness).into());
+            models.push(Principled::new(Color::white(), 0.0, roughness).into());
+            models.push(Principled::new(Color::white(), 0.5, roughness).into());
+            models.push(Principled::new(Color::white(), 1.0, roughness).into());
+        }
+        models
+    }
+
+    /// Outgoing directions from head on to grazing, outside and inside the surface
+    fn views() -> Vec<Vector> {
+        [1.0, 0.7, 0.3, 0.05, -0.8]
+            .map(|cos: f64| Vector::new((1.0 - cos * cos).sqrt(), 0.0, cos))
+            .to_vec()
+    }
+
+    /// Fraction of the light arriving at the surface from every direction that leaves towards
+    /// `wo`, estimated with the samples of the BSDF
+    fn albedo(bsdf: &Bsdfs, wo: &Vector, samples: usize) -> Color {
+        let mut rng = Rng::new(3);
+        let sum = (0..samples)
+            .filter_map(|_| bsdf.sample(wo, &mut rng))
+            .fold(Color::black(), |sum, sample| sum + sample.weight());
+        sum * (1.0 / samples as f64)
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let frame = Frame::new(&Vector::new(1.0, -2.0, 0.5).normalize());
+        let v = Vector::new(0.3, 0.2, -0.9);
+        assert_eq!(frame.to_world(&frame.to_local(&v)), v);
+        assert_eq!(frame.to_local(&frame.normal), Vector::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn white_furnace() {
+        // Under the same light from every direction, no surface can reflect more than it gets
+        for bsdf in models() {
+            for wo in views() {
+                let albedo = albedo(&bsdf, &wo, 20000);
+                for channel in [albedo.red, albedo.green, albedo.blue] {
+                    assert!(channel <= 1.01, "{bsdf:?} {wo:?} {albedo:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lossless_surfaces_keep_all_the_light() {
+        // A white diffuse surface and smooth glass only send the light somewhere else
+        let models: [Bsdfs; 2] = [
+            Lambertian::new(Color::white()).into(),
+            Dielectric::new(1.5, 0.0).into(),
+        ];
+        for bsdf in models {
+            for wo in views() {
+                let albedo = albedo(&bsdf, &wo, 20000);
+                assert!(
+                    (albedo.red - 1.0).abs() < 0.01,
+                    "{bsdf:?} {wo:?} {albedo:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn samples_agree_with_evaluate_and_pdf() {
+        let mut rng = Rng::new(5);
+        for bsdf in models() {
+            for wo in views() {
+                for _ in 0..50 {
+                    let Some(sample) = bsdf.sample(&wo, &mut rng) else {
+                        continue;
+                    };
+                    let pdf = bsdf.pdf(&wo, &sample.wi);
+                    assert!(
+                        (pdf - sample.pdf).abs() <= 1e-6 * pdf.max(1.0),
+                        "{bsdf:?} {pdf} {sample:?}"
+                    );
+                    let value = bsdf.evaluate(&wo, &sample.wi);
+                    assert!(
+                        (value.red - sample.value.red).abs() <= 1e-6 * value.red.max(1.0),
+                        "{bsdf:?} {value:?} {sample:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        // Rough enough for uniform directions to find the whole lobe. Samples that would leave
+        // through the wrong side are dropped, so the total can be a bit under 1
+        let mut rng = Rng::new(11);
+        let models: [Bsdfs; 4] = [
+            Lambertian::new(Color::white()).into(),
+            Conductor::gold(0.4).into(),
+            Dielectric::new(1.5, 0.6).into(),
+            Principled::new(Color::new(0.8, 0.3, 0.2), 0.3, 0.6).into(),
+        ];
+        let samples = 100000;
+        for bsdf in models {
+            let wo = Vector::new(0.28, 0.0, 0.96);
+            let total: f64 = (0..samples)
+                .map(|_| {
+                    // Uniform direction on the sphere
+                    let z = 1.0 - 2.0 * rng.next_f64();
+                    let angle = 2.0 * consts::PI * rng.next_f64();
+                    let r = (1.0 - z * z).sqrt();
+                    let wi = Vector::new(r * angle.cos(), r * angle.sin(), z);
+                    bsdf.pdf(&wo, &wi) * 4.0 * consts::PI
+                })
+                .sum::<f64>()
+                / samples as f64;
+            assert!(total > 0.9 && total < 1.03, "{bsdf:?} {total}");
+        }
+    }
+
+    #[test]
+    fn metals_have_their_color() {
+        let wo = Vector::new(0.0, 0.0, 1.0);
+        let gold = albedo(&Conductor::gold(0.0).into(), &wo, 100);
+        assert!(gold.red > gold.green && gold.green > gold.blue, "{gold:?}");
+        let aluminium = albedo(&Conductor::aluminium(0.0).into(), &wo, 100);
+        assert!(aluminium.red > 0.9 && aluminium.green > 0.9 && aluminium.blue > 0.9);
+    }
+}
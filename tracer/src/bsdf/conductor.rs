@@ -0,0 +1,97 @@
+use image::color::Color;
+
+use super::{
+    microfacet::{fresnel_conductor, half_vector, reflect, Ggx},
+    Bsdf, BsdfSample,
+};
+use crate::{point::vector::Vector, sampling::rng::Rng};
+
+/// Rough metal, made of tiny mirrors with a GGX distribution (Cook-Torrance model)
+///
+/// Metals absorb the light that enters them, so their color comes only from the Fresnel
+/// reflectance, given by the complex refractive index `eta + i k` of each channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color,
+    /// 0 is a polished mirror, 1 is very rough
+    pub roughness: f64,
+}
+
+impl Conductor {
+    pub fn new(eta: Color, k: Color, roughness: f64) -> Self {
+        Self { eta, k, roughness }
+    }
+
+    pub fn gold(roughness: f64) -> Self {
+        Self::new(
+            Color::new(0.143, 0.374, 1.442),
+            Color::new(3.983, 2.385, 1.603),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        Self::new(
+            Color::new(0.200, 0.924, 1.102),
+            Color::new(3.912, 2.452, 2.142),
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: f64) -> Self {
+        Self::new(
+            Color::new(1.657, 0.880, 0.521),
+            Color::new(9.224, 6.270, 4.837),
+            roughness,
+        )
+    }
+
+    fn fresnel(&self, cos_i: f64) -> Color {
+        Color::new(
+            fresnel_conductor(cos_i, self.eta.red, self.k.red),
+            fresnel_conductor(cos_i, self.eta.green, self.k.green),
+            fresnel_conductor(cos_i, self.eta.blue, self.k.blue),
+        )
+    }
+}
+
+impl Default for Conductor {
+    fn default() -> Self {
+        Self::aluminium(0.3)
+    }
+}
+
+impl Bsdf for Conductor {
+    fn evaluate(&self, wo: &Vector, wi: &Vector) -> Color {
+        let Some(m) = half_vector(wo, wi) else {
+            return Color::black();
+        };
+        let ggx = Ggx::from_roughness(self.roughness);
+        let specular = ggx.d(&m) * ggx.g(wo, wi) / (4.0 * wo.z * wi.z).abs();
+        self.fresnel(wo.dot(&m).abs()) * specular
+    }
+
+    fn sample(&self, wo: &Vector, rng: &mut Rng) -> Option<BsdfSample> {
+        let ggx = Ggx::from_roughness(self.roughness);
+        let m = ggx.sample_visible(wo, rng);
+        let wi = reflect(wo, &m);
+        let pdf = self.pdf(wo, &wi);
+        if pdf == 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            value: self.evaluate(wo, &wi),
+            wi,
+            pdf,
+        })
+    }
+
+    fn pdf(&self, wo: &Vector, wi: &Vector) -> f64 {
+        let Some(m) = half_vector(wo, wi) else {
+            return 0.0;
+        };
+        let ggx = Ggx::from_roughness(self.roughness);
+        ggx.visible_pdf(wo, &m) / (4.0 * wo.dot(&m).abs())
+    }
+}
@@ -0,0 +1,120 @@
+use image::color::Color;
+
+use super::{
+    microfacet::{fresnel_dielectric, reflect, refract, Ggx},
+    same_hemisphere, Bsdf, BsdfSample,
+};
+use crate::{point::vector::Vector, sampling::rng::Rng};
+
+/// Rough glass, with GGX microfacets that both reflect and refract the light (Walter et al., 2007)
+///
+/// The outward normal points to the outside of the shape, so `wo.z < 0` means the path is inside.
+/// The change in radiance when light is squeezed into a denser medium is left out, so the
+/// brightness of what is seen through the glass does not depend on the side it is seen from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dielectric {
+    /// Refractive index of the inside over the one of the outside
+    pub ior: f64,
+    /// 0 is polished, 1 is very frosted
+    pub roughness: f64,
+}
+
+impl Dielectric {
+    pub fn new(ior: f64, roughness: f64) -> Self {
+        Self { ior, roughness }
+    }
+
+    /// Microfacet normal between the two directions, on the side of the outward normal, and the
+    /// ratio of refractive indices used for it. `None` for the directions no facet connects
+    fn half_vector(&self, wo: &Vector, wi: &Vector) -> Option<(Vector, f64)> {
+        if wo.z == 0.0 || wi.z == 0.0 {
+            return None;
+        }
+        let eta = match same_hemisphere(wo, wi) {
+            true => 1.0,
+            false if wo.z > 0.0 => self.ior,
+            false => 1.0 / self.ior,
+        };
+        let half = wi.clone() * eta + wo.clone();
+        if half.magnitude() == 0.0 {
+            return None;
+        }
+        let half = half.normalize();
+        // Directions that are too close to grazing for a facet to connect
+        if !same_hemisphere(wo, wi) && (wi.dot(&half) + wo.dot(&half) / eta) == 0.0 {
+            return None;
+        }
+        let half = match half.z < 0.0 {
+            true => half.negate(),
+            false => half,
+        };
+        // Facets seen from behind do not scatter anything
+        if half.dot(wi) * wi.z < 0.0 || half.dot(wo) * wo.z < 0.0 {
+            return None;
+        }
+        Some((half, eta))
+    }
+}
+
+impl Default for Dielectric {
+    fn default() -> Self {
+        Self::new(1.5, 0.0)
+    }
+}
+
+impl Bsdf for Dielectric {
+    fn evaluate(&self, wo: &Vector, wi: &Vector) -> Color {
+        let Some((m, eta)) = self.half_vector(wo, wi) else {
+            return Color::black();
+        };
+        let ggx = Ggx::from_roughness(self.roughness);
+        let reflected = fresnel_dielectric(wo.dot(&m), self.ior);
+        let value = match same_hemisphere(wo, wi) {
+            true => ggx.d(&m) * ggx.g(wo, wi) * reflected / (4.0 * wo.z * wi.z).abs(),
+            false => {
+                let denom = (wi.dot(&m) + wo.dot(&m) / eta).powi(2) * wi.z * wo.z;
+                ggx.d(&m)
+                    * ggx.g(wo, wi)
+                    * (1.0 - reflected)
+                    * (wi.dot(&m) * wo.dot(&m) / denom).abs()
+            }
+        };
+        Color::new(value, value, value)
+    }
+
+    fn sample(&self, wo: &Vector, rng: &mut Rng) -> Option<BsdfSample> {
+        let ggx = Ggx::from_roughness(self.roughness);
+        let m = ggx.sample_visible(wo, rng);
+        let reflected = fresnel_dielectric(wo.dot(&m), self.ior);
+        let wi = match rng.next_f64() < reflected {
+            true => Some(reflect(wo, &m)).filter(|wi| same_hemisphere(wo, wi)),
+            false => refract(wo, &m, self.ior)
+                .map(|(wi, _)| wi)
+                .filter(|wi| wo.z * wi.z < 0.0),
+        }?;
+        let pdf = self.pdf(wo, &wi);
+        if pdf == 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            value: self.evaluate(wo, &wi),
+            wi,
+            pdf,
+        })
+    }
+
+    fn pdf(&self, wo: &Vector, wi: &Vector) -> f64 {
+        let Some((m, eta)) = self.half_vector(wo, wi) else {
+            return 0.0;
+        };
+        let ggx = Ggx::from_roughness(self.roughness);
+        let reflected = fresnel_dielectric(wo.dot(&m), self.ior);
+        match same_hemisphere(wo, wi) {
+            true => ggx.visible_pdf(wo, &m) / (4.0 * wo.dot(&m).abs()) * reflected,
+            false => {
+                let denom = (wi.dot(&m) + wo.dot(&m) / eta).powi(2);
+                ggx.visible_pdf(wo, &m) * wi.dot(&m).abs() / denom * (1.0 - reflected)
+            }
+        }
+    }
+}
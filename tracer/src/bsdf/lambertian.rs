@@ -0,0 +1,56 @@
+use std::f64::consts;
+
+use image::color::Color;
+
+use super::{same_hemisphere, Bsdf, BsdfSample};
+use crate::{point::vector::Vector, sampling::cosine_hemisphere, sampling::rng::Rng};
+
+/// Perfectly matte surface, which scatters the light equally in every direction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lambertian {
+    /// Fraction of the light that is reflected
+    pub albedo: Color,
+}
+
+impl Lambertian {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Default for Lambertian {
+    fn default() -> Self {
+        Self::new(Color::white())
+    }
+}
+
+impl Bsdf for Lambertian {
+    fn evaluate(&self, wo: &Vector, wi: &Vector) -> Color {
+        match same_hemisphere(wo, wi) {
+            true => self.albedo * consts::FRAC_1_PI,
+            false => Color::black(),
+        }
+    }
+
+    fn sample(&self, wo: &Vector, rng: &mut Rng) -> Option<BsdfSample> {
+        // Both sides of the surface are matte
+        let normal = Vector::new(0.0, 0.0, 1.0f64.copysign(wo.z));
+        let wi = cosine_hemisphere(&normal, rng);
+        let pdf = self.pdf(wo, &wi);
+        if pdf == 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            value: self.evaluate(wo, &wi),
+            wi,
+            pdf,
+        })
+    }
+
+    fn pdf(&self, wo: &Vector, wi: &Vector) -> f64 {
+        match same_hemisphere(wo, wi) {
+            true => wi.z.abs() * consts::FRAC_1_PI,
+            false => 0.0,
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::f64::consts;
+
+use super::same_hemisphere;
+use crate::{point::vector::Vector, sampling::rng::Rng};
+
+/// Smallest roughness used by the microfacet models. Perfectly smooth surfaces would need special
+/// cases for their infinitely sharp lobes, so they are approximated by very smooth ones
+const MIN_ALPHA: f64 = 1e-3;
+
+/// GGX (Trowbridge-Reitz) distribution of the normals of the tiny facets that make up a rough
+/// surface, with Smith's height correlated shadowing and masking
+///
+/// Directions are in the local shading frame, where the normal of the surface is +z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ggx {
+    pub alpha: f64,
+}
+
+impl Ggx {
+    /// Distribution for a perceptual roughness between 0 (smooth) and 1 (very rough). The width of
+    /// the distribution is the square of the roughness
+    pub fn from_roughness(roughness: f64) -> Self {
+        Self {
+            alpha: (roughness * roughness).max(MIN_ALPHA),
+        }
+    }
+
+    /// Density of facets with normal `m`, per unit of projected area
+    pub fn d(&self, m: &Vector) -> f64 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+        let alpha2 = self.alpha * self.alpha;
+        let cos2 = m.z * m.z;
+        let denom = cos2 * (alpha2 - 1.0) + 1.0;
+        alpha2 / (consts::PI * denom * denom)
+    }
+
+    fn lambda(&self, w: &Vector) -> f64 {
+        let cos2 = w.z * w.z;
+        if cos2 == 0.0 {
+            return f64::INFINITY;
+        }
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+        ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// Fraction of the facets visible from `w`
+    pub fn g1(&self, w: &Vector) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of the facets visible from both directions
+    pub fn g(&self, wo: &Vector, wi: &Vector) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of the facet normals seen from `w`, the one `sample_visible` draws from
+    pub fn visible_pdf(&self, w: &Vector, m: &Vector) -> f64 {
+        if w.z == 0.0 {
+            return 0.0;
+        }
+        self.g1(w) / w.z.abs() * self.d(m) * w.dot(m).abs()
+    }
+
+    /// Random facet normal among the ones visible from `w` (Heitz, 2018)
+    pub fn sample_visible(&self, w: &Vector, rng: &mut Rng) -> Vector {
+        // Stretch the view so that the distribution becomes a hemisphere
+        let mut wh = Vector::new(self.alpha * w.x, self.alpha * w.y, w.z).normalize();
+        if wh.z < 0.0 {
+            wh = wh.negate();
+        }
+        let length2 = wh.x * wh.x + wh.y * wh.y;
+        let t1 = match length2 > 0.0 {
+            true => Vector::new(-wh.y, wh.x, 0.0) / length2.sqrt(),
+            false => Vector::new(1.0, 0.0, 0.0),
+        };
+        let t2 = wh.cross_product(&t1);
+
+        // Point on a disk, warped to the part of the hemisphere that faces the view
+        let radius = rng.next_f64().sqrt();
+        let angle = 2.0 * consts::PI * rng.next_f64();
+        let (p1, p2) = (radius * angle.cos(), radius * angle.sin());
+        let s = (1.0 + wh.z) / 2.0;
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * p2;
+        let pz = (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+        let nh = t1 * p1 + t2 * p2 + wh * pz;
+        Vector::new(self.alpha * nh.x, self.alpha * nh.y, nh.z.max(1e-6)).normalize()
+    }
+}
+
+/// Microfacet normal that reflects `wo` into `wi`, on the side of the outward normal. `None` if
+/// they are not on the same side
+pub fn half_vector(wo: &Vector, wi: &Vector) -> Option<Vector> {
+    if !same_hemisphere(wo, wi) {
+        return None;
+    }
+    let half = wo.clone() + wi.clone();
+    if half.magnitude() == 0.0 {
+        return None;
+    }
+    let half = half.normalize();
+    Some(match half.z < 0.0 {
+        true => half.negate(),
+        false => half,
+    })
+}
+
+/// Mirror direction of `wo` around the normal `m`
+pub fn reflect(wo: &Vector, m: &Vector) -> Vector {
+    m.clone() * (2.0 * wo.dot(m)) - wo.clone()
+}
+
+/// Direction of `wo` after refracting through a surface with normal `m`, where `eta` is the
+/// refractive index of the side `m` points away from over the one it points to. Also returns
+/// the ratio of indices that was used, which is inverted when `wo` comes from the other side.
+/// `None` on total internal reflection
+pub fn refract(wo: &Vector, m: &Vector, eta: f64) -> Option<(Vector, f64)> {
+    let (mut cos_i, mut eta, mut m) = (wo.dot(m), eta, m.clone());
+    if cos_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_i = -cos_i;
+        m = m.negate();
+    }
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some((wo.clone().negate() / eta + m * (cos_i / eta - cos_t), eta))
+}
+
+/// Fraction of the light reflected by the boundary between two dielectrics, where `eta` is the
+/// ratio between the refractive indices of the inner and outer sides
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let (mut cos_i, mut eta) = (cos_i.clamp(-1.0, 1.0), eta);
+    if cos_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_i = -cos_i;
+    }
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (parallel * parallel + perpendicular * perpendicular) / 2.0
+}
+
+/// Fraction of the light reflected by a metal with complex refractive index `eta + i k`
+pub fn fresnel_conductor(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos_i = cos_i.abs().min(1.0);
+    let cos2 = cos_i * cos_i;
+    let sin2 = 1.0 - cos2;
+    let t0 = eta * eta - k * k - sin2;
+    let a2b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let t1 = a2b2 + cos2;
+    let a = (0.5 * (a2b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    (rs + rp) / 2.0
+}
+
+/// Schlick's approximation of the reflected fraction, given the one at normal incidence
+pub fn fresnel_schlick(f0: f64, cos_i: f64) -> f64 {
+    f0 + (1.0 - f0) * (1.0 - cos_i.abs()).clamp(0.0, 1.0).powi(5)
+}
+
+#[cfg(test)]
+mod test_microfacet {
+    use super::*;
+    use crate::approx::approx;
+
+    #[test]
+    fn distribution_is_normalized() {
+        // The projected area of the facets adds up to the area of the surface
+        for roughness in [0.2, 0.5, 0.9] {
+            let ggx = Ggx::from_roughness(roughness);
+            let steps = 20000;
+            let integral: f64 = (0..steps)
+                .map(|i| {
+                    let cos = (i as f64 + 0.5) / steps as f64;
+                    let m = Vector::new((1.0 - cos * cos).sqrt(), 0.0, cos);
+                    ggx.d(&m) * cos * 2.0 * consts::PI / steps as f64
+                })
+                .sum();
+            assert!((integral - 1.0).abs() < 1e-3, "{roughness} {integral}");
+        }
+    }
+
+    #[test]
+    fn fresnel_values() {
+        // Glass reflects 4% head on, and everything past the critical angle from inside
+        assert!(approx(fresnel_dielectric(1.0, 1.5), 0.04));
+        assert_eq!(fresnel_dielectric(-0.2, 1.5), 1.0);
+        assert!(approx(fresnel_dielectric(0.0, 1.5), 1.0));
+        // A conductor without absorption is a dielectric
+        assert!(approx(
+            fresnel_conductor(0.6, 1.5, 0.0),
+            fresnel_dielectric(0.6, 1.5)
+        ));
+        assert!(approx(fresnel_schlick(0.04, 1.0), 0.04));
+    }
+
+    #[test]
+    fn refraction_bends_towards_the_normal() {
+        let normal = Vector::new(0.0, 0.0, 1.0);
+        let wo = Vector::new(0.6, 0.0, 0.8);
+        let (wi, eta) = refract(&wo, &normal, 1.5).unwrap();
+        assert!(approx(eta, 1.5));
+        assert!(approx(wi.x, -0.4) && wi.z < 0.0 && approx(wi.magnitude(), 1.0));
+
+        // Going back out gives the original direction
+        let (back, eta) = refract(&wi, &normal, 1.5).unwrap();
+        assert!(approx(eta, 1.0 / 1.5));
+        assert!(approx(back.x, 0.6) && approx(back.z, 0.8));
+
+        assert!(refract(&Vector::new(0.9, 0.0, -0.43589), &normal, 1.5).is_none());
+    }
+}
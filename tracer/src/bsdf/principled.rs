@@ -0,0 +1,113 @@
+use std::f64::consts;
+
+use image::color::Color;
+
+use super::{
+    microfacet::{fresnel_schlick, half_vector, reflect, Ggx},
+    same_hemisphere, Bsdf, BsdfSample,
+};
+use crate::{point::vector::Vector, sampling::cosine_hemisphere, sampling::rng::Rng};
+
+/// Reflectance at normal incidence of the non metallic part, the one of most plastics and paints
+const DIELECTRIC_F0: f64 = 0.04;
+
+/// Metallic-roughness material, the one used by most modelling tools and by glTF
+///
+/// It blends a diffuse base coated by a dielectric GGX layer with a GGX metal whose reflectance
+/// is the base color. The diffuse part only gets the light the coating lets through (Ashikhmin
+/// and Shirley, 2000), so the material never reflects more light than it receives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Principled {
+    pub base_color: Color,
+    /// 0 is a dielectric, 1 is a metal. Values in between blend both
+    pub metallic: f64,
+    /// 0 is polished, 1 is very rough
+    pub roughness: f64,
+}
+
+impl Principled {
+    pub fn new(base_color: Color, metallic: f64, roughness: f64) -> Self {
+        Self {
+            base_color,
+            metallic,
+            roughness,
+        }
+    }
+
+    /// Reflectance of the specular layer at normal incidence
+    fn f0(&self) -> Color {
+        Color::new(DIELECTRIC_F0, DIELECTRIC_F0, DIELECTRIC_F0) * (1.0 - self.metallic)
+            + self.base_color * self.metallic
+    }
+
+    /// Probability of sampling the specular layer instead of the diffuse one
+    fn specular_probability(&self) -> f64 {
+        (1.0 + self.metallic.clamp(0.0, 1.0)) / 2.0
+    }
+
+    fn specular(&self, wo: &Vector, wi: &Vector) -> Color {
+        let Some(m) = half_vector(wo, wi) else {
+            return Color::black();
+        };
+        let ggx = Ggx::from_roughness(self.roughness);
+        let f0 = self.f0();
+        let cos = wo.dot(&m);
+        let fresnel = Color::new(
+            fresnel_schlick(f0.red, cos),
+            fresnel_schlick(f0.green, cos),
+            fresnel_schlick(f0.blue, cos),
+        );
+        fresnel * (ggx.d(&m) * ggx.g(wo, wi) / (4.0 * wo.z * wi.z).abs())
+    }
+
+    fn diffuse(&self, wo: &Vector, wi: &Vector) -> Color {
+        if !same_hemisphere(wo, wi) {
+            return Color::black();
+        }
+        let through = |w: &Vector| 1.0 - (1.0 - w.z.abs() / 2.0).powi(5);
+        let scale = 28.0 / (23.0 * consts::PI) * (1.0 - DIELECTRIC_F0) * through(wo) * through(wi);
+        self.base_color * ((1.0 - self.metallic) * scale)
+    }
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Self::new(Color::white(), 0.0, 0.5)
+    }
+}
+
+impl Bsdf for Principled {
+    fn evaluate(&self, wo: &Vector, wi: &Vector) -> Color {
+        self.diffuse(wo, wi) + self.specular(wo, wi)
+    }
+
+    fn sample(&self, wo: &Vector, rng: &mut Rng) -> Option<BsdfSample> {
+        let wi = match rng.next_f64() < self.specular_probability() {
+            true => reflect(
+                wo,
+                &Ggx::from_roughness(self.roughness).sample_visible(wo, rng),
+            ),
+            false => cosine_hemisphere(&Vector::new(0.0, 0.0, 1.0f64.copysign(wo.z)), rng),
+        };
+        let pdf = self.pdf(wo, &wi);
+        if pdf == 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            value: self.evaluate(wo, &wi),
+            wi,
+            pdf,
+        })
+    }
+
+    fn pdf(&self, wo: &Vector, wi: &Vector) -> f64 {
+        let Some(m) = half_vector(wo, wi) else {
+            return 0.0;
+        };
+        let ggx = Ggx::from_roughness(self.roughness);
+        let specular = ggx.visible_pdf(wo, &m) / (4.0 * wo.dot(&m).abs());
+        let diffuse = wi.z.abs() * consts::FRAC_1_PI;
+        let p = self.specular_probability();
+        p * specular + (1.0 - p) * diffuse
+    }
+}
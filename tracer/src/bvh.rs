@@ -0,0 +1,452 @@
+use crate::{
+    approx::EPSILON,
+    bounding_box::BoundingBox,
+    intersection::intersections::IntersectionTracker,
+    point::coord::Coord,
+    ray::Ray,
+    shapes::{Hittable, Shapes},
+};
+
+/// Number of buckets the shapes are sorted into when looking for the best split
+const BIN_COUNT: usize = 12;
+/// Cost of testing a ray against a box, relative to the cost of intersecting a shape
+const TRAVERSAL_COST: f64 = 0.125;
+/// Nodes with this many shapes or less are never split
+const MAX_LEAF_SIZE: usize = 2;
+
+#[derive(Debug, PartialEq)]
+enum BvhNode {
+    Leaf {
+        bounds: BoundingBox,
+        shapes: Vec<usize>,
+    },
+    Interior {
+        bounds: BoundingBox,
+        left: usize,
+        right: usize,
+    },
+}
+
+/// Bounding volume hierarchy over a list of shapes
+///
+/// The shapes are split into a tree of boxes using the surface area heuristic, so a ray only has
+/// to be tested against the shapes whose boxes it goes through. The hierarchy only stores the
+/// indices of the shapes, so it must be built again when the shapes change. Shapes with infinite
+/// bounds (like planes) are kept out of the tree, and tested against every ray.
+#[derive(Debug, PartialEq, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    unbounded: Vec<usize>,
+}
+
+/// Shape being placed in the tree, with its bounds
+struct Item {
+    index: usize,
+    bounds: BoundingBox,
+    centroid: Coord,
+}
+
+fn axis_value(coord: &Coord, axis: usize) -> f64 {
+    match axis {
+        0 => coord.x,
+        1 => coord.y,
+        _ => coord.z,
+    }
+}
+
+impl Bvh {
+    pub fn build(shapes: &[Shapes]) -> Self {
+        let mut bvh = Bvh::default();
+        let mut items = vec![];
+
+        for (index, shape) in shapes.iter().enumerate() {
+            // Grow the boxes a bit, so that rounding errors do not make rays miss them
+            let bounds = shape.bounds().expand(EPSILON);
+            match bounds.is_finite() {
+                true => items.push(Item {
+                    index,
+                    centroid: bounds.centroid(),
+                    bounds,
+                }),
+                false => bvh.unbounded.push(index),
+            }
+        }
+
+        if !items.is_empty() {
+            bvh.build_node(&mut items);
+        }
+        bvh
+    }
+
+    /// Add the node for the items to the tree, returning its index
+    fn build_node(&mut self, items: &mut [Item]) -> usize {
+        let bounds = items.iter().fold(BoundingBox::default(), |bounds, item| {
+            bounds.merge(&item.bounds)
+        });
+
+        let node = self.nodes.len();
+        match self.find_split(items, &bounds) {
+            None => self.nodes.push(BvhNode::Leaf {
+                bounds,
+                shapes: items.iter().map(|item| item.index).collect(),
+            }),
+            Some(split) => {
+                // The children are added after this node, so the indices are updated once known
+                self.nodes.push(BvhNode::Interior {
+                    bounds,
+                    left: 0,
+                    right: 0,
+                });
+                let (left_items, right_items) = items.split_at_mut(split);
+                let left_node = self.build_node(left_items);
+                let right_node = self.build_node(right_items);
+                if let BvhNode::Interior { left, right, .. } = &mut self.nodes[node] {
+                    *left = left_node;
+                    *right = right_node;
+                }
+            }
+        }
+        node
+    }
+
+    /// Sort the items and return how many of them go to the left child, using the surface area
+    /// heuristic. `None` when splitting would be more expensive than keeping them in a leaf
+    fn find_split(&self, items: &mut [Item], bounds: &BoundingBox) -> Option<usize> {
+        if items.len() <= MAX_LEAF_SIZE {
+            return None;
+        }
+
+        let centroids = BoundingBox::from_points(items.iter().map(|item| &item.centroid));
+        let extents = [
+            centroids.max.x - centroids.min.x,
+            centroids.max.y - centroids.min.y,
+            centroids.max.z - centroids.min.z,
+        ];
+        let axis = (0..3)
+            .max_by(|&a, &b| extents[a].total_cmp(&extents[b]))
+            .expect("There are 3 axes");
+        let (start, extent) = (axis_value(&centroids.min, axis), extents[axis]);
+
+        // Every centroid is in the same spot, they cannot be split
+        if extent <= 0.0 {
+            return None;
+        }
+
+        let bin_of = |item: &Item| {
+            let offset = (axis_value(&item.centroid, axis) - start) / extent;
+            ((offset * BIN_COUNT as f64) as usize).min(BIN_COUNT - 1)
+        };
+
+        let mut bins: [(usize, BoundingBox); BIN_COUNT] = Default::default();
+        for item in items.iter() {
+            let bin = &mut bins[bin_of(item)];
+            bin.0 += 1;
+            bin.1 = bin.1.merge(&item.bounds);
+        }
+
+        // Cost of splitting after each of the bins, from the areas of the boxes at both sides
+        let area = bounds.surface_area();
+        let (best_bin, best_cost) = (1..BIN_COUNT)
+            .map(|split| {
+                let side = |bins: &[(usize, BoundingBox)]| {
+                    bins.iter()
+                        .fold((0, BoundingBox::default()), |(count, bounds), bin| {
+                            (count + bin.0, bounds.merge(&bin.1))
+                        })
+                };
+                let (left_count, left_bounds) = side(&bins[..split]);
+                let (right_count, right_bounds) = side(&bins[split..]);
+                let cost = TRAVERSAL_COST
+                    + (left_count as f64 * left_bounds.surface_area()
+                        + right_count as f64 * right_bounds.surface_area())
+                        / area;
+                (split, cost)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("There is more than one bin");
+
+        if best_cost >= items.len() as f64 {
+            return None;
+        }
+
+        items.sort_by_key(bin_of);
+        Some(items.partition_point(|item| bin_of(item) < best_bin))
+    }
+
+    /// Intersections of the ray with the shapes the hierarchy was built for, the same ones as
+    /// testing every shape
+    pub fn intersect<'a>(&self, shapes: &'a [Shapes], ray: &Ray) -> IntersectionTracker<'a> {
+        let mut hits: Vec<_> = self
+            .unbounded
+            .iter()
+            .flat_map(|&index| shapes[index].get_intersections(ray))
+            .collect();
+
+        let mut stack = match self.nodes.is_empty() {
+            true => vec![],
+            false => vec![0],
+        };
+        while let Some(node) = stack.pop() {
+            match &self.nodes[node] {
+                BvhNode::Leaf {
+                    bounds,
+                    shapes: indices,
+                } if bounds.intersects(ray) => {
+                    hits.extend(
+                        indices
+                            .iter()
+                            .flat_map(|&index| shapes[index].get_intersections(ray)),
+                    );
+                }
+                BvhNode::Interior {
+                    bounds,
+                    left,
+                    right,
+                } if bounds.intersects(ray) => {
+                    stack.push(*right);
+                    stack.push(*left);
+                }
+                _ => {}
+            }
+        }
+
+        IntersectionTracker::new(hits)
+    }
+}
+
+#[cfg(test)]
+mod test_bvh {
+    use super::*;
+    use crate::{
+        point::vector::Vector,
+        shapes::{
+            cone::Cone, cube::Cube, cylinder::Cylinder, group::Group, plane::Plane, sphere::Sphere,
+            triangle::Triangle,
+        },
+        world::World,
+    };
+
+    /// Small generator of numbers between -1 and 1, so that the tests are repeatable
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> f64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+        }
+
+        fn coord(&mut self, scale: f64) -> Coord {
+            Coord::new(
+                self.next() * scale,
+                self.next() * scale,
+                self.next() * scale,
+            )
+        }
+    }
+
+    fn random_shapes(rng: &mut Lcg, count: usize) -> Vec<Shapes> {
+        (0..count)
+            .map(|i| {
+                let center = rng.coord(20.0);
+                let size = rng.next().abs() + 0.1;
+                let mut shape = match i % 5 {
+                    0 => Shapes::Sphere(Sphere::default()),
+                    1 => Shapes::Cube(Cube::default()),
+                    2 => Shapes::Cylinder(Cylinder {
+                        minimum: -1.0,
+                        maximum: 1.0,
+                        closed: true,
+                        ..Default::default()
+                    }),
+                    3 => Shapes::Cone(Cone {
+                        minimum: -1.0,
+                        maximum: 0.5,
+                        ..Default::default()
+                    }),
+                    _ => {
+                        let p1 = rng.coord(2.0);
+                        Shapes::Triangle(Triangle::new(p1, rng.coord(2.0), rng.coord(2.0)))
+                    }
+                };
+                let transformation = match &mut shape {
+                    Shapes::Sphere(s) => &mut s.transformation,
+                    Shapes::Cube(s) => &mut s.transformation,
+                    Shapes::Cylinder(s) => &mut s.transformation,
+                    Shapes::Cone(s) => &mut s.transformation,
+                    Shapes::Triangle(s) => &mut s.transformation,
+                    _ => unreachable!(),
+                };
+                transformation
+                    .scale((size, size * 2.0, size))
+                    .rotate(crate::transformations::Axis::Z, rng.next())
+                    .translate((center.x, center.y, center.z));
+                shape
+            })
+            .collect()
+    }
+
+    /// Time of every hit, with the address of the shape that was hit
+    fn hits(tracker: IntersectionTracker) -> Vec<(f64, *const Shapes)> {
+        let mut hits: Vec<_> = tracker
+            .iter()
+            .map(|inter| (inter.time, inter.shape as *const Shapes))
+            .collect();
+        hits.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        hits
+    }
+
+    #[test]
+    fn empty_hierarchy() {
+        let bvh = Bvh::build(&[]);
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        assert!(bvh.intersect(&[], &ray).is_empty());
+    }
+
+    #[test]
+    fn splits_distant_shapes() {
+        let mut rng = Lcg(7);
+        let mut shapes = random_shapes(&mut rng, 40);
+        let bvh = Bvh::build(&shapes);
+        assert!(matches!(bvh.nodes[0], BvhNode::Interior { .. }));
+        assert!(bvh.unbounded.is_empty());
+
+        shapes.push(Shapes::Plane(Plane::default()));
+        let bvh = Bvh::build(&shapes);
+        assert_eq!(bvh.unbounded, vec![40]);
+
+        // Every shape is in exactly one leaf
+        let mut indices: Vec<usize> = bvh
+            .nodes
+            .iter()
+            .flat_map(|node| match node {
+                BvhNode::Leaf { shapes, .. } => shapes.clone(),
+                BvhNode::Interior { .. } => vec![],
+            })
+            .collect();
+        indices.sort();
+        assert_eq!(indices, (0..40).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn same_hits_as_brute_force() {
+        let mut rng = Lcg(42);
+        let mut shapes = random_shapes(&mut rng, 150);
+        shapes.push(Shapes::Plane(Plane::default()));
+        let bvh = Bvh::build(&shapes);
+
+        let mut total = 0;
+        for _ in 0..250 {
+            let origin = rng.coord(30.0);
+            let target = rng.coord(15.0);
+            let ray = Ray::new(origin.clone(), origin.vector_to(&target));
+
+            let brute_force = hits(IntersectionTracker::new(
+                shapes
+                    .iter()
+                    .flat_map(|shape| shape.get_intersections(&ray))
+                    .collect(),
+            ));
+            let accelerated = hits(bvh.intersect(&shapes, &ray));
+            assert_eq!(accelerated, brute_force);
+            total += brute_force.len();
+        }
+        // Make sure that the rays actually hit something
+        assert!(total > 200, "{total}");
+    }
+
+    #[test]
+    fn axis_aligned_rays() {
+        // Rays parallel to the axes, some of them along the faces of flat boxes
+        let shapes = vec![
+            Shapes::Triangle(Triangle::new(
+                Coord::from((0, 0, 0)),
+                Coord::from((1, 0, 0)),
+                Coord::from((0, 1, 0)),
+            )),
+            Shapes::Triangle(Triangle::new(
+                Coord::from((0, 0, 3)),
+                Coord::from((1, 0, 3)),
+                Coord::from((0, 1, 3)),
+            )),
+            Shapes::Sphere(Sphere::default()),
+        ];
+        let bvh = Bvh::build(&shapes);
+
+        for x in [-1.5, -0.5, 0.0, 0.25, 0.5, 1.0] {
+            for dir in [(0, 0, 1), (0, 1, 0), (1, 0, 0)] {
+                let ray = Ray::new(Coord::new(x, 0.25, -5.0), Vector::from(dir));
+                let brute_force = hits(IntersectionTracker::new(
+                    shapes
+                        .iter()
+                        .flat_map(|shape| shape.get_intersections(&ray))
+                        .collect(),
+                ));
+                assert_eq!(hits(bvh.intersect(&shapes, &ray)), brute_force);
+            }
+        }
+    }
+
+    #[test]
+    fn world_and_groups_use_hierarchy() {
+        let mut rng = Lcg(3);
+        let mut group = Group::new(random_shapes(&mut rng, 50));
+        group
+            .transformation
+            .scale((0.5, 0.5, 0.5))
+            .translate((5, 0, 0));
+
+        let mut world = World::new(random_shapes(&mut rng, 50));
+        world.shapes_mut().push(Shapes::Group(group));
+
+        let rays: Vec<Ray> = (0..100)
+            .map(|_| {
+                let origin = rng.coord(30.0);
+                let target = rng.coord(10.0);
+                Ray::new(origin.clone(), origin.vector_to(&target))
+            })
+            .collect();
+        let brute_force: Vec<_> = rays.iter().map(|ray| hits(world.intersect(ray))).collect();
+
+        world.build_bvh();
+        assert!(world.has_bvh());
+        let Some(Shapes::Group(group)) = world.shapes().last() else {
+            panic!("The group is the last shape");
+        };
+        assert!(group.has_bvh());
+
+        let accelerated: Vec<_> = rays.iter().map(|ray| hits(world.intersect(ray))).collect();
+        assert_eq!(accelerated, brute_force);
+    }
+
+    #[test]
+    fn changing_shapes_drops_hierarchy() {
+        let mut rng = Lcg(5);
+        let mut world = World::new(vec![Shapes::Group(Group::new(random_shapes(&mut rng, 10)))]);
+        world.build_bvh();
+
+        let Some(Shapes::Group(group)) = world.shapes_mut().last_mut() else {
+            panic!("The group is the only shape");
+        };
+        assert!(group.has_bvh());
+        group.children_mut().truncate(2);
+        assert!(!group.has_bvh());
+        assert!(!world.has_bvh());
+
+        // Without the stale hierarchies the remaining shapes are intersected one by one
+        let ray = Ray::from(((0, 0, -30), (0, 0, 1)));
+        let Shapes::Group(group) = &world.shapes()[0] else {
+            panic!("The group is the only shape");
+        };
+        let expected = IntersectionTracker::new(
+            group
+                .children()
+                .iter()
+                .flat_map(|child| child.get_intersections(&ray))
+                .collect(),
+        );
+        assert_eq!(hits(world.intersect(&ray)), hits(expected));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    point::{coord::Coord, vector::Vector},
+    ray::Ray,
+    transformations::TransformationMatrix,
+};
+
+/// Pinhole camera that maps the pixels of an image to rays in the world
+///
+/// The camera looks towards -z from the origin, with the image one unit in front of it. Its
+/// transformation moves the world around the camera, see `TransformationMatrix::look_at`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera {
+    /// Width of the image in pixels
+    pub hsize: usize,
+    /// Height of the image in pixels
+    pub vsize: usize,
+    /// Angle, in radians, that the image covers along its longest side
+    pub field_of_view: f64,
+    pub transformation: TransformationMatrix,
+    half_width: f64,
+    half_height: f64,
+    pixel_size: f64,
+}
+
+impl Camera {
+    pub fn new(hsize: usize, vsize: usize, field_of_view: f64) -> Self {
+        let half_view = (field_of_view / 2.0).tan();
+        let aspect = hsize as f64 / vsize as f64;
+        let (half_width, half_height) = match aspect >= 1.0 {
+            true => (half_view, half_view / aspect),
+            false => (half_view * aspect, half_view),
+        };
+
+        Self {
+            hsize,
+            vsize,
+            field_of_view,
+            transformation: TransformationMatrix::default(),
+            half_width,
+            half_height,
+            pixel_size: half_width * 2.0 / hsize as f64,
+        }
+    }
+
+    /// Size of a pixel in the image, which is one unit away from the camera
+    pub fn pixel_size(&self) -> f64 {
+        self.pixel_size
+    }
+
+    /// Ray that goes through the center of a pixel
+    pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
+        self.ray_through(px as f64 + 0.5, py as f64 + 0.5)
+    }
+
+    /// Ray that goes through some point of the image, given in pixels from its top left corner
+    pub fn ray_through(&self, x: f64, y: f64) -> Ray {
+        // The camera looks towards -z, so +x is to the left
+        let world_x = self.half_width - x * self.pixel_size;
+        let world_y = self.half_height - y * self.pixel_size;
+
+        let inverse = self
+            .transformation
+            .inverse()
+            .expect("Could not get inverse of transformation");
+        let pixel = inverse * Coord::new(world_x, world_y, -1.0);
+        let origin = inverse * Coord::new(0.0, 0.0, 0.0);
+        let direction: Vector = origin.clone().vector_to(&pixel).normalize();
+
+        Ray::new(origin, direction)
+    }
+}
+
+#[cfg(test)]
+mod test_camera {
+    use super::*;
+    use crate::{approx::approx, transformations::Axis};
+    use std::f64::consts;
+
+    #[test]
+    fn pixel_size() {
+        assert!(approx(
+            Camera::new(200, 125, consts::FRAC_PI_2).pixel_size(),
+            0.01
+        ));
+        assert!(approx(
+            Camera::new(125, 200, consts::FRAC_PI_2).pixel_size(),
+            0.01
+        ));
+    }
+
+    #[test]
+    fn ray_through_center() {
+        let camera = Camera::new(201, 101, consts::FRAC_PI_2);
+        let ray = camera.ray_for_pixel(100, 50);
+        assert_eq!(ray.origin, Coord::from((0, 0, 0)));
+        assert_eq!(ray.dir, Vector::from((0, 0, -1)));
+    }
+
+    #[test]
+    fn ray_through_corner() {
+        let camera = Camera::new(201, 101, consts::FRAC_PI_2);
+        let ray = camera.ray_for_pixel(0, 0);
+        assert_eq!(ray.origin, Coord::from((0, 0, 0)));
+        assert_eq!(ray.dir, Vector::from((0.66519, 0.33259, -0.66851)));
+    }
+
+    #[test]
+    fn ray_with_transformed_camera() {
+        let mut camera = Camera::new(201, 101, consts::FRAC_PI_2);
+        camera
+            .transformation
+            .translate((0, -2, 5))
+            .rotate(Axis::Y, consts::FRAC_PI_4);
+        let ray = camera.ray_for_pixel(100, 50);
+        assert_eq!(ray.origin, Coord::from((0, 2, -5)));
+        assert_eq!(
+            ray.dir,
+            Vector::new(consts::FRAC_1_SQRT_2, 0.0, -consts::FRAC_1_SQRT_2)
+        );
+    }
+}
@@ -0,0 +1,530 @@
+use std::{collections::HashMap, f64::consts};
+
+use image::color::Color;
+
+use crate::{
+    approx::EPSILON,
+    area_light::DEFAULT_AREA_SAMPLES,
+    light::LightSample,
+    matrix::square4::Matrix4x4,
+    point::{coord::Coord, vector::Vector},
+    sampling::{luminance, rng::Rng, uniform_cone},
+    shapes::{Hittable, Shapes},
+};
+
+/// Identifier of a shape, to recognize the emitters that rays hit. It only stays the same while
+/// the shapes of the world are not changed
+pub fn shape_id(shape: &Shapes) -> usize {
+    shape as *const Shapes as usize
+}
+
+/// Surface of an emitter in world space
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmitterSurface {
+    /// Sphere that keeps its round shape, aimed at through the cone it fills
+    Sphere { center: Coord, radius: f64 },
+    /// Triangle starting at `p1` with edges `e1` and `e2`, aimed at through a random point on it
+    Triangle {
+        p1: Coord,
+        e1: Vector,
+        e2: Vector,
+        normal: Vector,
+        area: f64,
+    },
+}
+
+/// Piece of the surface of an emissive shape
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emitter {
+    /// `shape_id` of the shape it belongs to
+    pub shape: usize,
+    pub emissive: Color,
+    pub surface: EmitterSurface,
+}
+
+impl Emitter {
+    pub fn area(&self) -> f64 {
+        match &self.surface {
+            EmitterSurface::Sphere { radius, .. } => 4.0 * consts::PI * radius * radius,
+            EmitterSurface::Triangle { area, .. } => *area,
+        }
+    }
+
+    /// Light given off by the whole surface
+    pub fn power(&self) -> f64 {
+        self.area() * luminance(&self.emissive)
+    }
+}
+
+/// Direction from a point to a random point of an emitter
+#[derive(Debug, Clone)]
+pub struct EmitterSample {
+    pub direction: Vector,
+    /// Distance to the point of the emitter
+    pub distance: f64,
+    /// Probability density of the direction, per unit of solid angle
+    pub pdf: f64,
+    pub emissive: Color,
+    /// `shape_id` of the emissive shape
+    pub shape: usize,
+}
+
+/// Every emissive shape in the world, which the path tracer can aim its rays at instead of waiting
+/// for them to hit the shapes
+///
+/// Shapes are chosen in proportion to the light they give off (their area times their
+/// brightness), so the triangles of a mesh light are chosen in proportion to their area. Spheres
+/// that keep their round shape, triangles and cubes are used, inside of groups too. Other emissive
+/// shapes are still found by the rays that bounce into them.
+#[derive(Debug, Clone, Default)]
+pub struct Emitters {
+    pub emitters: Vec<Emitter>,
+    /// Running sum of the power of the emitters
+    cdf: Vec<f64>,
+    /// First emitter of each shape, and how many of them it has
+    by_shape: HashMap<usize, (usize, usize)>,
+    /// Number of points of the emitters that light each point in `light_samples`
+    pub samples: usize,
+}
+
+impl Emitters {
+    pub fn collect(shapes: &[Shapes]) -> Self {
+        let mut emitters = Emitters {
+            samples: DEFAULT_AREA_SAMPLES,
+            ..Default::default()
+        };
+        shapes
+            .iter()
+            .for_each(|shape| emitters.add(shape, &Matrix4x4::identity()));
+
+        let mut total = 0.0;
+        emitters.cdf = emitters
+            .emitters
+            .iter()
+            .map(|emitter| {
+                total += emitter.power();
+                total
+            })
+            .collect();
+        emitters
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.emitters.is_empty()
+    }
+
+    fn total_power(&self) -> f64 {
+        self.cdf.last().copied().unwrap_or(0.0)
+    }
+
+    /// Add the emitters of a shape, where `parent` places it in the world
+    fn add(&mut self, shape: &Shapes, parent: &Matrix4x4) {
+        if let Shapes::Group(group) = shape {
+            let transformation = *parent * group.transformation.matrix;
+            for child in group.children() {
+                self.add(child, &transformation);
+            }
+            return;
+        }
+
+        let emissive = shape.material().emissive;
+        if luminance(&emissive) <= 0.0 {
+            return;
+        }
+
+        let surfaces = match shape {
+            Shapes::Sphere(sphere) => round_sphere(&(*parent * sphere.transformation.matrix))
+                .into_iter()
+                .collect(),
+            Shapes::Triangle(triangle) => {
+                let transformation = *parent * triangle.transformation.matrix;
+                let (p1, p2, p3) = triangle.points();
+                triangle_surface(&transformation, [p1, p2, p3])
+                    .into_iter()
+                    .collect()
+            }
+            Shapes::SmoothTriangle(triangle) => {
+                let transformation = *parent * triangle.transformation.matrix;
+                let (p1, p2, p3) = triangle.points();
+                triangle_surface(&transformation, [p1, p2, p3])
+                    .into_iter()
+                    .collect()
+            }
+            Shapes::Cube(cube) => cube_surfaces(&(*parent * cube.transformation.matrix)),
+            _ => vec![],
+        };
+
+        let id = shape_id(shape);
+        if !surfaces.is_empty() {
+            self.by_shape
+                .insert(id, (self.emitters.len(), surfaces.len()));
+        }
+        self.emitters
+            .extend(surfaces.into_iter().map(|surface| Emitter {
+                shape: id,
+                emissive,
+                surface,
+            }));
+    }
+
+    /// Random direction from the point towards the emitters
+    pub fn sample(&self, from: &Coord, rng: &mut Rng) -> Option<EmitterSample> {
+        let total = self.total_power();
+        if total <= 0.0 {
+            return None;
+        }
+        let target = rng.next_f64() * total;
+        let index = self.cdf.partition_point(|&sum| sum <= target);
+        let emitter = self.emitters.get(index)?;
+        let chosen = emitter.power() / total;
+
+        let (direction, distance, pdf) = match &emitter.surface {
+            EmitterSurface::Sphere { center, radius } => {
+                let (axis, cos_max) = cone(from, center, *radius)?;
+                let direction = uniform_cone(&axis, cos_max, rng);
+                let distance = sphere_distance(from, &direction, center, *radius);
+                (direction, distance, cone_pdf(cos_max))
+            }
+            EmitterSurface::Triangle {
+                p1,
+                e1,
+                e2,
+                normal,
+                area,
+            } => {
+                // Uniform point on the triangle
+                let root = rng.next_f64().sqrt();
+                let v = rng.next_f64() * root;
+                let point = p1.add_vector(&(e1.clone() * (root - v) + e2.clone() * v));
+                let to_point = from.clone().vector_to(&point);
+                let distance = to_point.magnitude();
+                let direction = to_point.normalize();
+                let cos = direction.dot(normal).abs();
+                if cos == 0.0 || distance == 0.0 {
+                    return None;
+                }
+                (direction, distance, distance * distance / (cos * area))
+            }
+        };
+
+        Some(EmitterSample {
+            direction,
+            distance,
+            pdf: chosen * pdf,
+            emissive: emitter.emissive,
+            shape: emitter.shape,
+        })
+    }
+
+    /// Probability density with which `sample` chooses the direction from `from` that hits the
+    /// shape at `point`, where its normal is `normal`. 0 for shapes that are not emitters
+    pub fn pdf(&self, from: &Coord, shape: &Shapes, point: &Coord, normal: &Vector) -> f64 {
+        let Some(&(index, count)) = self.by_shape.get(&shape_id(shape)) else {
+            return 0.0;
+        };
+        let emitter = &self.emitters[index];
+        let total = self.total_power();
+
+        match &emitter.surface {
+            EmitterSurface::Sphere { center, radius } => cone(from, center, *radius)
+                .map_or(0.0, |(_, cos_max)| {
+                    emitter.power() / total * cone_pdf(cos_max)
+                }),
+            EmitterSurface::Triangle {
+                normal: triangle_normal,
+                ..
+            } => {
+                // Every triangle of a shape is chosen with the same density per unit of area. A
+                // single triangle knows its flat normal, the faces of cubes take the one of the hit
+                let normal = match count {
+                    1 => triangle_normal,
+                    _ => normal,
+                };
+                let to_point = from.clone().vector_to(point);
+                let distance2 = to_point.dot(&to_point);
+                let cos = to_point.normalize().dot(normal).abs();
+                match cos > 0.0 {
+                    true => luminance(&emitter.emissive) / total * distance2 / cos,
+                    false => 0.0,
+                }
+            }
+        }
+    }
+
+    /// Light of random points of the emitters, that shades the point like the light of an area
+    /// light. Points get the same samples every time, so images have no noise between frames
+    pub fn light_samples(&self, point: &Coord) -> Vec<LightSample> {
+        if self.is_empty() {
+            return vec![];
+        }
+        let mut rng = Rng::for_point(point);
+        let count = self.samples.max(1);
+
+        (0..count)
+            .filter_map(|_| self.sample(point, &mut rng))
+            .map(|sample| LightSample {
+                // Lights give `intensity` times the cosine, which here is the irradiance over PI,
+                // like the Lambertian BSDF of the path tracer
+                intensity: sample.emissive * (1.0 / (consts::PI * sample.pdf * count as f64)),
+                // The emitter itself does not cast a shadow
+                distance: sample.distance * (1.0 - 1e-6) - 2.0 * EPSILON,
+                direction: sample.direction,
+            })
+            .collect()
+    }
+
+    /// Whether a hit at `time` along the ray of the sample is the point of the emitter it aimed at,
+    /// with nothing in between
+    pub fn reaches(sample: &EmitterSample, shape: &Shapes, time: f64) -> bool {
+        shape_id(shape) == sample.shape && time >= sample.distance * (1.0 - 1e-6) - 2.0 * EPSILON
+    }
+}
+
+/// Center and radius of a sphere with the transformation, if it is still round
+fn round_sphere(transformation: &Matrix4x4) -> Option<EmitterSurface> {
+    let axes = [(1, 0, 0), (0, 1, 0), (0, 0, 1)].map(|axis| *transformation * Vector::from(axis));
+    let radius = axes[0].magnitude();
+    let tolerance = 1e-9 * radius * radius;
+    let round = axes
+        .iter()
+        .all(|axis| (axis.dot(axis) - radius * radius).abs() < tolerance)
+        && axes[0].dot(&axes[1]).abs() < tolerance
+        && axes[1].dot(&axes[2]).abs() < tolerance
+        && axes[0].dot(&axes[2]).abs() < tolerance;
+    if !round || radius == 0.0 {
+        return None;
+    }
+
+    Some(EmitterSurface::Sphere {
+        center: *transformation * Coord::new(0.0, 0.0, 0.0),
+        radius,
+    })
+}
+
+fn triangle_surface(transformation: &Matrix4x4, points: [&Coord; 3]) -> Option<EmitterSurface> {
+    let [p1, p2, p3] = points.map(|point| *transformation * point);
+    let e1 = p1.clone().vector_to(&p2);
+    let e2 = p1.clone().vector_to(&p3);
+    let cross = e1.cross_product(&e2);
+    let area = cross.magnitude() / 2.0;
+    if area == 0.0 {
+        return None;
+    }
+    Some(EmitterSurface::Triangle {
+        p1,
+        e1,
+        e2,
+        normal: cross.normalize(),
+        area,
+    })
+}
+
+/// The six faces of a cube, two triangles each
+fn cube_surfaces(transformation: &Matrix4x4) -> Vec<EmitterSurface> {
+    let mut surfaces = vec![];
+    for axis in 0..3 {
+        for side in [-1.0, 1.0] {
+            let corner = |u: f64, v: f64| {
+                let mut coords = [0.0; 3];
+                coords[axis] = side;
+                coords[(axis + 1) % 3] = u;
+                coords[(axis + 2) % 3] = v;
+                Coord::new(coords[0], coords[1], coords[2])
+            };
+            let [a, b, c, d] = [
+                corner(-1.0, -1.0),
+                corner(1.0, -1.0),
+                corner(1.0, 1.0),
+                corner(-1.0, 1.0),
+            ];
+            surfaces.extend(triangle_surface(transformation, [&a, &b, &c]));
+            surfaces.extend(triangle_surface(transformation, [&a, &c, &d]));
+        }
+    }
+    surfaces
+}
+
+/// Axis and cosine of the half angle of the cone the sphere fills seen from the point. `None`
+/// from inside the sphere
+fn cone(from: &Coord, center: &Coord, radius: f64) -> Option<(Vector, f64)> {
+    let to_center = from.clone().vector_to(center);
+    let distance = to_center.magnitude();
+    if distance <= radius {
+        return None;
+    }
+    let sin2 = (radius / distance).powi(2);
+    Some((to_center.normalize(), (1.0 - sin2).max(0.0).sqrt()))
+}
+
+fn cone_pdf(cos_max: f64) -> f64 {
+    1.0 / (2.0 * consts::PI * (1.0 - cos_max))
+}
+
+/// Distance along the direction to the near side of the sphere
+fn sphere_distance(from: &Coord, direction: &Vector, center: &Coord, radius: f64) -> f64 {
+    let offset = center.clone().vector_to(from);
+    let b = direction.dot(&offset);
+    let c = offset.dot(&offset) - radius * radius;
+    -b - (b * b - c).max(0.0).sqrt()
+}
+
+#[cfg(test)]
+mod test_emitter {
+    use super::*;
+    use crate::{
+        ray::Ray,
+        shapes::{cube::Cube, group::Group, sphere::Sphere, triangle::Triangle},
+        transformations::Axis,
+    };
+
+    fn lamp(scale: (f64, f64, f64)) -> Shapes {
+        let mut sphere = Sphere::default();
+        sphere.transformation.scale(scale).translate((0, 3, 0));
+        sphere.material.emissive = Color::white();
+        Shapes::Sphere(sphere)
+    }
+
+    /// Density of the directions chosen by `sample` from the point, estimated by integrating `pdf`
+    /// over every direction that hits the shapes
+    fn total_pdf(shapes: &[Shapes], from: &Coord, samples: usize) -> f64 {
+        let emitters = Emitters::collect(shapes);
+        let mut rng = Rng::new(9);
+        let total: f64 = (0..samples)
+            .map(|_| {
+                // Uniform direction on the sphere
+                let z = 1.0 - 2.0 * rng.next_f64();
+                let angle = 2.0 * consts::PI * rng.next_f64();
+                let r = (1.0 - z * z).sqrt();
+                let ray = Ray::new(
+                    from.clone(),
+                    Vector::new(r * angle.cos(), r * angle.sin(), z),
+                );
+                let Some((shape, time)) = shapes
+                    .iter()
+                    .flat_map(|shape| shape.get_intersections(&ray))
+                    .filter(|hit| hit.time > 0.0)
+                    .min_by(|a, b| a.time.total_cmp(&b.time))
+                    .map(|hit| (hit.shape, hit.time))
+                else {
+                    return 0.0;
+                };
+                let point = from.add_vector(&(ray.dir.clone() * time));
+                let normal = shape.normal_with_uv(&point, None);
+                emitters.pdf(from, shape, &point, &normal) * 4.0 * consts::PI
+            })
+            .sum();
+        total / samples as f64
+    }
+
+    #[test]
+    fn only_emissive_shapes() {
+        let lamp = lamp((0.5, 0.5, 0.5));
+        let emitters = Emitters::collect(std::slice::from_ref(&lamp));
+        assert_eq!(
+            emitters.emitters,
+            vec![Emitter {
+                shape: shape_id(&lamp),
+                emissive: Color::white(),
+                surface: EmitterSurface::Sphere {
+                    center: Coord::new(0.0, 3.0, 0.0),
+                    radius: 0.5,
+                },
+            }]
+        );
+
+        let stretched = self::lamp((0.5, 1.0, 0.5));
+        assert!(Emitters::collect(&[stretched]).is_empty());
+        assert!(Emitters::collect(&[Shapes::Sphere(Sphere::default())]).is_empty());
+    }
+
+    #[test]
+    fn sphere_samples_hit_it() {
+        let lamp = lamp((0.5, 0.5, 0.5));
+        let emitters = Emitters::collect(std::slice::from_ref(&lamp));
+        let from = Coord::new(1.0, 0.0, -1.0);
+        let mut rng = Rng::new(4);
+        for _ in 0..1000 {
+            let sample = emitters.sample(&from, &mut rng).unwrap();
+            let times = lamp.hit_times(&Ray::new(from.clone(), sample.direction.clone()));
+            assert!((times[0] - sample.distance).abs() < 1e-6);
+            let point = from.add_vector(&(sample.direction.clone() * sample.distance));
+            let normal = lamp.normal(&point);
+            assert!((emitters.pdf(&from, &lamp, &point, &normal) - sample.pdf).abs() < 1e-9);
+        }
+
+        // The solid angle of the cone is the inverse of the density
+        let distance = 11f64.sqrt();
+        let cos_max = (1.0 - 0.25 / (distance * distance)).sqrt();
+        let solid_angle = 2.0 * consts::PI * (1.0 - cos_max);
+        let point = Coord::new(0.0, 2.5, 0.0);
+        let normal = Vector::new(0.0, -1.0, 0.0);
+        assert!((emitters.pdf(&from, &lamp, &point, &normal) * solid_angle - 1.0).abs() < 1e-9);
+        assert_eq!(
+            emitters.pdf(&Coord::new(0.0, 3.2, 0.0), &lamp, &point, &normal),
+            0.0
+        );
+    }
+
+    #[test]
+    fn mesh_light_in_a_group() {
+        let mut panel = Triangle::new(
+            Coord::new(-1.0, 0.0, -1.0),
+            Coord::new(1.0, 0.0, -1.0),
+            Coord::new(0.0, 0.0, 3.0),
+        );
+        panel.material.emissive = Color::new(2.0, 2.0, 2.0);
+        let mut small = Triangle::new(
+            Coord::new(-1.0, 0.0, 0.0),
+            Coord::new(1.0, 0.0, 0.0),
+            Coord::new(0.0, 0.0, 1.0),
+        );
+        small.material.emissive = Color::new(2.0, 2.0, 2.0);
+        let mut group = Group::new(vec![Shapes::Triangle(panel), Shapes::Triangle(small)]);
+        group.transformation.scale((2, 2, 2)).translate((0, 4, 0));
+        let shapes = [Shapes::Group(group)];
+
+        let emitters = Emitters::collect(&shapes);
+        assert_eq!(emitters.emitters.len(), 2);
+        assert!((emitters.emitters[0].area() - 16.0).abs() < 1e-9);
+        assert!((emitters.emitters[1].area() - 4.0).abs() < 1e-9);
+
+        // Triangles are chosen in proportion to their area
+        let mut rng = Rng::new(6);
+        let from = Coord::new(0.0, 0.0, 0.0);
+        let samples = 20000;
+        let on_panel = (0..samples)
+            .filter_map(|_| emitters.sample(&from, &mut rng))
+            .filter(|sample| sample.shape == emitters.emitters[0].shape)
+            .count();
+        let fraction = on_panel as f64 / samples as f64;
+        assert!((fraction - 0.8).abs() < 0.01, "{fraction}");
+    }
+
+    #[test]
+    fn densities_integrate_to_one() {
+        let mut cube = Cube::default();
+        cube.transformation
+            .scale((1.0, 0.5, 2.0))
+            .rotate(Axis::Y, 0.5)
+            .translate((3, 1, 0));
+        cube.material.emissive = Color::white();
+        let mut triangle = Triangle::new(
+            Coord::new(-2.0, 4.0, -1.0),
+            Coord::new(2.0, 3.0, -1.0),
+            Coord::new(0.0, 5.0, 2.0),
+        );
+        triangle.material.emissive = Color::new(0.5, 1.0, 3.0);
+        let shapes = [
+            Shapes::Cube(cube),
+            Shapes::Triangle(triangle),
+            lamp((0.7, 0.7, 0.7)),
+        ];
+
+        // Some samples land on the hidden side of the cube, and are not seen from the point
+        let total = total_pdf(&shapes, &Coord::new(0.0, 0.0, 0.0), 200000);
+        assert!(total > 0.5 && total < 1.0, "{total}");
+        // Seen from inside of the cube every sample is visible
+        let total = total_pdf(&shapes[..1], &Coord::new(3.0, 1.0, 0.0), 200000);
+        assert!((total - 1.0).abs() < 0.02, "{total}");
+    }
+}
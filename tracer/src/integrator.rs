@@ -0,0 +1,870 @@
+use std::f64::consts;
+
+use image::color::Color;
+
+use crate::{
+    bsdf::{lambertian::Lambertian, Bsdf, Bsdfs, Frame},
+    emitter::Emitters,
+    intersection::computations::Computations,
+    light::{Light, Lights},
+    point::{coord::Coord, vector::Vector},
+    ray::Ray,
+    sampling::{power_heuristic, rng::Rng},
+    shapes::Hittable,
+    world::World,
+};
+
+/// Way of computing the light that arrives to the camera along a ray
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Integrator {
+    /// Direct light from the point lights with the Phong model, plus mirror reflections and
+    /// refractions, see `World::color_at`
+    #[default]
+    Whitted,
+    /// Light bouncing around the scene, see `PathTracer`
+    PathTracer(PathTracer),
+}
+
+impl Integrator {
+    pub fn color_at(&self, world: &World, ray: &Ray, rng: &mut Rng) -> Color {
+        match self {
+            Integrator::Whitted => world.color_at(ray),
+            Integrator::PathTracer(tracer) => tracer.color_at(world, ray, rng),
+        }
+    }
+}
+
+/// Monte Carlo path tracer, which follows the light as it bounces off every surface
+///
+/// Diffuse surfaces scatter rays around their normal with a cosine weighted distribution, so they
+/// also get the light reflected by the surfaces around them (color bleeding, soft indirect light).
+/// Reflective and transparent materials choose at random between their diffuse, mirror and
+/// refracted parts, in the proportions given by the material. The ambient and specular terms of
+/// the Phong model are not used. Materials with a BSDF scatter the light as their physically based
+/// model says instead.
+///
+/// Point lights have no falloff, like in the `Whitted` integrator, so both give the same direct
+/// light on diffuse surfaces. Area lights are also found by the rays sampled from the BSDFs, and
+/// combined with their points like emissive shapes are (see `EmitterSampling`), but these rays
+/// only bring their direct light and never bounce off of them. Rays that leave the scene bring
+/// the light of the background, which also lights the surfaces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathTracer {
+    /// Maximum number of times a path bounces
+    pub max_bounces: usize,
+    /// Bounces after which paths are randomly stopped (Russian roulette), with a probability that
+    /// grows as less light can come back through them
+    pub roulette_after: usize,
+    /// Sample the lights, the emissive shapes and the background at every bounce (next event
+    /// estimation). Point lights cannot be hit by rays, so without it only emissive surfaces and
+    /// the background light the scene
+    pub next_event_estimation: bool,
+    /// How diffuse surfaces and surfaces with a BSDF find the light of emissive shapes, area
+    /// lights and the background, see `EmitterSampling`. Only used with next event estimation
+    pub emitter_sampling: EmitterSampling,
+}
+
+/// Ways of estimating the light that surfaces get from emissive shapes (see `Emitters`), area
+/// lights and the background
+///
+/// Aiming at the shapes, or at the bright parts of the background, works best for small, bright
+/// lights, while following the BSDF works best for glossy surfaces and large lights. Combining
+/// both keeps the best of each.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EmitterSampling {
+    /// Only the rays sampled from the BSDF that happen to hit a shape bring its light
+    Bsdf,
+    /// Only rays aimed at a random shape bring its light
+    Light,
+    /// Both, weighted with the power heuristic (multiple importance sampling)
+    #[default]
+    Mis,
+}
+
+impl EmitterSampling {
+    /// Weight of the light found by aiming at a shape, given the densities with which the light
+    /// and the BSDF choose the direction
+    fn light_weight(&self, light_pdf: f64, bsdf_pdf: f64) -> f64 {
+        match self {
+            EmitterSampling::Bsdf => 0.0,
+            EmitterSampling::Light => 1.0,
+            EmitterSampling::Mis => power_heuristic(light_pdf, bsdf_pdf),
+        }
+    }
+
+    /// Weight of the light found by a ray sampled from the BSDF. Shapes that cannot be aimed at
+    /// from the point (`light_pdf` is 0) are only found this way
+    fn bsdf_weight(&self, bsdf_pdf: f64, light_pdf: f64) -> f64 {
+        match self {
+            _ if light_pdf == 0.0 => 1.0,
+            EmitterSampling::Bsdf => 1.0,
+            EmitterSampling::Light => 0.0,
+            EmitterSampling::Mis => power_heuristic(bsdf_pdf, light_pdf),
+        }
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        Self {
+            max_bounces: 16,
+            roulette_after: 3,
+            next_event_estimation: true,
+            emitter_sampling: EmitterSampling::default(),
+        }
+    }
+}
+
+impl PathTracer {
+    /// One random estimate of the light arriving along the ray
+    pub fn color_at(&self, world: &World, ray: &Ray, rng: &mut Rng) -> Color {
+        let mut radiance = Color::black();
+        // Fraction of the light at the current bounce that makes it back to the camera
+        let mut throughput = Color::white();
+        let mut ray = ray.clone();
+        let none = Emitters::default();
+        let emitters = match self.next_event_estimation {
+            true => world.emitters(),
+            false => &none,
+        };
+        // Point and BSDF density of the last bounce, when the light of the emitters was also
+        // sampled there
+        let mut last_scatter: Option<(Coord, f64)> = None;
+
+        // The last bounce still follows its ray, to find the light that its BSDF samples
+        for bounce in 0..=self.max_bounces + 1 {
+            let tracker = world.intersect(&ray);
+            if let Some((_, bsdf_pdf)) = &last_scatter {
+                let distance = tracker.hit().map_or(f64::INFINITY, |hit| hit.time);
+                radiance =
+                    radiance + throughput * self.area_light(world, &ray, distance, *bsdf_pdf);
+            }
+            let Some(hit) = tracker.hit() else {
+                let background = &world.background;
+                let weight = match &last_scatter {
+                    Some((_, bsdf_pdf)) => self
+                        .emitter_sampling
+                        .bsdf_weight(*bsdf_pdf, background.pdf(&ray.dir)),
+                    None => 1.0,
+                };
+                radiance = radiance + throughput * background.radiance(&ray.dir) * weight;
+                break;
+            };
+            let comps = hit.prepare(&ray, &tracker);
+            let material = comps.shape.material();
+            let weight = match &last_scatter {
+                Some((from, bsdf_pdf)) if material.emissive != Color::black() => {
+                    let light_pdf = emitters.pdf(from, comps.shape, &comps.point, &comps.normalv);
+                    self.emitter_sampling.bsdf_weight(*bsdf_pdf, light_pdf)
+                }
+                _ => 1.0,
+            };
+            radiance = radiance + throughput * material.emissive * weight;
+            last_scatter = None;
+            if bounce > self.max_bounces {
+                break;
+            }
+
+            if let Some(bsdf) = &material.bsdf {
+                let scatter = Scatter {
+                    world,
+                    emitters,
+                    comps: &comps,
+                    bsdf,
+                    tint: material.color_at(comps.texture_coords),
+                };
+                let Some((direction, pdf)) =
+                    self.scatter(&scatter, &mut throughput, &mut radiance, rng)
+                else {
+                    break;
+                };
+                if self.next_event_estimation {
+                    last_scatter = Some((comps.point.clone(), pdf));
+                }
+                if !self.survives(bounce, &mut throughput, rng) {
+                    break;
+                }
+                ray = direction;
+                continue;
+            }
+
+            // Choose which part of the material scatters the ray
+            let choice = rng.next_f64();
+            let direction = if choice < material.reflective {
+                Ray::new(comps.over_point.clone(), comps.reflectv.clone())
+            } else if choice < material.reflective + material.transparency {
+                // The Fresnel effect decides between reflection and refraction
+                match comps.refracted_direction() {
+                    Some(refracted) if rng.next_f64() >= comps.schlick() => {
+                        Ray::new(comps.under_point.clone(), refracted)
+                    }
+                    _ => Ray::new(comps.over_point.clone(), comps.reflectv.clone()),
+                }
+            } else {
+                // The diffuse part scatters like a Lambertian BSDF, so it also aims at the
+                // emissive shapes
+                let albedo = material.color_at(comps.texture_coords) * material.diffuse;
+                let scatter = Scatter {
+                    world,
+                    emitters,
+                    comps: &comps,
+                    bsdf: &Bsdfs::Lambertian(Lambertian::new(albedo)),
+                    tint: Color::white(),
+                };
+                let Some((direction, pdf)) =
+                    self.scatter(&scatter, &mut throughput, &mut radiance, rng)
+                else {
+                    break;
+                };
+                if self.next_event_estimation {
+                    last_scatter = Some((comps.point.clone(), pdf));
+                }
+                direction
+            };
+
+            if !self.survives(bounce, &mut throughput, rng) {
+                break;
+            }
+            ray = direction;
+        }
+
+        radiance
+    }
+
+    /// Russian roulette, which randomly stops the path past `roulette_after` bounces and
+    /// compensates the paths that go on
+    fn survives(&self, bounce: usize, throughput: &mut Color, rng: &mut Rng) -> bool {
+        if bounce < self.roulette_after {
+            return true;
+        }
+        let survive = throughput.red.max(throughput.green).max(throughput.blue);
+        let survive = survive.clamp(0.05, 1.0);
+        if rng.next_f64() >= survive {
+            return false;
+        }
+        *throughput = *throughput * (1.0 / survive);
+        true
+    }
+
+    /// Adds the direct light scattered by the BSDF, and samples it for the next direction of the
+    /// path, which comes with its density. `None` when the path ends
+    fn scatter(
+        &self,
+        scatter: &Scatter,
+        throughput: &mut Color,
+        radiance: &mut Color,
+        rng: &mut Rng,
+    ) -> Option<(Ray, f64)> {
+        let Scatter {
+            world,
+            comps,
+            bsdf,
+            tint,
+            ..
+        } = *scatter;
+        // The frame follows the outward normal, so that the BSDF knows which side the path is on
+        let outward = match comps.inside {
+            true => comps.normalv.clone().negate(),
+            false => comps.normalv.clone(),
+        };
+        let frame = Frame::new(&outward);
+        let wo = frame.to_local(&comps.eyev);
+
+        if self.next_event_estimation {
+            // The emissive shapes are aimed at below, where their light is weighted with the BSDF
+            for light in &world.lights {
+                let samples = light.samples(&comps.over_point);
+                let count = samples.len() as f64;
+                for sample in samples {
+                    let origin = offset_point(comps, &sample.direction);
+                    if world.is_occluded(&origin, &sample) {
+                        continue;
+                    }
+                    let wi = frame.to_local(&sample.direction);
+                    // Area lights are also found by the ray sampled from the BSDF, as all of
+                    // their points together
+                    let weight = match light {
+                        Lights::Area(area) => self.emitter_sampling.light_weight(
+                            count * area.pdf(&comps.over_point, &sample.direction),
+                            bsdf.pdf(&wo, &wi),
+                        ),
+                        _ => 1.0,
+                    };
+                    // A white diffuse surface facing a light reflects all of its intensity, as
+                    // in the other branch, which cancels the PI of the BSDF
+                    let light = sample.intensity * (wi.z.abs() * consts::PI * weight);
+                    *radiance = *radiance + *throughput * tint * bsdf.evaluate(&wo, &wi) * light;
+                }
+            }
+            let light = self.emitter_light(scatter, &frame, &wo, rng)
+                + self.background_light(scatter, &frame, &wo, rng);
+            *radiance = *radiance + *throughput * tint * light;
+        }
+
+        let sample = bsdf.sample(&wo, rng)?;
+        *throughput = *throughput * tint * sample.weight();
+        let direction = frame.to_world(&sample.wi);
+        Some((
+            Ray::new(offset_point(comps, &direction), direction),
+            sample.pdf,
+        ))
+    }
+
+    /// Light of the area lights that the ray sampled from the BSDF hits before `distance`,
+    /// weighted for the emitter sampling strategy. An area light gives the light of its points,
+    /// spread over the solid angle that they are chosen from
+    fn area_light(&self, world: &World, ray: &Ray, distance: f64, bsdf_pdf: f64) -> Color {
+        world
+            .lights
+            .iter()
+            .filter_map(|light| match light {
+                Lights::Area(area) => Some(area),
+                _ => None,
+            })
+            .filter(|area| area.intersect(ray).is_some_and(|time| time < distance))
+            .fold(Color::black(), |sum, area| {
+                let pdf = area.pdf(&ray.origin, &ray.dir);
+                let light_pdf = area.point_count() as f64 * pdf;
+                let weight = self.emitter_sampling.bsdf_weight(bsdf_pdf, light_pdf);
+                sum + area.intensity * (consts::PI * pdf * weight)
+            })
+    }
+
+    /// Light of a random point of the emissive shapes that reaches the point and is scattered
+    /// towards `wo`, weighted for the emitter sampling strategy
+    fn emitter_light(&self, scatter: &Scatter, frame: &Frame, wo: &Vector, rng: &mut Rng) -> Color {
+        let Scatter {
+            world,
+            emitters,
+            comps,
+            bsdf,
+            ..
+        } = *scatter;
+        if emitters.is_empty() || self.emitter_sampling == EmitterSampling::Bsdf {
+            return Color::black();
+        }
+        let Some(sample) = emitters.sample(&comps.point, rng) else {
+            return Color::black();
+        };
+
+        let wi = frame.to_local(&sample.direction);
+        let value = bsdf.evaluate(wo, &wi);
+        if value == Color::black() {
+            return Color::black();
+        }
+        let ray = Ray::new(
+            offset_point(comps, &sample.direction),
+            sample.direction.clone(),
+        );
+        let tracker = world.intersect(&ray);
+        if !tracker
+            .hit()
+            .is_some_and(|hit| Emitters::reaches(&sample, hit.shape, hit.time))
+        {
+            return Color::black();
+        }
+
+        let weight = self
+            .emitter_sampling
+            .light_weight(sample.pdf, bsdf.pdf(wo, &wi));
+        value * sample.emissive * (wi.z.abs() * weight / sample.pdf)
+    }
+
+    /// Light of a random direction of the background that reaches the point and is scattered
+    /// towards `wo`, weighted for the emitter sampling strategy
+    fn background_light(
+        &self,
+        scatter: &Scatter,
+        frame: &Frame,
+        wo: &Vector,
+        rng: &mut Rng,
+    ) -> Color {
+        let Scatter {
+            world, comps, bsdf, ..
+        } = *scatter;
+        if self.emitter_sampling == EmitterSampling::Bsdf {
+            return Color::black();
+        }
+        let Some((direction, pdf)) = world.background.sample(rng) else {
+            return Color::black();
+        };
+
+        let wi = frame.to_local(&direction);
+        let value = bsdf.evaluate(wo, &wi);
+        if value == Color::black() {
+            return Color::black();
+        }
+        let ray = Ray::new(offset_point(comps, &direction), direction.clone());
+        if world.intersect(&ray).hit().is_some() {
+            return Color::black();
+        }
+
+        let weight = self.emitter_sampling.light_weight(pdf, bsdf.pdf(wo, &wi));
+        value * world.background.radiance(&direction) * (wi.z.abs() * weight / pdf)
+    }
+}
+
+/// Everything about the point being scattered that the direct light needs
+struct Scatter<'a> {
+    world: &'a World,
+    emitters: &'a Emitters,
+    comps: &'a Computations<'a>,
+    bsdf: &'a Bsdfs,
+    tint: Color,
+}
+
+/// Point slightly off the surface on the side the direction leaves through, where rays that go
+/// that way start so they do not hit the surface again
+fn offset_point(comps: &Computations, direction: &Vector) -> Coord {
+    match direction.dot(&comps.normalv) > 0.0 {
+        true => comps.over_point.clone(),
+        false => comps.under_point.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test_integrator {
+    use super::*;
+    use crate::{
+        approx::approx,
+        area_light::AreaLight,
+        background::{environment::EnvironmentMap, Background},
+        bsdf::{dielectric::Dielectric, lambertian::Lambertian, principled::Principled},
+        material::Material,
+        point_light::PointLight,
+        sampling::RunningStats,
+        shapes::{group::Group, plane::Plane, sphere::Sphere, triangle::Triangle, Shapes},
+        transformations::Axis,
+    };
+    use image::hdr::HdrImage;
+    use std::f64::consts;
+
+    fn matte(color: Color) -> Material {
+        Material {
+            color,
+            ambient: 0.0,
+            diffuse: 0.8,
+            specular: 0.0,
+            ..Default::default()
+        }
+    }
+
+    /// Mean of many estimates of the same ray
+    fn average(tracer: &PathTracer, world: &World, ray: &Ray, samples: usize) -> Color {
+        let mut rng = Rng::new(17);
+        let sum = (0..samples)
+            .map(|_| tracer.color_at(world, ray, &mut rng))
+            .fold(Color::black(), |sum, color| sum + color);
+        sum * (1.0 / samples as f64)
+    }
+
+    /// Red light of many paths along the ray with one bounce, found with the emitter sampling
+    fn estimate(world: &World, ray: &Ray, sampling: EmitterSampling, seed: u64) -> RunningStats {
+        let tracer = PathTracer {
+            max_bounces: 1,
+            emitter_sampling: sampling,
+            ..Default::default()
+        };
+        let mut rng = Rng::new(seed);
+        let mut stats = RunningStats::default();
+        for _ in 0..4000 {
+            stats.add(tracer.color_at(world, ray, &mut rng).red);
+        }
+        stats
+    }
+
+    #[test]
+    fn emissive_surface_seen_directly() {
+        let mut lamp = Sphere::default();
+        lamp.material.emissive = Color::new(2.0, 1.0, 0.5);
+        let world = World::new(vec![Shapes::Sphere(lamp)]);
+
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let color = PathTracer::default().color_at(&world, &ray, &mut Rng::new(0));
+        assert_eq!(color, Color::new(2.0, 1.0, 0.5));
+
+        let ray = Ray::from(((0, 2, -5), (0, 0, 1)));
+        let color = PathTracer::default().color_at(&world, &ray, &mut Rng::new(0));
+        assert_eq!(color, Color::black());
+    }
+
+    #[test]
+    fn direct_light_matches_whitted() {
+        let floor = Plane {
+            material: matte(Color::new(0.9, 0.5, 0.3)),
+            ..Default::default()
+        };
+        let mut world = World::new(vec![Shapes::Plane(floor)]);
+        world.lights = vec![PointLight::new(Coord::from((-3, 6, -2)), Color::white()).into()];
+
+        let tracer = PathTracer {
+            max_bounces: 0,
+            ..Default::default()
+        };
+        let ray = Ray::new(
+            Coord::new(0.0, 1.0, -3.0),
+            Vector::new(0.0, -1.0, 2.0).normalize(),
+        );
+        let color = tracer.color_at(&world, &ray, &mut Rng::new(0));
+        assert_eq!(color, world.color_at(&ray));
+    }
+
+    #[test]
+    fn area_light_matches_whitted() {
+        let floor = Plane {
+            material: matte(Color::new(0.9, 0.5, 0.3)),
+            ..Default::default()
+        };
+        let mut ball = Sphere::default();
+        ball.transformation.translate((0, 1, 0));
+        ball.material = matte(Color::white());
+        let mut world = World::new(vec![Shapes::Plane(floor), Shapes::Sphere(ball)]);
+        world.lights = vec![AreaLight::disk(
+            Coord::new(1.0, 5.0, -1.0),
+            Vector::new(0.0, -1.0, 0.0),
+            1.0,
+            Color::white(),
+        )
+        .into()];
+
+        // Only the points of the light, which Whitted uses too
+        let tracer = PathTracer {
+            max_bounces: 0,
+            emitter_sampling: EmitterSampling::Light,
+            ..Default::default()
+        };
+        // On the edge of the shadow of the ball
+        let ray = Ray::new(
+            Coord::new(-1.0, 1.0, -3.0),
+            Vector::new(0.0, -1.0, 2.0).normalize(),
+        );
+        let color = tracer.color_at(&world, &ray, &mut Rng::new(0));
+        assert_eq!(color, world.color_at(&ray));
+    }
+
+    #[test]
+    fn light_bounces_inside_emissive_sphere() {
+        // Every bounce inside a sphere that emits `E` with albedo `a` adds `E * a^n`, which sums
+        // up to `E / (1 - a)`. Russian roulette must not change that on average
+        let mut room = Sphere::default();
+        room.transformation.scale((10, 10, 10));
+        room.material = Material {
+            emissive: Color::new(0.5, 0.5, 0.5),
+            ..matte(Color::new(0.625, 0.625, 0.625))
+        };
+        let world = World::new(vec![Shapes::Sphere(room)]);
+
+        let tracer = PathTracer {
+            max_bounces: 100,
+            roulette_after: 2,
+            next_event_estimation: false,
+            ..Default::default()
+        };
+        let ray = Ray::from(((0, 0, 0), (0, 0, 1)));
+        let color = average(&tracer, &world, &ray, 4000);
+        assert!((color.red - 1.0).abs() < 0.05, "{color:?}");
+    }
+
+    #[test]
+    fn indirect_light_bleeds_color() {
+        // A white floor next to a red wall, both lit by a white light
+        let floor = Plane {
+            material: matte(Color::white()),
+            ..Default::default()
+        };
+        let mut wall = Plane::default();
+        wall.transformation
+            .rotate(Axis::Z, consts::FRAC_PI_2)
+            .translate((1, 0, 0));
+        wall.material = matte(Color::new(1.0, 0.0, 0.0));
+        let mut world = World::new(vec![Shapes::Plane(floor), Shapes::Plane(wall)]);
+        world.lights = vec![PointLight::new(Coord::from((-5, 5, 0)), Color::white()).into()];
+
+        let ray = Ray::new(
+            Coord::new(0.5, 0.5, -1.0),
+            Vector::new(0.0, -0.5, 1.0).normalize(),
+        );
+        let direct = PathTracer {
+            max_bounces: 0,
+            ..Default::default()
+        }
+        .color_at(&world, &ray, &mut Rng::new(0));
+        assert_eq!(direct, world.color_at(&ray));
+
+        // The light reflected by the wall turns the floor red
+        let color = average(&PathTracer::default(), &world, &ray, 500);
+        assert!(approx(color.green, direct.green) && approx(color.blue, direct.blue));
+        assert!(color.red > direct.red + 0.05, "{color:?} {direct:?}");
+    }
+
+    #[test]
+    fn lambertian_bsdf_matches_matte_material() {
+        let world = |material: Material| {
+            let mut world = World::new(vec![Shapes::Plane(Plane {
+                material,
+                ..Default::default()
+            })]);
+            world.lights = vec![PointLight::new(Coord::from((-3, 6, -2)), Color::white()).into()];
+            world
+        };
+        let color = Color::new(0.9, 0.5, 0.3);
+        let matte = world(matte(color));
+        let lambertian = world(Material {
+            bsdf: Some(Lambertian::new(color * 0.8).into()),
+            ..Default::default()
+        });
+
+        let ray = Ray::new(
+            Coord::new(0.0, 1.0, -3.0),
+            Vector::new(0.0, -1.0, 2.0).normalize(),
+        );
+        let tracer = PathTracer {
+            max_bounces: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            tracer.color_at(&lambertian, &ray, &mut Rng::new(0)),
+            tracer.color_at(&matte, &ray, &mut Rng::new(0))
+        );
+    }
+
+    #[test]
+    fn smooth_glass_keeps_the_light() {
+        // Every path into a glass ball comes out again and reaches the glowing room around it
+        let mut room = Sphere::default();
+        room.transformation.scale((10, 10, 10));
+        room.material = Material {
+            emissive: Color::white(),
+            ..matte(Color::black())
+        };
+        let mut ball = Sphere::default();
+        ball.material.bsdf = Some(Dielectric::new(1.5, 0.0).into());
+        let world = World::new(vec![Shapes::Sphere(room), Shapes::Sphere(ball)]);
+
+        let tracer = PathTracer {
+            max_bounces: 64,
+            ..Default::default()
+        };
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let color = average(&tracer, &world, &ray, 2000);
+        assert!((color.red - 1.0).abs() < 0.02, "{color:?}");
+    }
+
+    #[test]
+    fn multiple_importance_sampling_reduces_noise() {
+        // A glossy floor under a tiny bright lamp, which is best found by aiming at it, and a big
+        // dim one in its reflection, which is best found by following the BSDF
+        let floor = Plane {
+            material: Material {
+                bsdf: Some(Principled::new(Color::white(), 0.0, 0.2).into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let lamp = |position: (f64, f64, f64), radius: f64, emissive: f64| {
+            let mut sphere = Sphere::default();
+            sphere
+                .transformation
+                .scale((radius, radius, radius))
+                .translate(position);
+            sphere.material = Material {
+                emissive: Color::new(emissive, emissive, emissive),
+                ..matte(Color::black())
+            };
+            Shapes::Sphere(sphere)
+        };
+        let world = World::new(vec![
+            Shapes::Plane(floor),
+            lamp((2.0, 1.0, 0.0), 0.05, 400.0),
+            lamp((0.0, 3.0, 6.0), 2.0, 2.0),
+        ]);
+
+        let ray = Ray::new(
+            Coord::new(0.0, 1.0, -2.0),
+            Vector::new(0.0, -1.0, 2.0).normalize(),
+        );
+        let bsdf = estimate(&world, &ray, EmitterSampling::Bsdf, 23);
+        let light = estimate(&world, &ray, EmitterSampling::Light, 23);
+        let mis = estimate(&world, &ray, EmitterSampling::Mis, 23);
+
+        // Both find the tiny lamp, so they agree on the mean up to their noise
+        let error = mis.standard_error().hypot(light.standard_error());
+        assert!(
+            (mis.mean - light.mean).abs() < 3.0 * error,
+            "{mis:?} {light:?}"
+        );
+        assert!(mis.variance() < light.variance(), "{mis:?} {light:?}");
+        assert!(mis.variance() < bsdf.variance(), "{mis:?} {bsdf:?}");
+    }
+
+    #[test]
+    fn multiple_importance_sampling_of_area_lights() {
+        // A glossy floor under a small bright light and a big dim one, seen through a pixel
+        let floor = Plane {
+            material: Material {
+                bsdf: Some(Principled::new(Color::white(), 0.0, 0.2).into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut world = World::new(vec![Shapes::Plane(floor)]);
+        world.lights = vec![
+            AreaLight::sphere(Coord::new(2.0, 1.0, 0.0), 0.05, Color::new(2.0, 2.0, 2.0)).into(),
+            AreaLight::rectangle(
+                Coord::new(-2.0, 3.0, 4.0),
+                Vector::new(4.0, 0.0, 0.0),
+                Vector::new(0.0, 0.0, 4.0),
+                Color::new(0.5, 0.5, 0.5),
+            )
+            .into(),
+        ];
+
+        let estimate = |emitter_sampling| {
+            let tracer = PathTracer {
+                max_bounces: 0,
+                emitter_sampling,
+                ..Default::default()
+            };
+            let mut rng = Rng::new(29);
+            let mut stats = RunningStats::default();
+            for _ in 0..4000 {
+                let (dx, dy) = (rng.next_f64() - 0.5, rng.next_f64() - 0.5);
+                let ray = Ray::new(
+                    Coord::new(0.0, 1.0, -2.0),
+                    Vector::new(0.02 * dx, -1.0 + 0.02 * dy, 2.0).normalize(),
+                );
+                stats.add(tracer.color_at(&world, &ray, &mut rng).red);
+            }
+            stats
+        };
+        let bsdf = estimate(EmitterSampling::Bsdf);
+        let light = estimate(EmitterSampling::Light);
+        let mis = estimate(EmitterSampling::Mis);
+
+        for stats in [&bsdf, &light] {
+            let error = mis.standard_error().hypot(stats.standard_error());
+            assert!(
+                (mis.mean - stats.mean).abs() < 3.0 * error,
+                "{mis:?} {stats:?}"
+            );
+        }
+        assert!(mis.variance() < light.variance(), "{mis:?} {light:?}");
+        assert!(mis.variance() < bsdf.variance(), "{mis:?} {bsdf:?}");
+    }
+
+    #[test]
+    fn mesh_light_over_matte_floor() {
+        // A square panel made of two glowing triangles, placed by its group
+        let triangle = |p1: (f64, f64, f64), p2: (f64, f64, f64), p3: (f64, f64, f64)| {
+            let mut triangle = Triangle::new(p1.into(), p2.into(), p3.into());
+            triangle.material.emissive = Color::new(5.0, 5.0, 5.0);
+            Shapes::Triangle(triangle)
+        };
+        let mut panel = Group::new(vec![
+            triangle((-0.5, 0.0, -0.5), (0.5, 0.0, -0.5), (0.5, 0.0, 0.5)),
+            triangle((-0.5, 0.0, -0.5), (0.5, 0.0, 0.5), (-0.5, 0.0, 0.5)),
+        ]);
+        panel.transformation.translate((0, 2, 0));
+        let world = World::new(vec![
+            Shapes::Plane(Plane {
+                material: matte(Color::white()),
+                ..Default::default()
+            }),
+            Shapes::Group(panel),
+        ]);
+
+        let ray = Ray::new(
+            Coord::new(0.0, 1.0, -2.0),
+            Vector::new(0.0, -1.0, 2.0).normalize(),
+        );
+        let bsdf = estimate(&world, &ray, EmitterSampling::Bsdf, 31);
+        let light = estimate(&world, &ray, EmitterSampling::Light, 31);
+        let mis = estimate(&world, &ray, EmitterSampling::Mis, 31);
+
+        // The floor reflects 0.8 / PI of the light of the panel, integrated over its solid angle
+        assert!((light.mean - 0.2939).abs() < 0.003, "{light:?}");
+        // Aiming at the panel finds the same light as hitting it by chance, with less noise
+        let error = light.standard_error().hypot(bsdf.standard_error());
+        assert!(
+            (light.mean - bsdf.mean).abs() < 3.0 * error,
+            "{light:?} {bsdf:?}"
+        );
+        let error = mis.standard_error().hypot(bsdf.standard_error());
+        assert!(
+            (mis.mean - bsdf.mean).abs() < 3.0 * error,
+            "{mis:?} {bsdf:?}"
+        );
+        assert!(light.variance() < bsdf.variance(), "{light:?} {bsdf:?}");
+        assert!(mis.variance() < bsdf.variance(), "{mis:?} {bsdf:?}");
+    }
+
+    #[test]
+    fn uniform_background_lights_a_sphere() {
+        let mut ball = Sphere::default();
+        ball.material.bsdf = Some(Lambertian::new(Color::new(0.5, 0.5, 0.5)).into());
+        let mut world = World::new(vec![Shapes::Sphere(ball)]);
+        world.background = Background::Color(Color::white());
+
+        // A convex surface sees the background all around its normal
+        let ray = Ray::new(Coord::new(0.3, 0.2, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let color = average(&PathTracer::default(), &world, &ray, 2000);
+        assert!((color.red - 0.5).abs() < 0.01, "{color:?}");
+        let missed = Ray::new(Coord::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(
+            average(&PathTracer::default(), &world, &missed, 1),
+            Color::white()
+        );
+    }
+
+    #[test]
+    fn environment_map_importance_sampling() {
+        // Dim sky with a small bright sun over a matte floor
+        let mut image = HdrImage::with_size(16, 32);
+        for row in 0..16 {
+            for col in 0..32 {
+                image.set_pixel_color((row, col), Color::new(0.2, 0.2, 0.2));
+            }
+        }
+        image.set_pixel_color((4, 20), Color::new(200.0, 200.0, 200.0));
+        let map = EnvironmentMap::new(image);
+
+        // Light reaching the floor, summed over a grid finer than the pixels of the map
+        let (rows, cols) = (256, 512);
+        let (d_theta, d_phi) = (consts::PI / rows as f64, 2.0 * consts::PI / cols as f64);
+        let irradiance: f64 = (0..rows / 2 * cols)
+            .map(|i| {
+                let theta = ((i / cols) as f64 + 0.5) * d_theta;
+                let phi = ((i % cols) as f64 + 0.5) * d_phi;
+                let direction = Vector::new(
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                    theta.sin() * phi.cos(),
+                );
+                map.radiance(&direction).red * theta.cos() * theta.sin() * d_theta * d_phi
+            })
+            .sum();
+        let expected = 0.8 / consts::PI * irradiance;
+
+        let mut world = World::new(vec![Shapes::Plane(Plane {
+            material: matte(Color::white()),
+            ..Default::default()
+        })]);
+        world.background = Background::Map(map);
+        let ray = Ray::new(
+            Coord::new(0.0, 1.0, -2.0),
+            Vector::new(0.0, -1.0, 2.0).normalize(),
+        );
+        let bsdf = estimate(&world, &ray, EmitterSampling::Bsdf, 41);
+        let light = estimate(&world, &ray, EmitterSampling::Light, 41);
+        let mis = estimate(&world, &ray, EmitterSampling::Mis, 41);
+
+        for stats in [&bsdf, &light, &mis] {
+            assert!(
+                (stats.mean - expected).abs() < 3.0 * stats.standard_error(),
+                "{stats:?} {expected}"
+            );
+        }
+        assert!(light.variance() < bsdf.variance(), "{light:?} {bsdf:?}");
+        assert!(mis.variance() < bsdf.variance(), "{mis:?} {bsdf:?}");
+    }
+}
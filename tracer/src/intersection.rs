@@ -1,2 +1,3 @@
+pub mod computations;
 pub mod intersections;
 pub mod single_intersection;
@@ -0,0 +1,243 @@
+use crate::{
+    point::{coord::Coord, vector::Vector},
+    shapes::Shapes,
+};
+
+/// Values related to an intersection that are needed when shading the point that was hit
+#[derive(Debug)]
+pub struct Computations<'a> {
+    pub time: f64,
+    pub shape: &'a Shapes,
+    pub point: Coord,
+    /// The hit point moved slightly along the normal, used as the origin of the rays that leave
+    /// the surface so that they do not intersect the surface itself due to floating point errors
+    pub over_point: Coord,
+    /// The hit point moved slightly below the surface, used as the origin of refracted rays
+    pub under_point: Coord,
+    pub eyev: Vector,
+    pub normalv: Vector,
+    /// Direction of the ray after bouncing off the surface
+    pub reflectv: Vector,
+    /// Whether the hit happened from inside of the shape
+    pub inside: bool,
+    /// Refractive index of the material the ray is leaving
+    pub n1: f64,
+    /// Refractive index of the material the ray is entering
+    pub n2: f64,
+    /// Coordinates of the hit in the texture of the shape, if it has any
+    pub texture_coords: Option<(f64, f64)>,
+}
+
+impl<'a> Computations<'a> {
+    /// Fraction of the light that is reflected by the surface, approximated using Schlick's
+    /// formula for the Fresnel effect
+    pub fn schlick(&self) -> f64 {
+        let mut cos = self.eyev.dot(&self.normalv);
+
+        if self.n1 > self.n2 {
+            let ratio = self.n1 / self.n2;
+            let sin2_t = ratio * ratio * (1.0 - cos * cos);
+            // Total internal reflection
+            if sin2_t > 1.0 {
+                return 1.0;
+            }
+            cos = (1.0 - sin2_t).sqrt();
+        }
+
+        let r0 = ((self.n1 - self.n2) / (self.n1 + self.n2)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+    }
+
+    /// Direction of the ray after going through the surface, following Snell's law. `None` when
+    /// there is total internal reflection, and no light gets through
+    pub fn refracted_direction(&self) -> Option<Vector> {
+        // sin(theta_t) = sin(theta_i) * n1 / n2
+        let n_ratio = self.n1 / self.n2;
+        let cos_i = self.eyev.dot(&self.normalv);
+        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return None;
+        }
+
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some(self.normalv.scalar_mult(n_ratio * cos_i - cos_t) - self.eyev.scalar_mult(n_ratio))
+    }
+}
+
+#[cfg(test)]
+mod test_computations {
+    use crate::{
+        approx::approx,
+        intersection::{
+            intersections::IntersectionTracker, single_intersection::SingleIntersection,
+        },
+        material::Material,
+        point::{coord::Coord, vector::Vector},
+        ray::Ray,
+        shapes::{sphere::Sphere, Shapes},
+    };
+
+    fn glass_sphere() -> Sphere {
+        Sphere {
+            material: Material {
+                transparency: 1.0,
+                refractive_index: 1.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn hit_from_outside() {
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let shape = Shapes::Sphere(Sphere::default());
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(4.0, &shape)]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(comps.point, Coord::from((0, 0, -1)));
+        assert_eq!(comps.eyev, Vector::from((0, 0, -1)));
+        assert_eq!(comps.normalv, Vector::from((0, 0, -1)));
+        assert!(!comps.inside);
+    }
+
+    #[test]
+    fn hit_from_inside() {
+        let ray = Ray::from(((0, 0, 0), (0, 0, 1)));
+        let shape = Shapes::Sphere(Sphere::default());
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(1.0, &shape)]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(comps.point, Coord::from((0, 0, 1)));
+        assert_eq!(comps.eyev, Vector::from((0, 0, -1)));
+        assert_eq!(comps.normalv, Vector::from((0, 0, -1)));
+        assert!(comps.inside);
+    }
+
+    #[test]
+    fn over_point_above_surface() {
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let mut sphere = Sphere::default();
+        sphere.transformation.translate((0, 0, 1));
+        let shape = Shapes::Sphere(sphere);
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(5.0, &shape)]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert!(comps.over_point.z < -crate::approx::EPSILON / 2.0);
+        assert!(comps.point.z > comps.over_point.z);
+    }
+
+    #[test]
+    fn under_point_below_surface() {
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let mut sphere = glass_sphere();
+        sphere.transformation.translate((0, 0, 1));
+        let shape = Shapes::Sphere(sphere);
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(5.0, &shape)]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert!(comps.under_point.z > crate::approx::EPSILON / 2.0);
+        assert!(comps.point.z < comps.under_point.z);
+    }
+
+    #[test]
+    fn reflection_vector() {
+        let h = 2.0f64.sqrt() / 2.0;
+        let ray = Ray::from(((0.0, h, -1.0 - h), (0.0, -h, h)));
+        let shape = Shapes::Sphere(Sphere::default());
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(1.0, &shape)]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(comps.normalv, Vector::from((0, 0, -1)));
+        assert_eq!(comps.reflectv, Vector::from((0.0, -h, -h)));
+    }
+
+    #[test]
+    fn refractive_indices_between_shapes() {
+        let mut a = glass_sphere();
+        a.transformation.scale((2, 2, 2));
+        a.material.refractive_index = 1.5;
+
+        let mut b = glass_sphere();
+        b.transformation.translate((0.0, 0.0, -0.25));
+        b.material.refractive_index = 2.0;
+
+        let mut c = glass_sphere();
+        c.transformation.translate((0.0, 0.0, 0.25));
+        c.material.refractive_index = 2.5;
+
+        let (a, b, c) = (Shapes::Sphere(a), Shapes::Sphere(b), Shapes::Sphere(c));
+        let ray = Ray::from(((0, 0, -4), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![
+            SingleIntersection::new(2.0, &a),
+            SingleIntersection::new(2.75, &b),
+            SingleIntersection::new(3.25, &c),
+            SingleIntersection::new(4.75, &b),
+            SingleIntersection::new(5.25, &c),
+            SingleIntersection::new(6.0, &a),
+        ]);
+
+        let expected = [
+            (1.0, 1.5),
+            (1.5, 2.0),
+            (2.0, 2.5),
+            (2.5, 2.5),
+            (2.5, 1.5),
+            (1.5, 1.0),
+        ];
+        for (index, (n1, n2)) in expected.into_iter().enumerate() {
+            let comps = xs[index].prepare(&ray, &xs);
+            assert!(approx(comps.n1, n1));
+            assert!(approx(comps.n2, n2));
+        }
+    }
+
+    #[test]
+    fn equal_shapes_are_different_containers() {
+        // Both spheres are equal, but entering the second one must not be mistaken for leaving
+        // the first one
+        let (a, b) = (
+            Shapes::Sphere(glass_sphere()),
+            Shapes::Sphere(glass_sphere()),
+        );
+        let ray = Ray::from(((0, 0, -4), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![
+            SingleIntersection::new(3.0, &a),
+            SingleIntersection::new(3.0, &b),
+            SingleIntersection::new(5.0, &a),
+            SingleIntersection::new(5.0, &b),
+        ]);
+        let comps = xs[1].prepare(&ray, &xs);
+        assert!(approx(comps.n1, 1.5));
+        assert!(approx(comps.n2, 1.5));
+    }
+
+    #[test]
+    fn schlick_total_internal_reflection() {
+        let h = 2.0f64.sqrt() / 2.0;
+        let shape = Shapes::Sphere(glass_sphere());
+        let ray = Ray::from(((0.0, 0.0, h), (0, 1, 0)));
+        let xs = IntersectionTracker::new(vec![
+            SingleIntersection::new(-h, &shape),
+            SingleIntersection::new(h, &shape),
+        ]);
+        let comps = xs[1].prepare(&ray, &xs);
+        assert!(approx(comps.schlick(), 1.0));
+    }
+
+    #[test]
+    fn schlick_perpendicular() {
+        let shape = Shapes::Sphere(glass_sphere());
+        let ray = Ray::from(((0, 0, 0), (0, 1, 0)));
+        let xs = IntersectionTracker::new(vec![
+            SingleIntersection::new(-1.0, &shape),
+            SingleIntersection::new(1.0, &shape),
+        ]);
+        let comps = xs[1].prepare(&ray, &xs);
+        assert!(approx(comps.schlick(), 0.04));
+    }
+
+    #[test]
+    fn schlick_small_angle() {
+        let shape = Shapes::Sphere(glass_sphere());
+        let ray = Ray::from(((0.0, 0.99, -2.0), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(1.8589, &shape)]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert!(approx(comps.schlick(), 0.48873));
+    }
+}
@@ -1,37 +1,59 @@
 use crate::intersection::single_intersection::SingleIntersection;
 use std::ops::Index;
 
-/*
-* TODO:
-*   - The intersection tracker should be always kept in order
-*     (maybe make it a binaryheap), this way we can find the min
-*     element much faster
-* */
-
+/// Collection of the intersections of a ray with one or many shapes
+///
+/// The intersections are always kept sorted by time, so the first element is the one that
+/// happened the earliest
 pub struct IntersectionTracker<'a> {
     intersections: Vec<SingleIntersection<'a>>,
 }
 
 impl<'a> IntersectionTracker<'a> {
-    pub fn new(intersections: Vec<SingleIntersection<'a>>) -> Self {
+    pub fn new(mut intersections: Vec<SingleIntersection<'a>>) -> Self {
+        intersections.sort_by(|a, b| a.time.total_cmp(&b.time));
         Self { intersections }
     }
 
     pub fn push(&mut self, intersection: SingleIntersection<'a>) {
-        self.intersections.push(intersection)
+        let index = self
+            .intersections
+            .partition_point(|inter| inter.time <= intersection.time);
+        self.intersections.insert(index, intersection)
+    }
+
+    /// Add all of the intersections in another tracker to this one
+    pub fn merge(&mut self, other: IntersectionTracker<'a>) {
+        other
+            .intersections
+            .into_iter()
+            .for_each(|inter| self.push(inter));
     }
 
+    pub fn len(&self) -> usize {
+        self.intersections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intersections.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, SingleIntersection<'a>> {
+        self.intersections.iter()
+    }
+
+    /// The intersection with the smallest positive time, this is what the ray will "see"
     pub fn hit(&self) -> Option<&SingleIntersection<'a>> {
-        let mut f = None;
-        let mut min_time = f64::INFINITY;
-        for inter in &self.intersections {
-            let time = inter.time;
-            if time < min_time && time > 0.0 {
-                min_time = time;
-                f = Some(inter);
-            }
-        }
-        f
+        self.intersections.iter().find(|inter| inter.time > 0.0)
+    }
+}
+
+impl<'a> IntoIterator for IntersectionTracker<'a> {
+    type Item = SingleIntersection<'a>;
+    type IntoIter = std::vec::IntoIter<SingleIntersection<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.intersections.into_iter()
     }
 }
 
@@ -41,3 +63,41 @@ impl<'a> Index<usize> for IntersectionTracker<'a> {
         self.intersections.index(index)
     }
 }
+
+#[cfg(test)]
+mod test_intersections {
+    use super::*;
+    use crate::shapes::{sphere::Sphere, Shapes};
+
+    #[test]
+    fn kept_in_order() {
+        let shape = Shapes::Sphere(Sphere::default());
+        let mut tracker = IntersectionTracker::new(vec![
+            SingleIntersection::new(5.0, &shape),
+            SingleIntersection::new(-3.0, &shape),
+        ]);
+        tracker.push(SingleIntersection::new(2.0, &shape));
+        tracker.push(SingleIntersection::new(7.0, &shape));
+
+        let times: Vec<f64> = tracker.iter().map(|inter| inter.time).collect();
+        assert_eq!(times, vec![-3.0, 2.0, 5.0, 7.0]);
+    }
+
+    #[test]
+    fn hit_is_lowest_positive() {
+        let shape = Shapes::Sphere(Sphere::default());
+        let tracker = IntersectionTracker::new(vec![
+            SingleIntersection::new(5.0, &shape),
+            SingleIntersection::new(7.0, &shape),
+            SingleIntersection::new(-3.0, &shape),
+            SingleIntersection::new(2.0, &shape),
+        ]);
+        assert_eq!(tracker.hit().map(|inter| inter.time), Some(2.0));
+
+        let tracker = IntersectionTracker::new(vec![
+            SingleIntersection::new(-2.0, &shape),
+            SingleIntersection::new(-1.0, &shape),
+        ]);
+        assert!(tracker.hit().is_none());
+    }
+}
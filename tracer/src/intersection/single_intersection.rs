@@ -1,13 +1,107 @@
-use crate::shapes::Shapes;
+use crate::{
+    intersection::{computations::Computations, intersections::IntersectionTracker},
+    ray::Ray,
+    shapes::{Hittable, Shapes},
+    transformations::{normal_to_world, world_to_object, TransformationMatrix},
+};
 
 #[derive(Debug)]
 pub struct SingleIntersection<'a> {
     pub time: f64,
     pub shape: &'a Shapes,
+    /// Barycentric coordinates of the hit, only known for triangles
+    pub uv: Option<(f64, f64)>,
+    /// Transformations of the groups (or other shapes with children) that contain the shape, from
+    /// the outermost to the innermost one
+    pub parents: Vec<&'a TransformationMatrix>,
 }
 
 impl<'a> SingleIntersection<'a> {
     pub fn new(time: f64, shape: &'a Shapes) -> Self {
-        Self { time, shape }
+        Self {
+            time,
+            shape,
+            uv: None,
+            parents: vec![],
+        }
+    }
+
+    pub fn with_uv(time: f64, shape: &'a Shapes, u: f64, v: f64) -> Self {
+        Self {
+            time,
+            shape,
+            uv: Some((u, v)),
+            parents: vec![],
+        }
+    }
+
+    /// Pre-compute the values needed to shade the point where the ray hit the shape
+    ///
+    /// The tracker must contain all of the intersections of the ray (including this one), they
+    /// are used to find which shapes the ray is inside of, and so, the refractive indices on both
+    /// sides of the surface
+    pub fn prepare(&self, ray: &Ray, tracker: &IntersectionTracker<'a>) -> Computations<'a> {
+        let point = ray.position_at(self.time);
+        let eyev = ray.dir.clone().negate();
+        let object_point = world_to_object(&self.parents, &point);
+        let object_normal = self.shape.normal_with_uv(&object_point, self.uv);
+        let mut normalv = normal_to_world(&self.parents, object_normal);
+
+        // When the eye is inside the shape, the normal needs to point towards the eye
+        let inside = normalv.dot(&eyev) < 0.0;
+        if inside {
+            normalv = normalv.negate();
+        }
+
+        let (n1, n2) = self.refractive_indices(tracker);
+        let offset = normalv.scalar_mult(crate::approx::EPSILON);
+
+        Computations {
+            time: self.time,
+            shape: self.shape,
+            over_point: point.add_vector(&offset),
+            under_point: point.subtract_vector(&offset),
+            reflectv: ray.dir.reflect(&normalv),
+            point,
+            eyev,
+            normalv,
+            inside,
+            n1,
+            n2,
+            texture_coords: self.shape.texture_coords(self.uv),
+        }
+    }
+
+    /// Refractive index of the material that the ray is leaving, and of the one it is entering
+    fn refractive_indices(&self, tracker: &IntersectionTracker<'a>) -> (f64, f64) {
+        fn last_index(containers: &[&Shapes]) -> f64 {
+            containers
+                .last()
+                .map_or(1.0, |shape| shape.material().refractive_index)
+        }
+
+        // Shapes that the ray is currently inside of, in the order it entered them. Shapes are
+        // compared by address, as two distinct shapes may be equal
+        let mut containers: Vec<&Shapes> = vec![];
+        for inter in tracker.iter() {
+            let is_self = std::ptr::eq(inter, self);
+            let n1 = last_index(&containers);
+
+            match containers
+                .iter()
+                .position(|&shape| std::ptr::eq(shape, inter.shape))
+            {
+                Some(index) => {
+                    containers.remove(index);
+                }
+                None => containers.push(inter.shape),
+            }
+
+            if is_self {
+                return (n1, last_index(&containers));
+            }
+        }
+
+        (1.0, 1.0)
     }
 }
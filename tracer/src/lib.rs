@@ -0,0 +1,22 @@
+pub mod approx;
+pub mod area_light;
+pub mod background;
+pub mod bounding_box;
+pub mod bsdf;
+pub mod bvh;
+pub mod camera;
+pub mod emitter;
+pub mod integrator;
+pub mod intersection;
+pub mod light;
+pub mod material;
+pub mod matrix;
+pub mod mesh;
+pub mod point;
+pub mod point_light;
+pub mod ray;
+pub mod render;
+pub mod sampling;
+pub mod shapes;
+pub mod transformations;
+pub mod world;
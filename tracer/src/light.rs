@@ -0,0 +1,106 @@
+use directional::DirectionalLight;
+use enum_dispatch::enum_dispatch;
+use image::color::Color;
+use spot::SpotLight;
+
+use crate::{
+    area_light::AreaLight,
+    point::{coord::Coord, vector::Vector},
+    point_light::PointLight,
+};
+
+pub mod directional;
+pub mod spot;
+
+/// Every kind of light source a world can have
+#[derive(Debug, Clone, PartialEq)]
+#[enum_dispatch(Light)]
+pub enum Lights {
+    Point(PointLight),
+    Area(AreaLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+}
+
+#[enum_dispatch]
+pub trait Light {
+    /// Light that reaches the point, split by the places it comes from. Shading and shadows only
+    /// deal with these, so they work the same with every kind of light
+    fn samples(&self, point: &Coord) -> Vec<LightSample>;
+}
+
+/// Light arriving at a point from one place
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightSample {
+    /// Unit vector from the point towards the light
+    pub direction: Vector,
+    /// Distance to the light, infinite for directional lights. Only shapes closer than this cast
+    /// shadows
+    pub distance: f64,
+    /// Light that arrives, after any falloff
+    pub intensity: Color,
+}
+
+impl LightSample {
+    /// Light coming from a position
+    pub fn towards(point: &Coord, position: &Coord, intensity: Color) -> Self {
+        let to_light = point.clone().vector_to(position);
+        Self {
+            distance: to_light.magnitude(),
+            direction: to_light.normalize(),
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn samples(&self, point: &Coord) -> Vec<LightSample> {
+        vec![LightSample::towards(point, &self.position, self.intensity)]
+    }
+}
+
+impl Light for AreaLight {
+    fn samples(&self, point: &Coord) -> Vec<LightSample> {
+        let points = self.points(point);
+        let intensity = self.intensity * (1.0 / points.len() as f64);
+        points
+            .iter()
+            .map(|position| LightSample::towards(point, position, intensity))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_light {
+    use super::*;
+    use crate::approx::approx;
+
+    #[test]
+    fn point_light_sample() {
+        let light: Lights = PointLight::new(Coord::new(0.0, 3.0, 4.0), Color::white()).into();
+        let samples = light.samples(&Coord::new(0.0, 0.0, 0.0));
+        assert_eq!(
+            samples,
+            vec![LightSample {
+                direction: Vector::new(0.0, 0.6, 0.8),
+                distance: 5.0,
+                intensity: Color::white(),
+            }]
+        );
+    }
+
+    #[test]
+    fn area_light_shares_its_intensity() {
+        let light: Lights =
+            AreaLight::sphere(Coord::new(0.0, 5.0, 0.0), 1.0, Color::white()).into();
+        let samples = light.samples(&Coord::new(0.0, 0.0, 0.0));
+        assert_eq!(samples.len(), 16);
+        let total = samples
+            .iter()
+            .fold(Color::black(), |sum, sample| sum + sample.intensity);
+        assert_eq!(total, Color::white());
+        assert!(samples
+            .iter()
+            .all(|sample| sample.distance >= 4.0 && approx(sample.direction.magnitude(), 1.0)));
+    }
+}
@@ -0,0 +1,49 @@
+use image::color::Color;
+
+use super::{Light, LightSample};
+use crate::point::{coord::Coord, vector::Vector};
+
+/// Light so far away that all of its rays are parallel, like the sun. It lights every point with
+/// the same intensity
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectionalLight {
+    /// Direction the light travels in
+    pub direction: Vector,
+    pub intensity: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector, intensity: Color) -> Self {
+        Self {
+            direction: direction.normalize(),
+            intensity,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn samples(&self, _point: &Coord) -> Vec<LightSample> {
+        vec![LightSample {
+            direction: self.direction.clone().negate(),
+            distance: f64::INFINITY,
+            intensity: self.intensity,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod test_directional {
+    use super::*;
+
+    #[test]
+    fn same_light_everywhere() {
+        let light = DirectionalLight::new(Vector::new(0.0, -2.0, 0.0), Color::white());
+        for point in [Coord::new(0.0, 0.0, 0.0), Coord::new(100.0, -3.0, 7.0)] {
+            let samples = light.samples(&point);
+            assert_eq!(samples.len(), 1);
+            assert_eq!(samples[0].direction, Vector::new(0.0, 1.0, 0.0));
+            assert_eq!(samples[0].distance, f64::INFINITY);
+            assert_eq!(samples[0].intensity, Color::white());
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use image::color::Color;
+
+use super::{Light, LightSample};
+use crate::point::{coord::Coord, vector::Vector};
+
+/// Light that shines from a position inside a cone, like a flashlight or a stage light
+///
+/// Unlike point lights, it gets dimmer with the square of the distance, so its intensity is the
+/// one at 1 unit away. At the edge of the cone it fades out smoothly between `inner_angle` and
+/// `angle`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotLight {
+    pub position: Coord,
+    /// Axis of the cone, the direction the light points at
+    pub direction: Vector,
+    pub intensity: Color,
+    /// Angle between the axis and the edge of the cone, in radians
+    pub angle: f64,
+    /// Angle from the axis where the light starts fading out
+    pub inner_angle: f64,
+}
+
+impl SpotLight {
+    /// Spot light that fades out over the outer quarter of its cone
+    pub fn new(position: Coord, direction: Vector, angle: f64, intensity: Color) -> Self {
+        Self {
+            position,
+            direction: direction.normalize(),
+            intensity,
+            angle,
+            inner_angle: angle * 0.75,
+        }
+    }
+
+    /// Fraction of the light that goes in a direction, 1 inside the inner cone and 0 outside the
+    /// cone
+    pub fn falloff(&self, direction: &Vector) -> f64 {
+        let cos = direction.dot(&self.direction);
+        let (outer, inner) = (self.angle.cos(), self.inner_angle.cos());
+        if inner <= outer {
+            return if cos >= outer { 1.0 } else { 0.0 };
+        }
+        let t = ((cos - outer) / (inner - outer)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn samples(&self, point: &Coord) -> Vec<LightSample> {
+        let mut sample = LightSample::towards(point, &self.position, self.intensity);
+        let falloff = self.falloff(&sample.direction.clone().negate());
+        let distance2 = (sample.distance * sample.distance).max(f64::EPSILON);
+        sample.intensity = self.intensity * (falloff / distance2);
+        vec![sample]
+    }
+}
+
+#[cfg(test)]
+mod test_spot {
+    use super::*;
+    use crate::approx::approx;
+    use std::f64::consts;
+
+    fn spot() -> SpotLight {
+        SpotLight::new(
+            Coord::new(0.0, 2.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            consts::FRAC_PI_4,
+            Color::white(),
+        )
+    }
+
+    #[test]
+    fn inverse_square_attenuation() {
+        let light = spot();
+        let near = light.samples(&Coord::new(0.0, 0.0, 0.0));
+        let far = light.samples(&Coord::new(0.0, -2.0, 0.0));
+        assert_eq!(near[0].intensity, Color::new(0.25, 0.25, 0.25));
+        assert_eq!(far[0].intensity, Color::new(0.0625, 0.0625, 0.0625));
+        assert_eq!(near[0].direction, Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(near[0].distance, 2.0);
+    }
+
+    #[test]
+    fn cone_with_smooth_edge() {
+        let light = spot();
+        let at_angle = |angle: f64| light.falloff(&Vector::new(angle.sin(), -angle.cos(), 0.0));
+        assert_eq!(at_angle(0.0), 1.0);
+        assert_eq!(at_angle(light.inner_angle - 0.01), 1.0);
+        assert_eq!(at_angle(light.angle + 0.01), 0.0);
+        assert_eq!(at_angle(consts::PI), 0.0);
+
+        // Fades out between the inner and outer angles
+        let middle = at_angle((light.angle + light.inner_angle) / 2.0);
+        assert!(middle > 0.0 && middle < 1.0);
+        assert!(at_angle(light.inner_angle + 0.05) > at_angle(light.angle - 0.05));
+
+        // Points outside the cone get no light
+        let outside = light.samples(&Coord::new(5.0, 0.0, 0.0));
+        assert!(approx(outside[0].intensity.red, 0.0));
+    }
+}
@@ -1,59 +1,65 @@
-use std::f64::{self, consts::PI};
+use std::f64;
 
-use image::{
-    canvas::Canvas,
-    color::{self, RGBAColor},
+use image::color::Color;
+use tracer::{
+    camera::Camera,
+    material::Material,
+    point::{coord::Coord, vector::Vector},
+    point_light::PointLight,
+    render::{Progress, RenderObserver, Renderer, Tile},
+    sampling::{filter::PixelFilter, AdaptiveSampling, SamplePattern, Sampler},
+    shapes::{plane::Plane, sphere::Sphere, Shapes},
+    world::World,
 };
-use point::{coord::Coord, vector::Vector};
-use ray::Ray;
-use shapes::{sphere::Sphere, Hittable};
-use transformations::Transform;
-
-pub(crate) mod approx;
-pub(crate) mod intersection;
-pub(crate) mod matrix;
-pub(crate) mod point;
-pub(crate) mod point_light;
-pub(crate) mod ray;
-pub(crate) mod shapes;
-pub(crate) mod transformations;
 
 const H: usize = 300;
 const W: usize = 300;
 
-// Draw a clock using the image api and the trasformations
+/// Prints the progress of the render on a single line
+struct ProgressLine;
+
+impl RenderObserver for ProgressLine {
+    fn tile_finished(&mut self, _: &Tile, progress: &Progress) {
+        let eta = progress.eta().unwrap_or_default().as_secs_f64();
+        eprint!("\r{:5.1}% - ETA {eta:.1}s", progress.percentage());
+    }
+}
+
+// Render a sphere on a floor, with more samples on the edges, using every available thread
 fn main() {
-    let mut canvas = image::canvas::Canvas::with_size(H, W);
+    let mut floor = Plane::default();
+    floor.material.reflective = 0.2;
 
-    let red = RGBAColor::from((0.5, 0.0, 0.5));
     let mut sphere = Sphere::default();
-    sphere
-        .transformation
-        .scale((100, 100, 1))
-        .rotate(transformations::Axis::Y, f64::consts::PI / 4.0)
-        .translate((-40, -40, 0));
-
-    for row in 0..H {
-        for col in 0..W {
-            println!("{row}/{H} - {col}/{W}");
-
-            let ray_origin = Coord::from((
-                -(W as f64 / 2.0) + (row as f64),
-                (H as f64 / 2.0) - (col as f64),
-                -5.0,
-            ));
-
-            let ray_direction = Vector::from((0, 0, 1));
-            let ray = Ray::new(ray_origin, ray_direction);
-
-            if !sphere.hit_times(&ray).is_empty() {
-                canvas.set_pixel_color(
-                    (row, col),
-                    RGBAColor::from((row as f64 / H as f64, 0.0, col as f64 / W as f64)),
-                );
-            }
-        }
-    }
+    sphere.transformation.translate((0, 1, 0));
+    sphere.material = Material {
+        color: Color::new(0.1, 0.4, 0.9),
+        diffuse: 0.7,
+        specular: 0.3,
+        ..Default::default()
+    };
+
+    let mut world = World::new(vec![Shapes::Plane(floor), Shapes::Sphere(sphere)]);
+    world.lights = vec![PointLight::new(Coord::from((-10, 10, -10)), Color::white()).into()];
+
+    let mut camera = Camera::new(W, H, f64::consts::FRAC_PI_3);
+    camera.transformation.look_at(
+        &Coord::from((0.0, 1.5, -5.0)),
+        &Coord::from((0, 1, 0)),
+        &Vector::from((0, 1, 0)),
+    );
 
-    canvas.save_as_ppm("sphere.ppm".to_string());
+    let renderer = Renderer {
+        sampler: Sampler {
+            adaptive: Some(AdaptiveSampling::new(4, 64, 0.01)),
+            ..Sampler::new(SamplePattern::Sobol, 8, PixelFilter::mitchell())
+        },
+        ..Default::default()
+    };
+    let rendered = renderer.render_with(&world, &camera, &mut ProgressLine);
+    eprintln!();
+    rendered.canvas.save_as_ppm("sphere.ppm".to_string());
+    rendered
+        .sample_heatmap()
+        .save_as_ppm("sphere_samples.ppm".to_string());
 }
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use image::{canvas::Canvas, color::Color};
+
+use crate::{bsdf::Bsdfs, light::LightSample, point::vector::Vector};
+
+/// Describes how the surface of a shape interacts with light
+///
+/// The ambient, diffuse and specular values are the weights of each of the terms in the Phong
+/// reflection model. They are usually between 0 and 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub color: Color,
+    pub ambient: f64,
+    pub diffuse: f64,
+    pub specular: f64,
+    pub shininess: f64,
+    /// How much of the surrounding scene the surface mirrors. 0 is completely matte, 1 is a
+    /// perfect mirror
+    pub reflective: f64,
+    /// How much light passes through the surface. 0 is opaque, 1 is completely transparent
+    pub transparency: f64,
+    /// How much light bends when entering the material (1 is vacuum, 1.5 is glass)
+    pub refractive_index: f64,
+    /// Light given off by the surface itself, black for surfaces that are not light sources.
+    ///
+    /// Rays that hit the surface always see this light, but it only lights other surfaces when it
+    /// is on a sphere (scaled the same along every axis), a cube, a triangle or a smooth triangle,
+    /// alone or inside groups. Planes, cylinders, cones, stretched spheres and the shapes of CSG
+    /// operations do not light other surfaces, as their area cannot be sampled
+    pub emissive: Color,
+    /// Image wrapped around the surface, its colors are multiplied by the color of the material.
+    /// Only used on shapes that have texture coordinates
+    pub texture: Option<Arc<Canvas>>,
+    /// Physically based model of the surface for the path tracer, which then ignores the Phong,
+    /// reflective and transparency values. The color and texture still tint it
+    pub bsdf: Option<Bsdfs>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            color: Color::white(),
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.9,
+            shininess: 200.0,
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            emissive: Color::black(),
+            texture: None,
+            bsdf: None,
+        }
+    }
+}
+
+impl Material {
+    /// Color of the material itself at some texture coordinates (u, v), where (0, 0) is the
+    /// bottom left corner of the texture and (1, 1) the top right one
+    pub fn color_at(&self, texture_coords: Option<(f64, f64)>) -> Color {
+        let (Some(texture), Some((u, v))) = (&self.texture, texture_coords) else {
+            return self.color;
+        };
+        if texture.width == 0 || texture.height == 0 {
+            return self.color;
+        }
+
+        // Coordinates outside of the texture wrap around, while 1 stays on the last pixel
+        let wrap = |coordinate: f64| match (0.0..=1.0).contains(&coordinate) {
+            true => coordinate,
+            false => coordinate.rem_euclid(1.0),
+        };
+        let (u, v) = (wrap(u), wrap(v));
+        let col = (u * (texture.width - 1) as f64).round() as usize;
+        let row = ((1.0 - v) * (texture.height - 1) as f64).round() as usize;
+
+        texture
+            .get_color_at((row, col))
+            .map_or(self.color, |&pixel| self.color * Color::from(pixel))
+    }
+
+    /// Color of the surface at some point, as seen from the eye, using the Phong reflection model
+    ///
+    /// When the point is in shadow, only the ambient term contributes to the color
+    pub fn lighting(
+        &self,
+        light: &LightSample,
+        eyev: &Vector,
+        normalv: &Vector,
+        in_shadow: bool,
+        texture_coords: Option<(f64, f64)>,
+    ) -> Color {
+        let effective_color = self.color_at(texture_coords) * light.intensity;
+        let ambient = effective_color * self.ambient;
+        if in_shadow {
+            return ambient;
+        }
+
+        let lightv = light.direction.clone();
+
+        // A negative dot product means that the light is on the other side of the surface
+        let light_dot_normal = lightv.dot(normalv);
+        if light_dot_normal < 0.0 {
+            return ambient;
+        }
+        let diffuse = effective_color * self.diffuse * light_dot_normal;
+
+        // A negative dot product means that the light reflects away from the eye
+        let reflect_dot_eye = lightv.negate().reflect(normalv).dot(eyev);
+        let specular = match reflect_dot_eye <= 0.0 {
+            true => Color::black(),
+            false => light.intensity * self.specular * reflect_dot_eye.powf(self.shininess),
+        };
+
+        ambient + diffuse + specular
+    }
+}
+
+#[cfg(test)]
+mod test_material {
+    use super::*;
+    use crate::{light::Light, point::coord::Coord, point_light::PointLight};
+
+    fn setup() -> (Material, Coord) {
+        (Material::default(), Coord::from((0, 0, 0)))
+    }
+
+    #[test]
+    fn eye_between_light_and_surface() {
+        let (m, position) = setup();
+        let eyev = Vector::from((0, 0, -1));
+        let normalv = Vector::from((0, 0, -1));
+        let light = PointLight::new(Coord::from((0, 0, -10)), Color::white());
+        let result = m.lighting(&light.samples(&position)[0], &eyev, &normalv, false, None);
+        assert_eq!(result, Color::from((1.9, 1.9, 1.9)));
+    }
+
+    #[test]
+    fn eye_offset_45() {
+        let (m, position) = setup();
+        let eyev = Vector::from((0.0, 2.0f64.sqrt() / 2.0, -(2.0f64.sqrt()) / 2.0));
+        let normalv = Vector::from((0, 0, -1));
+        let light = PointLight::new(Coord::from((0, 0, -10)), Color::white());
+        let result = m.lighting(&light.samples(&position)[0], &eyev, &normalv, false, None);
+        assert_eq!(result, Color::from((1.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn light_offset_45() {
+        let (m, position) = setup();
+        let eyev = Vector::from((0, 0, -1));
+        let normalv = Vector::from((0, 0, -1));
+        let light = PointLight::new(Coord::from((0, 10, -10)), Color::white());
+        let result = m.lighting(&light.samples(&position)[0], &eyev, &normalv, false, None);
+        assert_eq!(result, Color::from((0.7364, 0.7364, 0.7364)));
+    }
+
+    #[test]
+    fn eye_in_reflection_path() {
+        let (m, position) = setup();
+        let eyev = Vector::from((0.0, -(2.0f64.sqrt()) / 2.0, -(2.0f64.sqrt()) / 2.0));
+        let normalv = Vector::from((0, 0, -1));
+        let light = PointLight::new(Coord::from((0, 10, -10)), Color::white());
+        let result = m.lighting(&light.samples(&position)[0], &eyev, &normalv, false, None);
+        assert_eq!(result, Color::from((1.6364, 1.6364, 1.6364)));
+    }
+
+    #[test]
+    fn light_behind_surface() {
+        let (m, position) = setup();
+        let eyev = Vector::from((0, 0, -1));
+        let normalv = Vector::from((0, 0, -1));
+        let light = PointLight::new(Coord::from((0, 0, 10)), Color::white());
+        let result = m.lighting(&light.samples(&position)[0], &eyev, &normalv, false, None);
+        assert_eq!(result, Color::from((0.1, 0.1, 0.1)));
+    }
+
+    #[test]
+    fn surface_in_shadow() {
+        let (m, position) = setup();
+        let eyev = Vector::from((0, 0, -1));
+        let normalv = Vector::from((0, 0, -1));
+        let light = PointLight::new(Coord::from((0, 0, -10)), Color::white());
+        let result = m.lighting(&light.samples(&position)[0], &eyev, &normalv, true, None);
+        assert_eq!(result, Color::from((0.1, 0.1, 0.1)));
+    }
+
+    #[test]
+    fn texture_color() {
+        let mut texture = Canvas::with_size(2, 2);
+        texture.set_pixel_color((0, 0), (255, 0, 0));
+        texture.set_pixel_color((0, 1), (0, 255, 0));
+        texture.set_pixel_color((1, 0), (0, 0, 255));
+        texture.set_pixel_color((1, 1), (255, 255, 255));
+
+        let m = Material {
+            color: Color::from((0.5, 0.5, 0.5)),
+            texture: Some(Arc::new(texture)),
+            ..Default::default()
+        };
+
+        assert_eq!(m.color_at(None), Color::from((0.5, 0.5, 0.5)));
+        assert_eq!(m.color_at(Some((0.0, 1.0))), Color::from((0.5, 0.0, 0.0)));
+        // The corners of the texture
+        assert_eq!(m.color_at(Some((1.0, 1.0))), Color::from((0.0, 0.5, 0.0)));
+        assert_eq!(m.color_at(Some((0.0, 0.0))), Color::from((0.0, 0.0, 0.5)));
+        assert_eq!(m.color_at(Some((0.9, 0.9))), Color::from((0.0, 0.5, 0.0)));
+        assert_eq!(m.color_at(Some((0.1, 0.1))), Color::from((0.0, 0.0, 0.5)));
+        // Wraps around
+        assert_eq!(m.color_at(Some((1.9, -0.9))), Color::from((0.5, 0.5, 0.5)));
+    }
+}
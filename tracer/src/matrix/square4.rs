@@ -108,7 +108,7 @@ impl Mul<&Coord> for Matrix4x4 {
 impl Mul<&Ray> for Matrix4x4 {
     type Output = Ray;
     fn mul(self, rhs: &Ray) -> Self::Output {
-        let new_origin = self.clone().mul(&rhs.origin);
+        let new_origin = self.mul(&rhs.origin);
         let new_direction = self.mul(&rhs.dir);
         Ray::new(new_origin, new_direction)
     }
@@ -214,7 +214,7 @@ mod matrix_4x4_test {
             40, 58, 110, 102;
             16, 26, 46, 42];
 
-        assert_eq!(m4.clone() * Matrix4x4::identity(), m4);
+        assert_eq!(m4 * Matrix4x4::identity(), m4);
     }
 
     #[test]
@@ -0,0 +1,4 @@
+pub mod mtl;
+pub mod obj;
+pub mod ply;
+pub mod stl;
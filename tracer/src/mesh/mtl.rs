@@ -0,0 +1,409 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use image::{
+    canvas::{Canvas, PpmError},
+    color::Color,
+};
+
+use crate::material::Material;
+
+/// Errors found while reading a MTL material library, the line numbers start at 1
+#[derive(Debug)]
+pub enum MtlError {
+    /// The material library could not be read
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// Some value could not be read as a number
+    InvalidNumber { line: usize, value: String },
+    /// A statement has less values than it needs
+    MissingValues {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A statement describing a material came before any `newmtl` statement
+    NoMaterial { line: usize },
+    /// The texture image could not be read, only PPM images are supported
+    Texture {
+        line: usize,
+        path: PathBuf,
+        error: PpmError,
+    },
+    /// The texture file could not be opened
+    TextureFile {
+        line: usize,
+        path: PathBuf,
+        error: std::io::Error,
+    },
+}
+
+impl fmt::Display for MtlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MtlError::Io { path, error } => {
+                write!(f, "could not read {}: {error}", path.display())
+            }
+            MtlError::InvalidNumber { line, value } => {
+                write!(f, "line {line}: `{value}` is not a valid number")
+            }
+            MtlError::MissingValues {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line}: expected at least {expected} values, found {found}"
+            ),
+            MtlError::NoMaterial { line } => {
+                write!(f, "line {line}: no material was declared with `newmtl`")
+            }
+            MtlError::Texture { line, path, error } => {
+                write!(f, "line {line}: could not read {}: {error}", path.display())
+            }
+            MtlError::TextureFile { line, path, error } => {
+                write!(f, "line {line}: could not open {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for MtlError {}
+
+/// Materials read from a MTL file, by name
+#[derive(Debug, Default)]
+pub struct MtlLibrary {
+    pub materials: HashMap<String, Material>,
+    /// Numbers of the lines that were not understood, and so, ignored
+    pub ignored_lines: Vec<usize>,
+    /// Textures that could not be read, which are also in `ignored_lines`. Their materials keep
+    /// the color given by `Kd`
+    pub skipped_textures: Vec<MtlError>,
+}
+
+/// Values given to a material in the file, they are only turned into a `Material` once all of
+/// them are known, as some depend on each other
+#[derive(Debug, Default)]
+struct MtlEntry {
+    diffuse: Option<Color>,
+    ambient: Option<Color>,
+    specular: Option<Color>,
+    shininess: Option<f64>,
+    transparency: Option<f64>,
+    refractive_index: Option<f64>,
+    illumination: Option<u32>,
+    texture: Option<Arc<Canvas>>,
+}
+
+/// The ambient, specular and reflective values of a `Material` are weights rather than colors,
+/// so the colors in the file are averaged
+fn weight(color: Color) -> f64 {
+    (color.red + color.green + color.blue) / 3.0
+}
+
+impl MtlEntry {
+    fn into_material(self) -> Material {
+        let mut material = Material::default();
+
+        // Kd is the color of the diffuse reflection itself, so it should not be weighted down
+        if let Some(diffuse) = self.diffuse {
+            material.color = diffuse;
+            material.diffuse = 1.0;
+        }
+        if let Some(ambient) = self.ambient {
+            material.ambient = weight(ambient);
+        }
+        if let Some(specular) = self.specular {
+            material.specular = weight(specular);
+        }
+        if let Some(shininess) = self.shininess {
+            material.shininess = shininess;
+        }
+        if let Some(transparency) = self.transparency {
+            material.transparency = transparency;
+        }
+        if let Some(refractive_index) = self.refractive_index {
+            material.refractive_index = refractive_index;
+        }
+        material.texture = self.texture;
+
+        match self.illumination {
+            // No highlights
+            Some(0 | 1) => material.specular = 0.0,
+            // Ray traced reflections, the specular color is also the color of the reflection
+            Some(3..=7) => material.reflective = self.specular.map_or(0.0, weight),
+            _ => {}
+        }
+
+        material
+    }
+}
+
+impl MtlLibrary {
+    /// Read a material library, textures are looked for relative to the folder of the file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MtlError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|error| MtlError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        Self::parse(&content, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Parse the content of a material library, textures are looked for relative to `base`
+    pub fn parse(input: &str, base: &Path) -> Result<Self, MtlError> {
+        let mut library = MtlLibrary::default();
+        let mut entries: Vec<(String, MtlEntry)> = vec![];
+
+        for (index, line) in input.lines().enumerate() {
+            let line_number = index + 1;
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            let values: Vec<&str> = tokens.collect();
+
+            if keyword == "newmtl" {
+                entries.push((values.join(" "), MtlEntry::default()));
+                continue;
+            }
+            if keyword.starts_with('#') {
+                continue;
+            }
+
+            let known = ["Kd", "Ka", "Ks", "Ns", "d", "Tr", "Ni", "illum", "map_Kd"];
+            if !known.contains(&keyword) {
+                library.ignored_lines.push(line_number);
+                continue;
+            }
+
+            let Some((_, entry)) = entries.last_mut() else {
+                return Err(MtlError::NoMaterial { line: line_number });
+            };
+
+            match keyword {
+                "Kd" => entry.diffuse = Some(parse_color(&values, line_number)?),
+                "Ka" => entry.ambient = Some(parse_color(&values, line_number)?),
+                "Ks" => entry.specular = Some(parse_color(&values, line_number)?),
+                "Ns" => entry.shininess = Some(parse_last(&values, line_number)?),
+                // Dissolve is the opposite of transparency. It may come with a `-halo` option
+                "d" => entry.transparency = Some(1.0 - parse_last(&values, line_number)?),
+                "Tr" => entry.transparency = Some(parse_last(&values, line_number)?),
+                "Ni" => entry.refractive_index = Some(parse_last(&values, line_number)?),
+                "illum" => entry.illumination = Some(parse_last(&values, line_number)? as u32),
+                "map_Kd" => {
+                    // Texture options may come before the name of the file
+                    let Some(file) = values.last() else {
+                        return Err(MtlError::MissingValues {
+                            line: line_number,
+                            expected: 1,
+                            found: 0,
+                        });
+                    };
+                    let path = base.join(file);
+                    let texture = match std::fs::read(&path) {
+                        Ok(bytes) => Canvas::from_ppm(&bytes).map_err(|error| MtlError::Texture {
+                            line: line_number,
+                            path,
+                            error,
+                        }),
+                        Err(error) => Err(MtlError::TextureFile {
+                            line: line_number,
+                            path,
+                            error,
+                        }),
+                    };
+                    // A texture that cannot be used does not make the whole library unusable
+                    match texture {
+                        Ok(texture) => entry.texture = Some(Arc::new(texture)),
+                        Err(error) => {
+                            library.ignored_lines.push(line_number);
+                            library.skipped_textures.push(error);
+                        }
+                    }
+                }
+                _ => unreachable!("Only known keywords get here"),
+            }
+        }
+
+        library.materials = entries
+            .into_iter()
+            .map(|(name, entry)| (name, entry.into_material()))
+            .collect();
+        Ok(library)
+    }
+}
+
+fn parse_number(value: &str, line: usize) -> Result<f64, MtlError> {
+    value.parse().map_err(|_| MtlError::InvalidNumber {
+        line,
+        value: value.to_string(),
+    })
+}
+
+/// Parse the last value of a statement, options may come before it
+fn parse_last(values: &[&str], line: usize) -> Result<f64, MtlError> {
+    let value = values.last().ok_or(MtlError::MissingValues {
+        line,
+        expected: 1,
+        found: 0,
+    })?;
+    parse_number(value, line)
+}
+
+/// Colors are given as three numbers, or as a single number for gray colors
+fn parse_color(values: &[&str], line: usize) -> Result<Color, MtlError> {
+    match values {
+        [gray] => {
+            let gray = parse_number(gray, line)?;
+            Ok(Color::new(gray, gray, gray))
+        }
+        [red, green, blue, ..] => Ok(Color::new(
+            parse_number(red, line)?,
+            parse_number(green, line)?,
+            parse_number(blue, line)?,
+        )),
+        _ => Err(MtlError::MissingValues {
+            line,
+            expected: 3,
+            found: values.len(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test_mtl {
+    use super::*;
+    use crate::approx::approx;
+    use indoc::indoc;
+
+    #[test]
+    fn basic_material() {
+        let input = indoc! {"
+            # Comment
+            newmtl red_plastic
+            Ka 0.1 0.1 0.1
+            Kd 0.8 0.1 0.1
+            Ks 0.5 0.5 0.5
+            Ns 96
+            illum 2
+            Pr 0.5
+        "};
+        let library = MtlLibrary::parse(input, Path::new("")).unwrap();
+        assert_eq!(library.ignored_lines, vec![8]);
+
+        let m = &library.materials["red_plastic"];
+        assert_eq!(m.color, Color::from((0.8, 0.1, 0.1)));
+        assert!(approx(m.diffuse, 1.0));
+        assert!(approx(m.ambient, 0.1));
+        assert!(approx(m.specular, 0.5));
+        assert!(approx(m.shininess, 96.0));
+        assert!(approx(m.reflective, 0.0));
+        assert!(m.texture.is_none());
+    }
+
+    #[test]
+    fn many_materials() {
+        let input = indoc! {"
+            newmtl glass
+            Kd 0 0 0
+            Ks 0.9
+            d 0.1
+            Ni 1.5
+            illum 7
+
+            newmtl matte
+            Kd 0.5 0.5 0.5
+            Tr 0.25
+            illum 1
+        "};
+        let library = MtlLibrary::parse(input, Path::new("")).unwrap();
+        assert_eq!(library.materials.len(), 2);
+
+        let glass = &library.materials["glass"];
+        assert!(approx(glass.transparency, 0.9));
+        assert!(approx(glass.refractive_index, 1.5));
+        assert!(approx(glass.reflective, 0.9));
+
+        let matte = &library.materials["matte"];
+        assert!(approx(matte.transparency, 0.25));
+        assert!(approx(matte.specular, 0.0));
+        assert!(approx(matte.reflective, 0.0));
+    }
+
+    #[test]
+    fn texture_map() {
+        let dir = std::env::temp_dir().join("tracer_test_mtl_texture");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("checker.ppm"), "P3\n2 1\n255\n255 0 0 0 0 255\n").unwrap();
+        std::fs::write(
+            dir.join("lib.mtl"),
+            "newmtl tex\nmap_Kd -s 1 1 1 checker.ppm\n",
+        )
+        .unwrap();
+
+        let library = MtlLibrary::load(dir.join("lib.mtl")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let m = &library.materials["tex"];
+        let texture = m.texture.as_ref().unwrap();
+        assert_eq!((texture.width, texture.height), (2, 1));
+        assert_eq!(m.color_at(Some((0.0, 0.0))), Color::from((1, 0, 0)));
+        assert_eq!(m.color_at(Some((0.99, 0.0))), Color::from((0, 0, 1)));
+    }
+
+    #[test]
+    fn malformed_input() {
+        let err = MtlLibrary::parse("Kd 1 1 1", Path::new("")).unwrap_err();
+        assert!(matches!(err, MtlError::NoMaterial { line: 1 }));
+
+        let err = MtlLibrary::parse("newmtl a\nKd 1 1", Path::new("")).unwrap_err();
+        assert!(matches!(
+            err,
+            MtlError::MissingValues {
+                line: 2,
+                expected: 3,
+                found: 2
+            }
+        ));
+
+        let err = MtlLibrary::parse("newmtl a\nNs shiny", Path::new("")).unwrap_err();
+        assert!(matches!(err, MtlError::InvalidNumber { line: 2, .. }));
+
+        let err = MtlLibrary::load("nowhere/lib.mtl").unwrap_err();
+        assert!(matches!(err, MtlError::Io { path, .. } if path == Path::new("nowhere/lib.mtl")));
+    }
+
+    #[test]
+    fn unusable_textures_are_skipped() {
+        let dir = std::env::temp_dir().join("tracer_test_mtl_bad_texture");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("photo.png"), b"\x89PNG").unwrap();
+        let input = "newmtl a\nKd 1 0 0\nmap_Kd photo.png\nnewmtl b\nmap_Kd missing.ppm\n";
+        let library = MtlLibrary::parse(input, &dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(library.ignored_lines, vec![3, 5]);
+        assert!(matches!(
+            &library.skipped_textures[..],
+            [
+                MtlError::Texture {
+                    line: 3,
+                    error: PpmError::UnsupportedFormat,
+                    ..
+                },
+                MtlError::TextureFile { line: 5, .. }
+            ]
+        ));
+        let a = &library.materials["a"];
+        assert!(a.texture.is_none());
+        assert_eq!(a.color, Color::from((1, 0, 0)));
+        assert!(library.materials["b"].texture.is_none());
+    }
+}
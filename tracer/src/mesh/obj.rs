@@ -0,0 +1,602 @@
+use std::{fmt, path::Path};
+
+use crate::{
+    point::{coord::Coord, vector::Vector},
+    shapes::{smooth_triangle::SmoothTriangle, triangle::Triangle, Hittable, Shapes},
+};
+
+use super::mtl::{MtlError, MtlLibrary};
+
+/// Errors found while reading a Wavefront OBJ file, the line numbers start at 1
+#[derive(Debug)]
+pub enum ObjError {
+    Io(std::io::Error),
+    /// Some value could not be read as a number
+    InvalidNumber {
+        line: usize,
+        value: String,
+    },
+    /// A statement has less values than it needs
+    MissingValues {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A face refers to a vertex, texture coordinate or normal that does not exist
+    InvalidIndex {
+        line: usize,
+        index: i64,
+    },
+    /// One of the material libraries given by `mtllib` could not be read
+    Material(MtlError),
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(err) => write!(f, "could not read obj file: {err}"),
+            ObjError::InvalidNumber { line, value } => {
+                write!(f, "line {line}: `{value}` is not a valid number")
+            }
+            ObjError::MissingValues {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line}: expected at least {expected} values, found {found}"
+            ),
+            ObjError::InvalidIndex { line, index } => {
+                write!(f, "line {line}: index {index} does not exist")
+            }
+            ObjError::Material(err) => write!(f, "could not read material library: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+impl From<MtlError> for ObjError {
+    fn from(err: MtlError) -> Self {
+        ObjError::Material(err)
+    }
+}
+
+impl From<std::io::Error> for ObjError {
+    fn from(err: std::io::Error) -> Self {
+        ObjError::Io(err)
+    }
+}
+
+/// Triangles that belong to the same object or group, and use the same material
+#[derive(Debug, Default)]
+pub struct ObjGroup {
+    /// Name given by the last `g` or `o` statement, `None` for faces before any of them
+    pub name: Option<String>,
+    /// Name of the material set by the last `usemtl` statement
+    pub material: Option<String>,
+    pub shapes: Vec<Shapes>,
+}
+
+/// Geometry read from a Wavefront OBJ file
+///
+/// Polygons are split into triangles, and faces that have a normal for each vertex become smooth
+/// triangles. A new group is started every time the object, group, or material changes.
+#[derive(Debug, Default)]
+pub struct ObjModel {
+    pub vertices: Vec<Coord>,
+    pub normals: Vec<Vector>,
+    pub texture_coords: Vec<(f64, f64)>,
+    pub groups: Vec<ObjGroup>,
+    /// Name of the material libraries given by `mtllib` statements
+    pub material_libraries: Vec<String>,
+    /// Numbers of the lines that were not understood, and so, ignored
+    pub ignored_lines: Vec<usize>,
+}
+
+/// One of the vertices of a face, with the indices (starting at 0) of its data
+#[derive(Debug, Clone, Copy)]
+struct FaceVertex {
+    vertex: usize,
+    texture: Option<usize>,
+    normal: Option<usize>,
+}
+
+impl ObjModel {
+    /// Read an OBJ file, and apply to its groups the materials from the libraries it uses, which
+    /// are looked for relative to the folder of the file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ObjError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let mut model = Self::parse(&content)?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        for library in model.material_libraries.clone() {
+            let library = MtlLibrary::load(base.join(library))?;
+            model.apply_materials(&library);
+        }
+
+        Ok(model)
+    }
+
+    /// Give the shapes of every group the material it uses from `library`. Returns the names of
+    /// the materials that were not in the library, those groups keep their current material
+    pub fn apply_materials(&mut self, library: &MtlLibrary) -> Vec<String> {
+        let mut missing = vec![];
+        for group in &mut self.groups {
+            let Some(name) = &group.material else {
+                continue;
+            };
+            match library.materials.get(name) {
+                Some(material) => group
+                    .shapes
+                    .iter_mut()
+                    .for_each(|shape| *shape.material_mut() = material.clone()),
+                None if !missing.contains(name) => missing.push(name.clone()),
+                None => {}
+            }
+        }
+        missing
+    }
+
+    pub fn parse(input: &str) -> Result<Self, ObjError> {
+        let mut model = ObjModel::default();
+        let mut name = None;
+        let mut material = None;
+
+        for (index, line) in input.lines().enumerate() {
+            let line_number = index + 1;
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            let values: Vec<&str> = tokens.collect();
+
+            match keyword {
+                "v" => {
+                    let [x, y, z] = parse_numbers(&values, line_number)?;
+                    model.vertices.push(Coord::new(x, y, z));
+                }
+                "vn" => {
+                    let [x, y, z] = parse_numbers(&values, line_number)?;
+                    model.normals.push(Vector::new(x, y, z));
+                }
+                "vt" => {
+                    let [u] = parse_numbers(&values, line_number)?;
+                    let v = match values.get(1) {
+                        Some(v) => parse_number(v, line_number)?,
+                        None => 0.0,
+                    };
+                    model.texture_coords.push((u, v));
+                }
+                "f" => {
+                    let face = values
+                        .iter()
+                        .map(|value| model.parse_face_vertex(value, line_number))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if face.len() < 3 {
+                        return Err(ObjError::MissingValues {
+                            line: line_number,
+                            expected: 3,
+                            found: face.len(),
+                        });
+                    }
+
+                    let triangles = model.triangulate(&face);
+                    model
+                        .current_group(&name, &material)
+                        .shapes
+                        .extend(triangles);
+                }
+                "g" | "o" => name = Some(values.join(" ")).filter(|name| !name.is_empty()),
+                "usemtl" => material = values.first().map(|name| name.to_string()),
+                "mtllib" => model
+                    .material_libraries
+                    .extend(values.iter().map(|lib| lib.to_string())),
+                // Comments are not worth reporting
+                _ if keyword.starts_with('#') => {}
+                _ => model.ignored_lines.push(line_number),
+            }
+        }
+
+        Ok(model)
+    }
+
+    /// All of the triangles in the model, regardless of their group
+    pub fn into_shapes(self) -> Vec<Shapes> {
+        self.groups
+            .into_iter()
+            .flat_map(|group| group.shapes)
+            .collect()
+    }
+
+    /// Group that new faces should be added to, a new one is created when the name or material
+    /// changed since the last face
+    fn current_group(&mut self, name: &Option<String>, material: &Option<String>) -> &mut ObjGroup {
+        let is_current = self
+            .groups
+            .last()
+            .is_some_and(|group| &group.name == name && &group.material == material);
+
+        if !is_current {
+            self.groups.push(ObjGroup {
+                name: name.clone(),
+                material: material.clone(),
+                shapes: vec![],
+            });
+        }
+
+        self.groups.last_mut().expect("A group was just added")
+    }
+
+    /// Parse one of the `v`, `v/vt`, `v//vn` or `v/vt/vn` values of a face
+    fn parse_face_vertex(&self, value: &str, line: usize) -> Result<FaceVertex, ObjError> {
+        let mut parts = value.split('/');
+        let vertex = parts.next().unwrap_or_default();
+        let texture = parts.next().filter(|part| !part.is_empty());
+        let normal = parts.next().filter(|part| !part.is_empty());
+
+        Ok(FaceVertex {
+            vertex: resolve_index(vertex, self.vertices.len(), line)?,
+            texture: texture
+                .map(|index| resolve_index(index, self.texture_coords.len(), line))
+                .transpose()?,
+            normal: normal
+                .map(|index| resolve_index(index, self.normals.len(), line))
+                .transpose()?,
+        })
+    }
+
+    /// Split a convex polygon into triangles that share its first vertex
+    fn triangulate(&self, face: &[FaceVertex]) -> Vec<Shapes> {
+        let smooth = face.iter().all(|vertex| vertex.normal.is_some());
+        let textured = face.iter().all(|vertex| vertex.texture.is_some());
+
+        face.windows(2)
+            .skip(1)
+            .map(|pair| {
+                let corners = [face[0], pair[0], pair[1]];
+                let [p1, p2, p3] = corners.map(|corner| self.vertices[corner.vertex].clone());
+                let texture_coords = textured.then(|| {
+                    corners.map(|corner| self.texture_coords[corner.texture.unwrap_or_default()])
+                });
+
+                match smooth {
+                    true => {
+                        let [n1, n2, n3] = corners
+                            .map(|corner| self.normals[corner.normal.unwrap_or_default()].clone());
+                        let mut triangle = SmoothTriangle::new((p1, p2, p3), (n1, n2, n3));
+                        triangle.texture_coords = texture_coords;
+                        Shapes::SmoothTriangle(triangle)
+                    }
+                    false => {
+                        let mut triangle = Triangle::new(p1, p2, p3);
+                        triangle.texture_coords = texture_coords;
+                        Shapes::Triangle(triangle)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+fn parse_number(value: &str, line: usize) -> Result<f64, ObjError> {
+    value.parse().map_err(|_| ObjError::InvalidNumber {
+        line,
+        value: value.to_string(),
+    })
+}
+
+/// Parse the first N values as numbers, extra values are ignored
+fn parse_numbers<const N: usize>(values: &[&str], line: usize) -> Result<[f64; N], ObjError> {
+    if values.len() < N {
+        return Err(ObjError::MissingValues {
+            line,
+            expected: N,
+            found: values.len(),
+        });
+    }
+
+    let mut numbers = [0.0; N];
+    for (number, value) in numbers.iter_mut().zip(values) {
+        *number = parse_number(value, line)?;
+    }
+    Ok(numbers)
+}
+
+/// OBJ indices start at 1, and negative indices count backwards from the last element defined
+fn resolve_index(value: &str, len: usize, line: usize) -> Result<usize, ObjError> {
+    let index: i64 = value.parse().map_err(|_| ObjError::InvalidNumber {
+        line,
+        value: value.to_string(),
+    })?;
+
+    let resolved = match index {
+        0 => None,
+        i if i > 0 => Some(i - 1),
+        i => Some(len as i64 + i),
+    };
+
+    resolved
+        .filter(|&i| (0..len as i64).contains(&i))
+        .map(|i| i as usize)
+        .ok_or(ObjError::InvalidIndex { line, index })
+}
+
+#[cfg(test)]
+mod test_obj {
+    use super::*;
+    use image::color::Color;
+    use indoc::indoc;
+
+    #[test]
+    fn ignores_unknown_lines() {
+        let input = indoc! {"
+            There was a young lady named Bright
+            who traveled much faster than light.
+            # This is a comment
+            She set out one day
+        "};
+        let model = ObjModel::parse(input).unwrap();
+        assert_eq!(model.ignored_lines, vec![1, 2, 4]);
+        assert!(model.groups.is_empty());
+    }
+
+    #[test]
+    fn vertex_records() {
+        let input = indoc! {"
+            v -1 1 0
+            v -1.0000 0.5000 0.0000
+            v 1 0 0
+            v 1 1 0
+        "};
+        let model = ObjModel::parse(input).unwrap();
+        assert_eq!(
+            model.vertices,
+            vec![
+                Coord::from((-1, 1, 0)),
+                Coord::from((-1.0, 0.5, 0.0)),
+                Coord::from((1, 0, 0)),
+                Coord::from((1, 1, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn triangle_faces() {
+        let input = indoc! {"
+            v -1 1 0
+            v -1 0 0
+            v 1 0 0
+            v 1 1 0
+
+            f 1 2 3
+            f 1 3 4
+        "};
+        let model = ObjModel::parse(input).unwrap();
+        assert_eq!(model.groups.len(), 1);
+
+        let shapes = &model.groups[0].shapes;
+        assert_eq!(
+            shapes[0],
+            Shapes::Triangle(Triangle::new(
+                Coord::from((-1, 1, 0)),
+                Coord::from((-1, 0, 0)),
+                Coord::from((1, 0, 0)),
+            ))
+        );
+        assert_eq!(
+            shapes[1],
+            Shapes::Triangle(Triangle::new(
+                Coord::from((-1, 1, 0)),
+                Coord::from((1, 0, 0)),
+                Coord::from((1, 1, 0)),
+            ))
+        );
+    }
+
+    #[test]
+    fn polygon_fan_triangulation() {
+        let input = indoc! {"
+            v -1 1 0
+            v -1 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 2 0
+
+            f 1 2 3 4 5
+        "};
+        let model = ObjModel::parse(input).unwrap();
+        let shapes = model.into_shapes();
+        assert_eq!(shapes.len(), 3);
+        assert_eq!(
+            shapes[2],
+            Shapes::Triangle(Triangle::new(
+                Coord::from((-1, 1, 0)),
+                Coord::from((1, 1, 0)),
+                Coord::from((0, 2, 0)),
+            ))
+        );
+    }
+
+    #[test]
+    fn named_groups() {
+        let input = indoc! {"
+            v -1 1 0
+            v -1 0 0
+            v 1 0 0
+            v 1 1 0
+
+            g FirstGroup
+            f 1 2 3
+            o SecondObject
+            f 1 3 4
+        "};
+        let model = ObjModel::parse(input).unwrap();
+        let names: Vec<_> = model.groups.iter().map(|g| g.name.as_deref()).collect();
+        assert_eq!(names, vec![Some("FirstGroup"), Some("SecondObject")]);
+        assert!(model.groups.iter().all(|group| group.shapes.len() == 1));
+    }
+
+    #[test]
+    fn materials_split_groups() {
+        let input = indoc! {"
+            mtllib scene.mtl
+            v -1 1 0
+            v -1 0 0
+            v 1 0 0
+            v 1 1 0
+
+            g Table
+            usemtl wood
+            f 1 2 3
+            f 1 3 4
+            usemtl metal
+            f 1 2 4
+        "};
+        let model = ObjModel::parse(input).unwrap();
+        assert_eq!(model.material_libraries, vec!["scene.mtl".to_string()]);
+        assert_eq!(model.groups.len(), 2);
+        assert_eq!(model.groups[0].material.as_deref(), Some("wood"));
+        assert_eq!(model.groups[0].shapes.len(), 2);
+        assert_eq!(model.groups[1].name.as_deref(), Some("Table"));
+        assert_eq!(model.groups[1].material.as_deref(), Some("metal"));
+    }
+
+    #[test]
+    fn faces_with_normals() {
+        let input = indoc! {"
+            v 0 1 0
+            v -1 0 0
+            v 1 0 0
+
+            vn -1 0 0
+            vn 1 0 0
+            vn 0 1 0
+
+            vt 0 0
+            vt 1 0
+            vt 0.5 1
+
+            f 1//3 2//1 3//2
+            f 1/3/3 2/1/1 3/2/2
+            f -3/-1/-1 -2/-3/-3 -1/-2/-2
+        "};
+        let model = ObjModel::parse(input).unwrap();
+        assert_eq!(model.texture_coords.len(), 3);
+
+        let mut expected = SmoothTriangle::new(
+            (
+                Coord::from((0, 1, 0)),
+                Coord::from((-1, 0, 0)),
+                Coord::from((1, 0, 0)),
+            ),
+            (
+                Vector::from((0, 1, 0)),
+                Vector::from((-1, 0, 0)),
+                Vector::from((1, 0, 0)),
+            ),
+        );
+        let shapes = model.into_shapes();
+        assert_eq!(shapes.len(), 3);
+        assert_eq!(shapes[0], Shapes::SmoothTriangle(expected.clone()));
+
+        // The other two faces also have texture coordinates
+        expected.texture_coords = Some([(0.5, 1.0), (0.0, 0.0), (1.0, 0.0)]);
+        assert_eq!(shapes[1], Shapes::SmoothTriangle(expected.clone()));
+        assert_eq!(shapes[2], Shapes::SmoothTriangle(expected));
+        assert_eq!(
+            shapes[0].normal_with_uv(&Coord::from((0, 0, 0)), Some((0.0, 0.0))),
+            Vector::from((0, 1, 0))
+        );
+    }
+
+    #[test]
+    fn malformed_input() {
+        let err = ObjModel::parse("v 1 two 3").unwrap_err();
+        assert!(matches!(err, ObjError::InvalidNumber { line: 1, ref value } if value == "two"));
+
+        let err = ObjModel::parse("v 1 2").unwrap_err();
+        assert!(matches!(
+            err,
+            ObjError::MissingValues {
+                line: 1,
+                expected: 3,
+                found: 2
+            }
+        ));
+
+        let err = ObjModel::parse("v 1 2 3\nv 1 2 4\nf 1 2").unwrap_err();
+        assert!(matches!(err, ObjError::MissingValues { line: 3, .. }));
+
+        let err = ObjModel::parse("v 1 2 3\nv 1 2 4\nv 0 0 0\nf 1 2 4").unwrap_err();
+        assert!(matches!(err, ObjError::InvalidIndex { line: 4, index: 4 }));
+
+        let err = ObjModel::parse("v 1 2 3\nv 1 2 4\nv 0 0 0\nf 0 1 2").unwrap_err();
+        assert!(matches!(err, ObjError::InvalidIndex { line: 4, index: 0 }));
+
+        let err = ObjModel::parse("v 1 2 3\nv 1 2 4\nv 0 0 0\nf -4 1 2").unwrap_err();
+        assert!(matches!(err, ObjError::InvalidIndex { line: 4, index: -4 }));
+
+        let err = ObjModel::parse("v 1 2 3\nv 1 2 4\nv 0 0 0\nf 1//1 2 3").unwrap_err();
+        assert!(matches!(err, ObjError::InvalidIndex { line: 4, index: 1 }));
+    }
+
+    #[test]
+    fn error_messages() {
+        let err = ObjModel::parse("v 1 2 3\nf 1 2 3").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: index 2 does not exist");
+    }
+
+    #[test]
+    fn load_with_materials() {
+        let dir = std::env::temp_dir().join("tracer_test_obj_materials");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("colors.mtl"),
+            "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n",
+        )
+        .unwrap();
+        let obj = indoc! {"
+            mtllib colors.mtl
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            usemtl red
+            f 1 2 3
+            usemtl blue
+            f 1 2 3
+            usemtl green
+            f 1 2 3
+        "};
+        std::fs::write(dir.join("model.obj"), obj).unwrap();
+
+        let model = ObjModel::load(dir.join("model.obj")).unwrap();
+        let colors: Vec<_> = model
+            .groups
+            .iter()
+            .map(|group| group.shapes[0].material().color)
+            .collect();
+        assert_eq!(
+            colors,
+            vec![
+                Color::from((1, 0, 0)),
+                Color::from((0, 0, 1)),
+                Color::from((1, 1, 1))
+            ]
+        );
+
+        let mut model = ObjModel::parse(obj).unwrap();
+        let library = MtlLibrary::parse("newmtl red\nKd 1 0 0", Path::new("")).unwrap();
+        assert_eq!(model.apply_materials(&library), vec!["blue", "green"]);
+    }
+
+    #[test]
+    fn missing_material_library() {
+        let dir = std::env::temp_dir().join("tracer_test_obj_missing_mtl");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("model.obj"), "mtllib nowhere.mtl\n").unwrap();
+        let err = ObjModel::load(dir.join("model.obj")).unwrap_err();
+        assert!(matches!(err, ObjError::Material(MtlError::Io { .. })));
+    }
+}
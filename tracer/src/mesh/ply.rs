@@ -0,0 +1,695 @@
+use std::{fmt, path::Path};
+
+use image::color::Color;
+
+use crate::{
+    point::{coord::Coord, vector::Vector},
+    shapes::{smooth_triangle::SmoothTriangle, triangle::Triangle, Hittable, Shapes},
+};
+
+/// Errors found while reading a PLY file, header line numbers start at 1, while elements are
+/// counted from 0
+#[derive(Debug)]
+pub enum PlyError {
+    Io(std::io::Error),
+    /// Some line of the header is not valid, or the header does not end
+    InvalidHeader {
+        line: usize,
+    },
+    /// Only ASCII and little endian binary files are supported
+    UnsupportedFormat(String),
+    /// Some element of the body does not match the properties given in the header
+    InvalidElement {
+        element: String,
+        index: usize,
+    },
+    /// The body does not have the number of elements given in the header
+    CountMismatch {
+        element: String,
+        declared: usize,
+        found: usize,
+    },
+    /// The body has more data after the last element given in the header
+    TrailingData,
+    /// The vertices or faces do not have a property needed to build triangles
+    MissingProperty {
+        element: &'static str,
+        property: &'static str,
+    },
+    /// A face refers to a vertex that does not exist
+    InvalidIndex {
+        face: usize,
+        index: i64,
+    },
+    /// A face with less than three vertices
+    InvalidFace {
+        face: usize,
+        vertices: usize,
+    },
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io(err) => write!(f, "could not read ply file: {err}"),
+            PlyError::InvalidHeader { line } => write!(f, "line {line}: invalid header"),
+            PlyError::UnsupportedFormat(format) => write!(f, "unsupported format `{format}`"),
+            PlyError::InvalidElement { element, index } => {
+                write!(f, "{element} {index} does not match its properties")
+            }
+            PlyError::CountMismatch {
+                element,
+                declared,
+                found,
+            } => write!(
+                f,
+                "the header declares {declared} {element} elements, but the file has {found}"
+            ),
+            PlyError::TrailingData => write!(f, "the file has data after the last element"),
+            PlyError::MissingProperty { element, property } => {
+                write!(f, "{element} elements do not have a `{property}` property")
+            }
+            PlyError::InvalidIndex { face, index } => {
+                write!(f, "face {face}: vertex {index} does not exist")
+            }
+            PlyError::InvalidFace { face, vertices } => {
+                write!(
+                    f,
+                    "face {face}: expected at least 3 vertices, found {vertices}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlyError {}
+
+impl From<std::io::Error> for PlyError {
+    fn from(err: std::io::Error) -> Self {
+        PlyError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(ScalarType::Int8),
+            "uchar" | "uint8" => Some(ScalarType::UInt8),
+            "short" | "int16" => Some(ScalarType::Int16),
+            "ushort" | "uint16" => Some(ScalarType::UInt16),
+            "int" | "int32" => Some(ScalarType::Int32),
+            "uint" | "uint32" => Some(ScalarType::UInt32),
+            "float" | "float32" => Some(ScalarType::Float32),
+            "double" | "float64" => Some(ScalarType::Float64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    /// Read a little endian value from the start of `bytes`, which must be long enough
+    fn read(self, bytes: &[u8]) -> f64 {
+        let b = bytes;
+        match self {
+            ScalarType::Int8 => b[0] as i8 as f64,
+            ScalarType::UInt8 => b[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            ScalarType::UInt16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            ScalarType::Int32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            ScalarType::UInt32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            ScalarType::Float32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            ScalarType::Float64 => {
+                f64::from_le_bytes(b[..8].try_into().expect("Slice has 8 bytes"))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PropertyKind {
+    Scalar(ScalarType),
+    /// A number of items, followed by the items
+    List {
+        count: ScalarType,
+        item: ScalarType,
+    },
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn property(&self, name: &str) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|property| property.name == name)
+    }
+}
+
+/// Values of the properties of one element, a scalar is a list with a single value
+type Row = Vec<Vec<f64>>;
+
+/// Triangle geometry read from an ASCII or little endian binary PLY file
+///
+/// Only the `vertex` and `face` elements are used, any other element is read and skipped. The
+/// vertices may have normals and colors, in which case the triangles become smooth, and get the
+/// average color of their vertices.
+#[derive(Debug, Default)]
+pub struct PlyModel {
+    pub vertices: Vec<Coord>,
+    pub normals: Option<Vec<Vector>>,
+    pub colors: Option<Vec<Color>>,
+    /// Indices (starting at 0) of the vertices of each face
+    pub faces: Vec<Vec<usize>>,
+    pub comments: Vec<String>,
+}
+
+impl PlyModel {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PlyError> {
+        let content = std::fs::read(path)?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(input: &[u8]) -> Result<Self, PlyError> {
+        let mut model = PlyModel::default();
+        let (format, elements, body) = parse_header(input, &mut model.comments)?;
+
+        let rows = match format {
+            Format::Ascii => read_ascii_body(&elements, &String::from_utf8_lossy(body))?,
+            Format::BinaryLittleEndian => read_binary_body(&elements, body)?,
+        };
+
+        for (element, rows) in elements.iter().zip(rows) {
+            match element.name.as_str() {
+                "vertex" => model.read_vertices(element, rows)?,
+                "face" => model.read_faces(element, rows)?,
+                _ => {}
+            }
+        }
+
+        Ok(model)
+    }
+
+    fn read_vertices(&mut self, element: &Element, rows: Vec<Row>) -> Result<(), PlyError> {
+        let find = |names: [&'static str; 3]| {
+            names
+                .map(|name| element.property(name))
+                .into_iter()
+                .collect::<Option<Vec<_>>>()
+        };
+        let position = find(["x", "y", "z"]).ok_or(PlyError::MissingProperty {
+            element: "vertex",
+            property: "x",
+        })?;
+        let normal = find(["nx", "ny", "nz"]);
+        let color = find(["red", "green", "blue"]);
+
+        // Integer colors go from 0 to 255, while floating point ones go from 0 to 1
+        let color_scale = match color
+            .as_ref()
+            .map(|color| &element.properties[color[0]].kind)
+        {
+            Some(PropertyKind::Scalar(ScalarType::Float32 | ScalarType::Float64)) => 1.0,
+            _ => 255.0,
+        };
+
+        let value = |row: &Row, indices: &[usize], i: usize| row[indices[i]][0];
+        for row in &rows {
+            self.vertices.push(Coord::new(
+                value(row, &position, 0),
+                value(row, &position, 1),
+                value(row, &position, 2),
+            ));
+            if let Some(normal) = &normal {
+                self.normals.get_or_insert_with(Vec::new).push(Vector::new(
+                    value(row, normal, 0),
+                    value(row, normal, 1),
+                    value(row, normal, 2),
+                ));
+            }
+            if let Some(color) = &color {
+                self.colors.get_or_insert_with(Vec::new).push(Color::new(
+                    value(row, color, 0) / color_scale,
+                    value(row, color, 1) / color_scale,
+                    value(row, color, 2) / color_scale,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_faces(&mut self, element: &Element, rows: Vec<Row>) -> Result<(), PlyError> {
+        let indices = element
+            .property("vertex_indices")
+            .or_else(|| element.property("vertex_index"))
+            .ok_or(PlyError::MissingProperty {
+                element: "face",
+                property: "vertex_indices",
+            })?;
+
+        for (face, row) in rows.iter().enumerate() {
+            let vertices = &row[indices];
+            if vertices.len() < 3 {
+                return Err(PlyError::InvalidFace {
+                    face,
+                    vertices: vertices.len(),
+                });
+            }
+
+            let vertices = vertices
+                .iter()
+                .map(|&index| {
+                    let index = index as i64;
+                    usize::try_from(index)
+                        .ok()
+                        .filter(|&index| index < self.vertices.len())
+                        .ok_or(PlyError::InvalidIndex { face, index })
+                })
+                .collect::<Result<_, _>>()?;
+            self.faces.push(vertices);
+        }
+
+        Ok(())
+    }
+
+    /// Split every face into triangles that share its first vertex
+    pub fn into_shapes(self) -> Vec<Shapes> {
+        self.faces
+            .iter()
+            .flat_map(|face| {
+                face.windows(2).skip(1).map(|pair| {
+                    let corners = [face[0], pair[0], pair[1]];
+                    let [p1, p2, p3] = corners.map(|corner| self.vertices[corner].clone());
+
+                    let mut shape = match &self.normals {
+                        Some(normals) => {
+                            let [n1, n2, n3] = corners.map(|corner| normals[corner].clone());
+                            Shapes::SmoothTriangle(SmoothTriangle::new((p1, p2, p3), (n1, n2, n3)))
+                        }
+                        None => Shapes::Triangle(Triangle::new(p1, p2, p3)),
+                    };
+                    if let Some(colors) = &self.colors {
+                        let sum = corners
+                            .iter()
+                            .fold(Color::black(), |sum, &corner| sum + colors[corner]);
+                        shape.material_mut().color = sum * (1.0 / 3.0);
+                    }
+                    shape
+                })
+            })
+            .collect()
+    }
+}
+
+/// Read the header, returning the format, the elements and the body after it
+fn parse_header<'a>(
+    input: &'a [u8],
+    comments: &mut Vec<String>,
+) -> Result<(Format, Vec<Element>, &'a [u8]), PlyError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    let mut rest = input;
+
+    for line_number in 1.. {
+        let Some(end) = rest.iter().position(|&byte| byte == b'\n') else {
+            return Err(PlyError::InvalidHeader { line: line_number });
+        };
+        let line = String::from_utf8_lossy(&rest[..end]);
+        rest = &rest[end + 1..];
+        let invalid = || PlyError::InvalidHeader { line: line_number };
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["ply"] if line_number == 1 => {}
+            _ if line_number == 1 => return Err(invalid()),
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", other, _] => return Err(PlyError::UnsupportedFormat(other.to_string())),
+            ["comment" | "obj_info", ..] => {
+                let comment = line.trim_start().split_once(' ').map_or("", |(_, c)| c);
+                comments.push(comment.trim().to_string());
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid())?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => {
+                let count = ScalarType::from_name(count).ok_or_else(invalid)?;
+                let item = ScalarType::from_name(item).ok_or_else(invalid)?;
+                let element = elements.last_mut().ok_or_else(invalid)?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::List { count, item },
+                });
+            }
+            ["property", kind, name] => {
+                let kind = ScalarType::from_name(kind).ok_or_else(invalid)?;
+                let element = elements.last_mut().ok_or_else(invalid)?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::Scalar(kind),
+                });
+            }
+            ["end_header"] => {
+                let format = format.ok_or_else(invalid)?;
+                return Ok((format, elements, rest));
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    unreachable!("The header loop only ends by returning")
+}
+
+fn read_ascii_body(elements: &[Element], body: &str) -> Result<Vec<Vec<Row>>, PlyError> {
+    let mut lines = body.lines().filter(|line| !line.trim().is_empty());
+
+    let mut all_rows = vec![];
+    for element in elements {
+        // The count comes from the header, each row needs at least one byte of the body
+        let mut rows = Vec::with_capacity(element.count.min(body.len()));
+        for index in 0..element.count {
+            let Some(line) = lines.next() else {
+                return Err(PlyError::CountMismatch {
+                    element: element.name.clone(),
+                    declared: element.count,
+                    found: index,
+                });
+            };
+            let invalid = || PlyError::InvalidElement {
+                element: element.name.clone(),
+                index,
+            };
+
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse::<f64>().map_err(|_| invalid()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut values = values.into_iter();
+            let mut row = Row::with_capacity(element.properties.len());
+            for property in &element.properties {
+                let len = match property.kind {
+                    PropertyKind::Scalar(_) => 1,
+                    PropertyKind::List { .. } => values.next().ok_or_else(invalid)? as usize,
+                };
+                let property_values: Vec<f64> = values.by_ref().take(len).collect();
+                if property_values.len() != len {
+                    return Err(invalid());
+                }
+                row.push(property_values);
+            }
+            if values.next().is_some() {
+                return Err(invalid());
+            }
+            rows.push(row);
+        }
+        all_rows.push(rows);
+    }
+
+    match lines.next() {
+        Some(_) => Err(PlyError::TrailingData),
+        None => Ok(all_rows),
+    }
+}
+
+fn read_binary_body(elements: &[Element], mut body: &[u8]) -> Result<Vec<Vec<Row>>, PlyError> {
+    let available = body.len();
+    // Read a value, or `None` when the body ends before it
+    let mut read = |kind: ScalarType| -> Option<f64> {
+        let value = kind.read(body.get(..kind.size())?);
+        body = &body[kind.size()..];
+        Some(value)
+    };
+
+    let mut all_rows = vec![];
+    for element in elements {
+        // The count comes from the header, so no more rows are expected than the body can hold
+        let row_size: usize = element
+            .properties
+            .iter()
+            .map(|property| match property.kind {
+                PropertyKind::Scalar(kind) => kind.size(),
+                PropertyKind::List { count, .. } => count.size(),
+            })
+            .sum();
+        let mut rows = Vec::with_capacity(element.count.min(available / row_size.max(1)));
+        for index in 0..element.count {
+            let row = element
+                .properties
+                .iter()
+                .map(|property| match property.kind {
+                    PropertyKind::Scalar(kind) => Some(vec![read(kind)?]),
+                    PropertyKind::List { count, item } => {
+                        let len = read(count)? as usize;
+                        (0..len).map(|_| read(item)).collect()
+                    }
+                })
+                .collect::<Option<Row>>()
+                .ok_or(PlyError::CountMismatch {
+                    element: element.name.clone(),
+                    declared: element.count,
+                    found: index,
+                })?;
+            rows.push(row);
+        }
+        all_rows.push(rows);
+    }
+
+    match read(ScalarType::UInt8) {
+        Some(_) => Err(PlyError::TrailingData),
+        None => Ok(all_rows),
+    }
+}
+
+#[cfg(test)]
+mod test_ply {
+    use super::*;
+    use indoc::indoc;
+
+    const ASCII_SQUARE: &str = indoc! {"
+        ply
+        format ascii 1.0
+        comment made by hand
+        element vertex 4
+        property float x
+        property float y
+        property float z
+        property uchar red
+        property uchar green
+        property uchar blue
+        element face 1
+        property list uchar int vertex_indices
+        end_header
+        0 0 0 255 0 0
+        1 0 0 255 0 0
+        1 1 0 0 0 255
+        0 1 0 0 0 255
+        4 0 1 2 3
+    "};
+
+    /// Binary version of a single triangle with normals, with an extra element that is skipped
+    fn binary_triangle(declared_faces: usize, faces: usize) -> Vec<u8> {
+        let header = format!(
+            "ply\nformat binary_little_endian 1.0\nelement vertex 3\nproperty float x\n\
+             property float y\nproperty float z\nproperty double nx\nproperty double ny\n\
+             property double nz\nelement material 1\nproperty ushort id\n\
+             element face {declared_faces}\nproperty list uchar uint vertex_index\nend_header\n"
+        );
+        let mut bytes = header.into_bytes();
+        for position in [[0.0f32, 1.0, 0.0], [-1.0, 0.0, 0.0], [1.0, 0.0, 0.0]] {
+            position.iter().for_each(|v| bytes.extend(v.to_le_bytes()));
+            [0.0f64, 0.0, -1.0]
+                .iter()
+                .for_each(|v| bytes.extend(v.to_le_bytes()));
+        }
+        bytes.extend(7u16.to_le_bytes());
+        for _ in 0..faces {
+            bytes.extend([3, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn ascii_file() {
+        let model = PlyModel::parse(ASCII_SQUARE.as_bytes()).unwrap();
+        assert_eq!(model.comments, vec!["made by hand"]);
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.vertices[2], Coord::from((1, 1, 0)));
+        assert_eq!(model.normals, None);
+        assert_eq!(model.colors.as_ref().unwrap()[3], Color::from((0, 0, 1)));
+        assert_eq!(model.faces, vec![vec![0, 1, 2, 3]]);
+
+        let shapes = model.into_shapes();
+        assert_eq!(shapes.len(), 2);
+        assert!(matches!(shapes[0], Shapes::Triangle(_)));
+        assert_eq!(
+            shapes[0].material().color,
+            Color::from((2.0 / 3.0, 0.0, 1.0 / 3.0))
+        );
+        assert_eq!(
+            shapes[1].material().color,
+            Color::from((1.0 / 3.0, 0.0, 2.0 / 3.0))
+        );
+    }
+
+    #[test]
+    fn binary_file() {
+        let model = PlyModel::parse(&binary_triangle(1, 1)).unwrap();
+        assert_eq!(model.vertices[0], Coord::from((0, 1, 0)));
+        assert_eq!(model.normals.as_ref().unwrap()[2], Vector::from((0, 0, -1)));
+        assert_eq!(model.colors, None);
+        assert_eq!(model.faces, vec![vec![0, 1, 2]]);
+
+        let shapes = model.into_shapes();
+        assert_eq!(shapes.len(), 1);
+        assert!(matches!(shapes[0], Shapes::SmoothTriangle(_)));
+    }
+
+    #[test]
+    fn count_mismatch() {
+        let err = PlyModel::parse(&binary_triangle(2, 1)).unwrap_err();
+        assert!(matches!(
+            err,
+            PlyError::CountMismatch {
+                ref element,
+                declared: 2,
+                found: 1
+            } if element == "face"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "the header declares 2 face elements, but the file has 1"
+        );
+
+        assert!(matches!(
+            PlyModel::parse(&binary_triangle(1, 2)).unwrap_err(),
+            PlyError::TrailingData
+        ));
+
+        let input = ASCII_SQUARE.replace("4 0 1 2 3\n", "");
+        assert!(matches!(
+            PlyModel::parse(input.as_bytes()).unwrap_err(),
+            PlyError::CountMismatch {
+                declared: 1,
+                found: 0,
+                ..
+            }
+        ));
+
+        let input = ASCII_SQUARE.replace("element vertex 4", "element vertex 3");
+        assert!(matches!(
+            PlyModel::parse(input.as_bytes()).unwrap_err(),
+            PlyError::InvalidElement { index: 0, .. }
+        ));
+
+        let input = format!("{ASCII_SQUARE}3 0 1 2\n");
+        assert!(matches!(
+            PlyModel::parse(input.as_bytes()).unwrap_err(),
+            PlyError::TrailingData
+        ));
+    }
+
+    #[test]
+    fn oversized_count() {
+        let huge = "999999999999999999";
+        let input = ASCII_SQUARE.replace("element vertex 4", &format!("element vertex {huge}"));
+        assert!(matches!(
+            PlyModel::parse(input.as_bytes()).unwrap_err(),
+            PlyError::InvalidElement { index: 4, .. }
+        ));
+
+        let bytes = binary_triangle(1, 1);
+        let declared = b"element face 1";
+        let at = bytes
+            .windows(declared.len())
+            .position(|w| w == declared)
+            .unwrap();
+        let face = format!("element face {huge}");
+        let bytes = [&bytes[..at], face.as_bytes(), &bytes[at + declared.len()..]].concat();
+        assert!(matches!(
+            PlyModel::parse(&bytes).unwrap_err(),
+            PlyError::CountMismatch { found: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn invalid_files() {
+        let input = ASCII_SQUARE.replace("ascii", "binary_big_endian");
+        assert!(matches!(
+            PlyModel::parse(input.as_bytes()).unwrap_err(),
+            PlyError::UnsupportedFormat(ref format) if format == "binary_big_endian"
+        ));
+
+        let input = ASCII_SQUARE.replace("property float y", "property quaternion y");
+        assert!(matches!(
+            PlyModel::parse(input.as_bytes()).unwrap_err(),
+            PlyError::InvalidHeader { line: 6 }
+        ));
+
+        assert!(matches!(
+            PlyModel::parse(b"ply\nformat ascii 1.0\n").unwrap_err(),
+            PlyError::InvalidHeader { line: 3 }
+        ));
+
+        let input = ASCII_SQUARE.replace("4 0 1 2 3", "3 0 1 4");
+        assert!(matches!(
+            PlyModel::parse(input.as_bytes()).unwrap_err(),
+            PlyError::InvalidIndex { face: 0, index: 4 }
+        ));
+
+        let input = ASCII_SQUARE.replace("4 0 1 2 3", "2 0 1");
+        assert!(matches!(
+            PlyModel::parse(input.as_bytes()).unwrap_err(),
+            PlyError::InvalidFace {
+                face: 0,
+                vertices: 2
+            }
+        ));
+
+        let input = ASCII_SQUARE.replace("property float z", "property float w");
+        assert!(matches!(
+            PlyModel::parse(input.as_bytes()).unwrap_err(),
+            PlyError::MissingProperty {
+                element: "vertex",
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,357 @@
+use std::{fmt, path::Path};
+
+use crate::{
+    point::coord::Coord,
+    shapes::{triangle::Triangle, Shapes},
+};
+
+/// Size of the header of a binary STL file, plus the number of triangles after it
+const BINARY_HEADER_SIZE: usize = 84;
+/// Size of each triangle in a binary STL file: the normal, three vertices and an attribute count
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+/// Errors found while reading a STL file, the line numbers start at 1
+#[derive(Debug)]
+pub enum StlError {
+    Io(std::io::Error),
+    /// A binary file is too short to have the header and the number of triangles
+    UnexpectedEnd,
+    /// A binary file does not have the number of triangles given in its header
+    CountMismatch {
+        declared: usize,
+        found: usize,
+    },
+    /// Some value could not be read as a number
+    InvalidNumber {
+        line: usize,
+        value: String,
+    },
+    /// A statement has less values than it needs
+    MissingValues {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A facet that does not have exactly three vertices
+    InvalidFacet {
+        line: usize,
+        vertices: usize,
+    },
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io(err) => write!(f, "could not read stl file: {err}"),
+            StlError::UnexpectedEnd => write!(f, "the file ended before the number of triangles"),
+            StlError::CountMismatch { declared, found } => write!(
+                f,
+                "the header declares {declared} triangles, but the file has {found}"
+            ),
+            StlError::InvalidNumber { line, value } => {
+                write!(f, "line {line}: `{value}` is not a valid number")
+            }
+            StlError::MissingValues {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line}: expected at least {expected} values, found {found}"
+            ),
+            StlError::InvalidFacet { line, vertices } => {
+                write!(f, "line {line}: facet has {vertices} vertices instead of 3")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StlError {}
+
+impl From<std::io::Error> for StlError {
+    fn from(err: std::io::Error) -> Self {
+        StlError::Io(err)
+    }
+}
+
+/// Triangles read from an ASCII or binary STL file
+///
+/// The normals in the file are ignored, the ones of the triangles are computed from the order of
+/// their vertices, as most tools do.
+#[derive(Debug, Default)]
+pub struct StlModel {
+    /// Name given by the `solid` statement of ASCII files
+    pub name: Option<String>,
+    pub triangles: Vec<Triangle>,
+    /// Numbers of the lines of ASCII files that were not understood, and so, ignored
+    pub ignored_lines: Vec<usize>,
+}
+
+impl StlModel {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, StlError> {
+        let content = std::fs::read(path)?;
+        Self::parse(&content)
+    }
+
+    /// Parse either kind of file. Some binary files also start with `solid`, so a file is only
+    /// read as text when it does not have the exact size of a binary one
+    pub fn parse(input: &[u8]) -> Result<Self, StlError> {
+        let binary_size = declared_triangles(input)
+            .map(|count| BINARY_HEADER_SIZE + count * BINARY_TRIANGLE_SIZE);
+        let is_ascii =
+            input.trim_ascii_start().starts_with(b"solid") && binary_size != Some(input.len());
+
+        match is_ascii {
+            true => Self::parse_ascii(&String::from_utf8_lossy(input)),
+            false => Self::parse_binary(input),
+        }
+    }
+
+    pub fn parse_binary(input: &[u8]) -> Result<Self, StlError> {
+        let declared = declared_triangles(input).ok_or(StlError::UnexpectedEnd)?;
+        let data = &input[BINARY_HEADER_SIZE..];
+        if data.len() != declared * BINARY_TRIANGLE_SIZE {
+            return Err(StlError::CountMismatch {
+                declared,
+                found: data.len() / BINARY_TRIANGLE_SIZE,
+            });
+        }
+
+        let triangles = data
+            .chunks_exact(BINARY_TRIANGLE_SIZE)
+            .map(|chunk| {
+                // The first 12 bytes are the normal, and the last 2 the attribute count
+                let [p1, p2, p3] = [12, 24, 36].map(|start| {
+                    let [x, y, z] = [0, 4, 8].map(|offset| {
+                        let bytes = &chunk[start + offset..start + offset + 4];
+                        f32::from_le_bytes(bytes.try_into().expect("Slice has 4 bytes")) as f64
+                    });
+                    Coord::new(x, y, z)
+                });
+                Triangle::new(p1, p2, p3)
+            })
+            .collect();
+
+        Ok(StlModel {
+            triangles,
+            ..Default::default()
+        })
+    }
+
+    pub fn parse_ascii(input: &str) -> Result<Self, StlError> {
+        let mut model = StlModel::default();
+        // Vertices of the facet being read, `None` outside of facets
+        let mut facet: Option<Vec<Coord>> = None;
+
+        for (index, line) in input.lines().enumerate() {
+            let line_number = index + 1;
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            let values: Vec<&str> = tokens.collect();
+
+            match keyword {
+                "solid" => model.name = Some(values.join(" ")).filter(|name| !name.is_empty()),
+                "facet" => facet = Some(vec![]),
+                "vertex" => {
+                    let [x, y, z] = parse_numbers(&values, line_number)?;
+                    facet.get_or_insert_with(Vec::new).push(Coord::new(x, y, z));
+                }
+                "endfacet" => {
+                    let vertices = facet.take().unwrap_or_default();
+                    let found = vertices.len();
+                    let Ok([p1, p2, p3]) = <[Coord; 3]>::try_from(vertices) else {
+                        return Err(StlError::InvalidFacet {
+                            line: line_number,
+                            vertices: found,
+                        });
+                    };
+                    model.triangles.push(Triangle::new(p1, p2, p3));
+                }
+                "outer" | "endloop" | "endsolid" => {}
+                _ => model.ignored_lines.push(line_number),
+            }
+        }
+
+        if let Some(vertices) = facet {
+            return Err(StlError::InvalidFacet {
+                line: input.lines().count(),
+                vertices: vertices.len(),
+            });
+        }
+
+        Ok(model)
+    }
+
+    pub fn into_shapes(self) -> Vec<Shapes> {
+        self.triangles.into_iter().map(Shapes::Triangle).collect()
+    }
+}
+
+/// Number of triangles given in the header of a binary file
+fn declared_triangles(input: &[u8]) -> Option<usize> {
+    let bytes = input.get(80..BINARY_HEADER_SIZE)?;
+    Some(u32::from_le_bytes(bytes.try_into().expect("Slice has 4 bytes")) as usize)
+}
+
+fn parse_numbers<const N: usize>(values: &[&str], line: usize) -> Result<[f64; N], StlError> {
+    if values.len() < N {
+        return Err(StlError::MissingValues {
+            line,
+            expected: N,
+            found: values.len(),
+        });
+    }
+
+    let mut numbers = [0.0; N];
+    for (number, value) in numbers.iter_mut().zip(values) {
+        *number = value.parse().map_err(|_| StlError::InvalidNumber {
+            line,
+            value: value.to_string(),
+        })?;
+    }
+    Ok(numbers)
+}
+
+#[cfg(test)]
+mod test_stl {
+    use super::*;
+    use indoc::indoc;
+
+    fn binary_stl(declared: u32, triangles: &[[[f32; 3]; 3]]) -> Vec<u8> {
+        let mut bytes = vec![0; 80];
+        bytes.extend(declared.to_le_bytes());
+        for triangle in triangles {
+            bytes.extend([0u8; 12]);
+            for value in triangle.iter().flatten() {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.extend([0u8; 2]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn ascii_file() {
+        let input = indoc! {"
+            solid pyramid piece
+              facet normal 0 0 -1
+                outer loop
+                  vertex 0 1 0
+                  vertex -1 0 0
+                  vertex 1 0 0
+                endloop
+              endfacet
+              facet normal 0 0 1
+                outer loop
+                  vertex 0 0 0
+                  vertex 1 0 0
+                  vertex 0 0 1
+                endloop
+              endfacet
+            endsolid pyramid piece
+        "};
+        let model = StlModel::parse(input.as_bytes()).unwrap();
+        assert_eq!(model.name.as_deref(), Some("pyramid piece"));
+        assert_eq!(model.triangles.len(), 2);
+        assert_eq!(
+            model.triangles[0].points(),
+            (
+                &Coord::from((0, 1, 0)),
+                &Coord::from((-1, 0, 0)),
+                &Coord::from((1, 0, 0))
+            )
+        );
+        assert!(model.ignored_lines.is_empty());
+        assert_eq!(model.into_shapes().len(), 2);
+    }
+
+    #[test]
+    fn binary_file() {
+        let bytes = binary_stl(
+            2,
+            &[
+                [[0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+                [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.5]],
+            ],
+        );
+        let model = StlModel::parse(&bytes).unwrap();
+        assert_eq!(model.name, None);
+        assert_eq!(model.triangles.len(), 2);
+        assert_eq!(model.triangles[1].points().2, &Coord::from((0.0, 0.0, 1.5)));
+    }
+
+    #[test]
+    fn binary_file_starting_with_solid() {
+        let mut bytes = binary_stl(1, &[[[0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [1.0, 0.0, 0.0]]]);
+        bytes[..5].copy_from_slice(b"solid");
+        assert_eq!(StlModel::parse(&bytes).unwrap().triangles.len(), 1);
+    }
+
+    #[test]
+    fn binary_count_mismatch() {
+        let triangle = [[0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [1.0, 0.0, 0.0]];
+        let bytes = binary_stl(3, &[triangle, triangle]);
+        let err = StlModel::parse(&bytes).unwrap_err();
+        assert!(matches!(
+            err,
+            StlError::CountMismatch {
+                declared: 3,
+                found: 2
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "the header declares 3 triangles, but the file has 2"
+        );
+
+        let bytes = binary_stl(1, &[triangle, triangle]);
+        assert!(matches!(
+            StlModel::parse(&bytes).unwrap_err(),
+            StlError::CountMismatch {
+                declared: 1,
+                found: 2
+            }
+        ));
+
+        assert!(matches!(
+            StlModel::parse(&[0; 20]).unwrap_err(),
+            StlError::UnexpectedEnd
+        ));
+    }
+
+    #[test]
+    fn malformed_ascii() {
+        let input =
+            "solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nendloop\nendfacet";
+        assert!(matches!(
+            StlModel::parse(input.as_bytes()).unwrap_err(),
+            StlError::InvalidFacet {
+                line: 7,
+                vertices: 2
+            }
+        ));
+
+        let input = "solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0";
+        assert!(matches!(
+            StlModel::parse(input.as_bytes()).unwrap_err(),
+            StlError::MissingValues { line: 4, .. }
+        ));
+
+        let input = "solid\nfacet normal 0 0 1\nouter loop\nvertex 0 zero 0";
+        assert!(matches!(
+            StlModel::parse(input.as_bytes()).unwrap_err(),
+            StlError::InvalidNumber { line: 4, .. }
+        ));
+
+        let input = "solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0";
+        assert!(matches!(
+            StlModel::parse(input.as_bytes()).unwrap_err(),
+            StlError::InvalidFacet {
+                line: 4,
+                vertices: 1
+            }
+        ));
+    }
+}
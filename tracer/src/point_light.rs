@@ -1,8 +1,19 @@
-use image::color::RGBAColor;
+use image::color::Color;
 
 use crate::point::coord::Coord;
 
+/// Light source with no size, emitting the same intensity in every direction
+#[derive(Debug, Clone, PartialEq)]
 pub struct PointLight {
-    pub color: RGBAColor,
+    pub intensity: Color,
     pub position: Coord,
 }
+
+impl PointLight {
+    pub fn new(position: Coord, intensity: Color) -> Self {
+        Self {
+            intensity,
+            position,
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::point::{coord::Coord, vector::Vector};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Ray {
     pub origin: Coord,
     pub dir: Vector,
@@ -11,7 +11,7 @@ impl Ray {
         Self { origin, dir }
     }
 
-    fn position_at(&self, t: f64) -> Coord {
+    pub fn position_at(&self, t: f64) -> Coord {
         self.origin.add_vector(&self.dir.scalar_mult(t))
     }
 }
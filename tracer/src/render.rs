@@ -0,0 +1,870 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    hash::Hasher,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use checkpoint::{Checkpoint, CheckpointError, CheckpointSettings, Fnv};
+use image::{canvas::Canvas, color::Color};
+use scene_hash::SceneHash;
+
+use crate::{
+    camera::Camera,
+    integrator::Integrator,
+    sampling::{PixelEstimate, Sampler},
+    world::World,
+};
+
+pub mod checkpoint;
+mod scene_hash;
+
+/// Rectangle of the image that is rendered as a single unit of work
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tile {
+    /// Column of the top left pixel
+    pub x: usize,
+    /// Row of the top left pixel
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Tile {
+    /// Split an image into tiles of `size` pixels of side, row by row. The tiles on the right and
+    /// bottom edges are smaller when the size of the image is not a multiple of `size`
+    pub fn split(width: usize, height: usize, size: usize) -> Vec<Tile> {
+        let size = size.max(1);
+        (0..height)
+            .step_by(size)
+            .flat_map(|y| {
+                (0..width).step_by(size).map(move |x| Tile {
+                    x,
+                    y,
+                    width: size.min(width - x),
+                    height: size.min(height - y),
+                })
+            })
+            .collect()
+    }
+
+    /// Position (column, row) of every pixel in the tile, row by row
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> {
+        let tile = *self;
+        (tile.y..tile.y + tile.height)
+            .flat_map(move |y| (tile.x..tile.x + tile.width).map(move |x| (x, y)))
+    }
+}
+
+/// Tiles waiting to be rendered, shared by every thread of a render
+///
+/// Each thread has its own queue, and takes tiles from its front. When it is empty, the thread
+/// steals from the back of the queues of the others, so no thread sits idle while there is work.
+struct TileQueue {
+    queues: Vec<Mutex<VecDeque<Tile>>>,
+}
+
+impl TileQueue {
+    /// Deal the tiles between the queues of the workers, in turns
+    fn new(tiles: &[Tile], workers: usize) -> Self {
+        let mut queues = vec![VecDeque::new(); workers.max(1)];
+        let count = queues.len();
+        for (i, tile) in tiles.iter().enumerate() {
+            queues[i % count].push_back(*tile);
+        }
+
+        Self {
+            queues: queues.into_iter().map(Mutex::new).collect(),
+        }
+    }
+
+    /// Next tile for a worker, `None` once every tile has been taken
+    fn next(&self, worker: usize) -> Option<Tile> {
+        let lock = |i: usize| self.queues[i].lock().expect("A tile queue was poisoned");
+        if let Some(tile) = lock(worker).pop_front() {
+            return Some(tile);
+        }
+
+        let count = self.queues.len();
+        (1..count).find_map(|offset| lock((worker + offset) % count).pop_back())
+    }
+}
+
+/// Progress of a render, given to observers as tiles are finished
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Number of tiles already rendered
+    pub done: usize,
+    /// Number of the tiles done that were loaded from a checkpoint instead of rendered
+    pub resumed: usize,
+    pub total: usize,
+    /// Time since the render started
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    pub fn percentage(&self) -> f64 {
+        match self.total {
+            0 => 100.0,
+            total => self.done as f64 * 100.0 / total as f64,
+        }
+    }
+
+    /// Estimated time until the render is done, assuming the remaining tiles take as long as the
+    /// ones already rendered. `None` until the first tile is rendered
+    pub fn eta(&self) -> Option<Duration> {
+        let rendered = self.done - self.resumed;
+        (rendered > 0).then(|| {
+            self.elapsed
+                .mul_f64((self.total - self.done) as f64 / rendered as f64)
+        })
+    }
+}
+
+/// Receives events about a render as it goes
+///
+/// Every callback is called from the thread that called `Renderer::render_with`, never from the
+/// workers, so observers don't need to be thread safe.
+pub trait RenderObserver {
+    fn tile_started(&mut self, _tile: &Tile) {}
+
+    fn tile_finished(&mut self, _tile: &Tile, _progress: &Progress) {}
+}
+
+/// Observer that ignores every event
+impl RenderObserver for () {}
+
+/// Lets a render be stopped from another thread, or from an observer
+///
+/// Clones share the same state, so cancelling any of them cancels the render. The renderer checks
+/// it between tiles, so the tiles being rendered are still finished.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// How a render ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderStatus {
+    /// Every tile was rendered
+    Complete,
+    /// The cancel token was cancelled before every tile was rendered
+    Cancelled,
+    /// The time budget ran out before every tile was rendered
+    OutOfTime,
+}
+
+/// Image produced by a render. Unless it is complete, the pixels of the tiles that were not
+/// rendered are left black
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    pub canvas: Canvas,
+    pub status: RenderStatus,
+    pub progress: Progress,
+    /// Colors of the pixels, row by row, before they are clamped to fit in the canvas
+    pub buffer: Vec<Color>,
+    /// Number of samples taken by each pixel, row by row. 0 for the pixels not rendered
+    pub sample_counts: Vec<usize>,
+}
+
+impl Rendered {
+    /// Image of the number of samples taken by each pixel, to see where adaptive sampling spent
+    /// its time. It goes from black, for no samples, through red and yellow to white, for the
+    /// pixels that took the most samples
+    pub fn sample_heatmap(&self) -> Canvas {
+        let (width, height) = (self.canvas.width, self.canvas.height);
+        let max = self.sample_counts.iter().copied().max().unwrap_or(0).max(1);
+
+        let mut heatmap = Canvas::with_size(height, width);
+        for (i, &count) in self.sample_counts.iter().enumerate() {
+            let t = count as f64 / max as f64;
+            let ramp = |start: f64| ((t - start) * 3.0).clamp(0.0, 1.0);
+            heatmap.set_pixel_color(
+                (i / width, i % width),
+                Color::new(ramp(0.0), ramp(1.0 / 3.0), ramp(2.0 / 3.0)),
+            );
+        }
+        heatmap
+    }
+}
+
+/// Message from the workers to the thread writing the canvas
+enum TileEvent {
+    Started(Tile),
+    Finished(Tile, Vec<PixelEstimate>),
+}
+
+/// Renders a world, as seen by a camera, into a canvas
+///
+/// The image is split into tiles which are rendered in parallel by `threads` threads. Every pixel
+/// is computed on its own, with its own random numbers, so the image is the same regardless of the
+/// number of threads or the order in which the tiles are rendered.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    pub threads: usize,
+    /// Side, in pixels, of the tiles the image is split into
+    pub tile_size: usize,
+    pub sampler: Sampler,
+    pub integrator: Integrator,
+    /// Wall clock time after which no more tiles are started, and the partial image is returned
+    pub time_budget: Option<Duration>,
+    pub cancel: CancelToken,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            tile_size: 16,
+            sampler: Sampler::default(),
+            integrator: Integrator::default(),
+            time_budget: None,
+            cancel: CancelToken::default(),
+        }
+    }
+}
+
+impl Renderer {
+    pub fn render(&self, world: &World, camera: &Camera) -> Canvas {
+        self.render_with(world, camera, &mut ()).canvas
+    }
+
+    /// Render, telling the observer about every tile. The render stops early if it is cancelled or
+    /// runs out of time
+    pub fn render_with(
+        &self,
+        world: &World,
+        camera: &Camera,
+        observer: &mut dyn RenderObserver,
+    ) -> Rendered {
+        self.render_tiles(world, camera, observer, &[], &mut |_, _| true)
+    }
+
+    /// Render, saving the finished tiles to a checkpoint file every `settings.interval`, and once
+    /// more when the render ends. If the file already exists, the render is resumed from it, and
+    /// only the tiles missing from it are rendered
+    ///
+    /// Resuming fails if the checkpoint was made for another scene, camera or settings.
+    pub fn render_with_checkpoint(
+        &self,
+        world: &World,
+        camera: &Camera,
+        observer: &mut dyn RenderObserver,
+        settings: &CheckpointSettings,
+    ) -> Result<Rendered, CheckpointError> {
+        let scene_hash = self.scene_hash(world, camera);
+        let mut checkpoint = match settings.path.exists() {
+            true => Checkpoint::load(&settings.path)?,
+            false => Checkpoint::new(scene_hash, camera.hsize, camera.vsize),
+        };
+        if checkpoint.scene_hash != scene_hash {
+            return Err(CheckpointError::SceneMismatch {
+                expected: scene_hash,
+                found: checkpoint.scene_hash,
+            });
+        }
+
+        let resumed = checkpoint.tiles.clone();
+        let mut last_save = Instant::now();
+        let mut error = None;
+        let rendered = self.render_tiles(world, camera, observer, &resumed, &mut |tile, pixels| {
+            checkpoint.tiles.push((*tile, pixels.to_vec()));
+            if last_save.elapsed() < settings.interval {
+                return true;
+            }
+            last_save = Instant::now();
+            checkpoint
+                .save(&settings.path)
+                .map_err(|err| error = Some(err))
+                .is_ok()
+        });
+
+        if let Some(err) = error {
+            return Err(err);
+        }
+        checkpoint.save(&settings.path)?;
+        Ok(rendered)
+    }
+
+    /// Hash of everything that changes the rendered image, to know if a checkpoint belongs to a
+    /// render. It is the same on every run and every machine
+    pub fn scene_hash(&self, world: &World, camera: &Camera) -> u64 {
+        let mut hasher = Fnv::default();
+        world.scene_hash(&mut hasher);
+        camera.scene_hash(&mut hasher);
+        self.tile_size.scene_hash(&mut hasher);
+        self.sampler.scene_hash(&mut hasher);
+        self.integrator.scene_hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Render every tile but the ones already done. `on_finished` is called with every rendered
+    /// tile, and returning `false` from it stops the render
+    fn render_tiles(
+        &self,
+        world: &World,
+        camera: &Camera,
+        observer: &mut dyn RenderObserver,
+        done: &[(Tile, Vec<PixelEstimate>)],
+        on_finished: &mut dyn FnMut(&Tile, &[PixelEstimate]) -> bool,
+    ) -> Rendered {
+        let start = Instant::now();
+        let deadline = self.time_budget.map(|budget| start + budget);
+        let aborted = AtomicBool::new(false);
+        let should_stop = || {
+            self.cancel.is_cancelled()
+                || aborted.load(Ordering::Relaxed)
+                || deadline.is_some_and(|end| Instant::now() >= end)
+        };
+
+        let mut canvas = Canvas::with_size(camera.vsize, camera.hsize);
+        let mut buffer = vec![Color::black(); camera.hsize * camera.vsize];
+        let mut sample_counts = vec![0; camera.hsize * camera.vsize];
+        let mut write = |tile: &Tile, pixels: &[PixelEstimate]| {
+            for ((x, y), pixel) in tile.pixels().zip(pixels) {
+                canvas.set_pixel_color((y, x), pixel.color);
+                buffer[y * camera.hsize + x] = pixel.color;
+                sample_counts[y * camera.hsize + x] = pixel.samples;
+            }
+        };
+        for (tile, pixels) in done {
+            write(tile, pixels);
+        }
+
+        let mut tiles = Tile::split(camera.hsize, camera.vsize, self.tile_size);
+        let total = tiles.len();
+        let finished: HashSet<_> = done.iter().map(|(tile, _)| tile).collect();
+        tiles.retain(|tile| !finished.contains(tile));
+        let mut progress = Progress {
+            done: total - tiles.len(),
+            resumed: total - tiles.len(),
+            total,
+            elapsed: Duration::ZERO,
+        };
+        let mut handle = |event: TileEvent| match event {
+            TileEvent::Started(tile) => observer.tile_started(&tile),
+            TileEvent::Finished(tile, pixels) => {
+                if !on_finished(&tile, &pixels) {
+                    aborted.store(true, Ordering::Relaxed);
+                }
+                write(&tile, &pixels);
+                progress.done += 1;
+                progress.elapsed = start.elapsed();
+                observer.tile_finished(&tile, &progress);
+            }
+        };
+
+        if self.threads <= 1 {
+            for tile in tiles {
+                if should_stop() {
+                    break;
+                }
+                handle(TileEvent::Started(tile));
+                handle(TileEvent::Finished(
+                    tile,
+                    self.render_tile(world, camera, tile),
+                ));
+            }
+        } else {
+            // Workers send their events, and only this thread writes to the canvas
+            let queue = TileQueue::new(&tiles, self.threads);
+            let (sender, receiver) = mpsc::channel();
+            thread::scope(|scope| {
+                for worker in 0..self.threads {
+                    let (queue, sender, should_stop) = (&queue, sender.clone(), &should_stop);
+                    scope.spawn(move || {
+                        while !should_stop() {
+                            let Some(tile) = queue.next(worker) else {
+                                return;
+                            };
+                            if sender.send(TileEvent::Started(tile)).is_err() {
+                                return;
+                            }
+                            let pixels = self.render_tile(world, camera, tile);
+                            if sender.send(TileEvent::Finished(tile, pixels)).is_err() {
+                                return;
+                            }
+                        }
+                    });
+                }
+                drop(sender);
+
+                receiver.into_iter().for_each(&mut handle);
+            });
+        }
+
+        progress.elapsed = start.elapsed();
+        let status = match progress.done == progress.total {
+            true => RenderStatus::Complete,
+            false if self.cancel.is_cancelled() => RenderStatus::Cancelled,
+            false => RenderStatus::OutOfTime,
+        };
+
+        Rendered {
+            canvas,
+            status,
+            progress,
+            buffer,
+            sample_counts,
+        }
+    }
+
+    /// Colors, and numbers of samples, of the pixels of the tile, row by row
+    fn render_tile(&self, world: &World, camera: &Camera, tile: Tile) -> Vec<PixelEstimate> {
+        tile.pixels()
+            .map(|(x, y)| {
+                self.sampler.sample_pixel(x, y, |px, py, rng| {
+                    let ray = camera.ray_through(px, py);
+                    self.integrator.color_at(world, &ray, rng)
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_render {
+    use super::*;
+    use crate::{
+        integrator::PathTracer,
+        material::Material,
+        point::{coord::Coord, vector::Vector},
+        point_light::PointLight,
+        sampling::{filter::PixelFilter, AdaptiveSampling, SamplePattern},
+        shapes::{plane::Plane, sphere::Sphere, Hittable, Shapes},
+    };
+    use image::color::RGBAColor;
+    use std::f64::consts;
+
+    fn scene() -> (World, Camera) {
+        let mut floor = Plane::default();
+        floor.material.reflective = 0.3;
+        let mut ball = Sphere::default();
+        ball.transformation.translate((0, 1, 0));
+        ball.material = Material {
+            color: Color::new(0.8, 0.2, 0.1),
+            transparency: 0.5,
+            refractive_index: 1.5,
+            ..Default::default()
+        };
+
+        let mut world = World::new(vec![Shapes::Plane(floor), Shapes::Sphere(ball)]);
+        world.lights = vec![PointLight::new(Coord::from((-10, 10, -10)), Color::white()).into()];
+
+        let mut camera = Camera::new(37, 23, consts::FRAC_PI_3);
+        camera.transformation.look_at(
+            &Coord::from((0.0, 1.5, -5.0)),
+            &Coord::from((0, 1, 0)),
+            &Vector::from((0, 1, 0)),
+        );
+        (world, camera)
+    }
+
+    #[test]
+    fn tiles_cover_image_once() {
+        let tiles = Tile::split(10, 7, 4);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[5],
+            Tile {
+                x: 8,
+                y: 4,
+                width: 2,
+                height: 3
+            }
+        );
+
+        let mut pixels: Vec<_> = tiles.iter().flat_map(Tile::pixels).collect();
+        pixels.sort();
+        let expected: Vec<_> = (0..10).flat_map(|x| (0..7).map(move |y| (x, y))).collect();
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn queue_steals_tiles() {
+        let tiles = Tile::split(4, 4, 1);
+        let queue = TileQueue::new(&tiles, 3);
+
+        // The first worker takes its own tiles in order, then steals the last ones of the others
+        let taken: Vec<_> = std::iter::from_fn(|| queue.next(0)).collect();
+        assert_eq!(taken.len(), 16);
+        assert_eq!(taken[0], tiles[0]);
+        assert_eq!(taken[1], tiles[3]);
+        assert_eq!(queue.next(1), None);
+        assert_eq!(queue.next(2), None);
+    }
+
+    #[test]
+    fn render_pixel() {
+        let mut world = World::new(vec![Shapes::Sphere(Sphere::default())]);
+        world.lights = vec![PointLight::new(Coord::from((-10, 10, -10)), Color::white()).into()];
+        let mut camera = Camera::new(11, 11, consts::FRAC_PI_2);
+        camera.transformation.look_at(
+            &Coord::from((0, 0, -5)),
+            &Coord::from((0, 0, 0)),
+            &Vector::from((0, 1, 0)),
+        );
+
+        let canvas = Renderer::default().render(&world, &camera);
+        let expected = world.color_at(&camera.ray_for_pixel(5, 5));
+        assert_eq!(canvas.get_color_at((5, 5)), Some(&expected.into()));
+    }
+
+    #[test]
+    fn threads_match_single_thread() {
+        let (world, camera) = scene();
+        let single = Renderer {
+            threads: 1,
+            tile_size: 8,
+            ..Default::default()
+        }
+        .render(&world, &camera);
+
+        for (threads, tile_size) in [(2, 8), (4, 5), (7, 1), (3, 64)] {
+            let multi = Renderer {
+                threads,
+                tile_size,
+                ..Default::default()
+            }
+            .render(&world, &camera);
+            assert_eq!(multi, single, "{threads} threads, tiles of {tile_size}");
+        }
+    }
+
+    #[test]
+    fn sampled_render_is_reproducible() {
+        let (world, camera) = scene();
+        let mut sampler = Sampler::new(SamplePattern::Jittered, 4, PixelFilter::mitchell());
+        sampler.seed = 9;
+        let render = |threads, seed| {
+            Renderer {
+                threads,
+                tile_size: 6,
+                sampler: Sampler {
+                    seed,
+                    ..sampler.clone()
+                },
+                ..Default::default()
+            }
+            .render(&world, &camera)
+        };
+
+        let single = render(1, 9);
+        assert_eq!(render(4, 9), single);
+        assert_ne!(render(4, 10), single);
+        assert_ne!(Renderer::default().render(&world, &camera), single);
+    }
+
+    /// Keeps every event, and cancels the render after some number of tiles
+    #[derive(Default)]
+    struct Recorder {
+        started: Vec<Tile>,
+        finished: Vec<(Tile, Progress)>,
+        cancel_after: Option<(usize, CancelToken)>,
+    }
+
+    impl RenderObserver for Recorder {
+        fn tile_started(&mut self, tile: &Tile) {
+            self.started.push(*tile);
+        }
+
+        fn tile_finished(&mut self, tile: &Tile, progress: &Progress) {
+            assert!(
+                self.started.contains(tile),
+                "{tile:?} finished before starting"
+            );
+            self.finished.push((*tile, *progress));
+            if let Some((after, cancel)) = &self.cancel_after {
+                if progress.done == *after {
+                    cancel.cancel();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn progress_percentage_and_eta() {
+        let progress = Progress {
+            done: 1,
+            resumed: 0,
+            total: 4,
+            elapsed: Duration::from_secs(2),
+        };
+        assert_eq!(progress.percentage(), 25.0);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(6)));
+
+        let progress = Progress {
+            done: 0,
+            ..progress
+        };
+        assert_eq!(progress.percentage(), 0.0);
+        assert_eq!(progress.eta(), None);
+    }
+
+    #[test]
+    fn observer_sees_every_tile() {
+        let (world, camera) = scene();
+        for threads in [1, 3] {
+            let renderer = Renderer {
+                threads,
+                tile_size: 10,
+                ..Default::default()
+            };
+            let mut recorder = Recorder::default();
+            let rendered = renderer.render_with(&world, &camera, &mut recorder);
+
+            assert_eq!(rendered.status, RenderStatus::Complete);
+            assert_eq!(rendered.progress.done, 12);
+            assert_eq!(recorder.started.len(), 12);
+            assert_eq!(recorder.finished.len(), 12);
+
+            // The progress grows with every tile
+            for (i, (_, progress)) in recorder.finished.iter().enumerate() {
+                assert_eq!(progress.done, i + 1);
+                assert_eq!(progress.total, 12);
+            }
+            let (_, last) = recorder.finished.last().unwrap();
+            assert_eq!(last.percentage(), 100.0);
+            assert_eq!(last.eta(), Some(Duration::ZERO));
+        }
+    }
+
+    #[test]
+    fn cancel_between_tiles() {
+        let (world, camera) = scene();
+        let full = Renderer::default().render(&world, &camera);
+
+        let renderer = Renderer {
+            threads: 1,
+            tile_size: 10,
+            ..Default::default()
+        };
+        let mut recorder = Recorder {
+            cancel_after: Some((2, renderer.cancel.clone())),
+            ..Default::default()
+        };
+        let rendered = renderer.render_with(&world, &camera, &mut recorder);
+        assert_eq!(rendered.status, RenderStatus::Cancelled);
+        assert_eq!(rendered.progress.done, 2);
+
+        // The finished tiles are kept, the rest of the image is left black
+        let tiles = Tile::split(camera.hsize, camera.vsize, 10);
+        for (i, tile) in tiles.iter().enumerate() {
+            for (x, y) in tile.pixels() {
+                let expected = match i < 2 {
+                    true => full.get_color_at((y, x)).cloned(),
+                    false => Some(RGBAColor::default()),
+                };
+                assert_eq!(rendered.canvas.get_color_at((y, x)).cloned(), expected);
+            }
+        }
+
+        // With many threads, the tiles already being rendered are still finished
+        let renderer = Renderer {
+            threads: 4,
+            tile_size: 2,
+            ..Default::default()
+        };
+        let mut recorder = Recorder {
+            cancel_after: Some((1, renderer.cancel.clone())),
+            ..Default::default()
+        };
+        let rendered = renderer.render_with(&world, &camera, &mut recorder);
+        assert_eq!(rendered.status, RenderStatus::Cancelled);
+        assert!(rendered.progress.done < rendered.progress.total);
+        assert_eq!(recorder.started.len(), recorder.finished.len());
+    }
+
+    #[test]
+    fn adaptive_render_heatmap() {
+        let (world, camera) = scene();
+        let renderer = Renderer {
+            sampler: Sampler {
+                adaptive: Some(AdaptiveSampling::new(4, 16, 0.005)),
+                ..Sampler::new(SamplePattern::Sobol, 4, PixelFilter::Box)
+            },
+            ..Default::default()
+        };
+        let rendered = renderer.render_with(&world, &camera, &mut ());
+        let counts = &rendered.sample_counts;
+        assert_eq!(counts.len(), camera.hsize * camera.vsize);
+        assert!(counts.iter().all(|&count| (4..=16).contains(&count)));
+
+        // Flat areas stop early, the edges of the sphere take more samples
+        let min = counts.iter().filter(|&&count| count == 4).count();
+        let max = counts.iter().filter(|&&count| count == 16).count();
+        assert!(min > counts.len() / 2);
+        assert!(max > 0);
+
+        let heatmap = rendered.sample_heatmap();
+        assert_eq!(
+            (heatmap.width, heatmap.height),
+            (camera.hsize, camera.vsize)
+        );
+        let i = counts.iter().position(|&count| count == 16).unwrap();
+        let hottest = heatmap.get_color_at((i / camera.hsize, i % camera.hsize));
+        assert_eq!(hottest, Some(&Color::white().into()));
+    }
+
+    #[test]
+    fn path_traced_render() {
+        let (world, camera) = scene();
+        let renderer = Renderer {
+            sampler: Sampler {
+                seed: 3,
+                ..Sampler::new(SamplePattern::Sobol, 2, PixelFilter::Box)
+            },
+            integrator: Integrator::PathTracer(PathTracer {
+                max_bounces: 4,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let rendered = renderer.render_with(&world, &camera, &mut ());
+        let single = Renderer {
+            threads: 1,
+            ..renderer.clone()
+        }
+        .render_with(&world, &camera, &mut ());
+        assert_eq!(rendered.canvas, single.canvas);
+        assert_eq!(rendered.buffer, single.buffer);
+
+        // The buffer keeps the colors that don't fit in the canvas
+        assert_eq!(rendered.buffer.len(), camera.hsize * camera.vsize);
+        for (i, color) in rendered.buffer.iter().enumerate() {
+            let pixel = rendered
+                .canvas
+                .get_color_at((i / camera.hsize, i % camera.hsize));
+            assert_eq!(pixel, Some(&(*color).into()));
+        }
+        assert!(rendered.buffer.iter().any(|color| color.red > 0.0));
+    }
+
+    #[test]
+    fn time_budget_returns_partial_image() {
+        let (world, camera) = scene();
+        let renderer = Renderer {
+            time_budget: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let rendered = renderer.render_with(&world, &camera, &mut ());
+        assert_eq!(rendered.status, RenderStatus::OutOfTime);
+        assert_eq!(rendered.progress.done, 0);
+        assert_eq!(
+            rendered.canvas,
+            Canvas::with_size(camera.vsize, camera.hsize)
+        );
+
+        let renderer = Renderer {
+            time_budget: Some(Duration::from_secs(600)),
+            ..Default::default()
+        };
+        let rendered = renderer.render_with(&world, &camera, &mut ());
+        assert_eq!(rendered.status, RenderStatus::Complete);
+    }
+
+    #[test]
+    fn resume_from_checkpoint() {
+        let (world, camera) = scene();
+        let full = Renderer::default().render(&world, &camera);
+        let name = format!("tracer_test_resume_checkpoint_{}", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let settings = CheckpointSettings::new(&path, Duration::ZERO);
+
+        let renderer = Renderer {
+            threads: 1,
+            tile_size: 10,
+            ..Default::default()
+        };
+        let mut recorder = Recorder {
+            cancel_after: Some((5, renderer.cancel.clone())),
+            ..Default::default()
+        };
+        let rendered = renderer
+            .render_with_checkpoint(&world, &camera, &mut recorder, &settings)
+            .unwrap();
+        assert_eq!(rendered.status, RenderStatus::Cancelled);
+        assert_eq!(Checkpoint::load(&path).unwrap().tiles.len(), 5);
+
+        // Only the missing tiles are rendered, and the image is the same as an uninterrupted one
+        let renderer = Renderer {
+            threads: 3,
+            tile_size: 10,
+            ..Default::default()
+        };
+        let mut recorder = Recorder::default();
+        let rendered = renderer
+            .render_with_checkpoint(&world, &camera, &mut recorder, &settings)
+            .unwrap();
+        assert_eq!(rendered.status, RenderStatus::Complete);
+        assert_eq!(rendered.progress.resumed, 5);
+        assert_eq!(recorder.started.len(), 7);
+        assert_eq!(rendered.canvas, full);
+        assert_eq!(Checkpoint::load(&path).unwrap().tiles.len(), 12);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn scene_hash_of_parameters() {
+        let (mut world, camera) = scene();
+        let renderer = Renderer::default();
+        let hash = renderer.scene_hash(&world, &camera);
+        assert_eq!(hash, renderer.scene_hash(&scene().0, &camera));
+
+        // What is computed from the shapes does not change the scene
+        world.build_bvh();
+        world.emitters();
+        assert_eq!(renderer.scene_hash(&world, &camera), hash);
+
+        world.shapes_mut()[0].material_mut().ambient += 0.01;
+        assert_ne!(renderer.scene_hash(&world, &camera), hash);
+    }
+
+    #[test]
+    fn refuse_checkpoint_of_other_scene() {
+        let (mut world, camera) = scene();
+        let name = format!("tracer_test_other_scene_checkpoint_{}", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let settings = CheckpointSettings::new(&path, Duration::from_secs(60));
+
+        let renderer = Renderer {
+            time_budget: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        renderer
+            .render_with_checkpoint(&world, &camera, &mut (), &settings)
+            .unwrap();
+
+        world.lights[0] =
+            PointLight::new(Coord::from((-10, 10, -10)), Color::new(0.5, 0.5, 0.5)).into();
+        let err = renderer
+            .render_with_checkpoint(&world, &camera, &mut (), &settings)
+            .unwrap_err();
+        assert!(matches!(err, CheckpointError::SceneMismatch { .. }));
+
+        let other_tiles = Renderer {
+            tile_size: 7,
+            ..renderer.clone()
+        };
+        world.lights[0] = PointLight::new(Coord::from((-10, 10, -10)), Color::white()).into();
+        let err = other_tiles
+            .render_with_checkpoint(&world, &camera, &mut (), &settings)
+            .unwrap_err();
+        assert!(matches!(err, CheckpointError::SceneMismatch { .. }));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    fmt,
+    hash::Hasher,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use image::color::Color;
+
+use super::Tile;
+use crate::sampling::PixelEstimate;
+
+/// First bytes of every checkpoint file
+const MAGIC: &[u8; 8] = b"TRCHKPT\0";
+/// Version of the format, increased whenever it changes
+const VERSION: u32 = 2;
+
+/// Errors found while saving, loading or resuming from a checkpoint
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(std::io::Error),
+    /// The file does not start like a checkpoint
+    NotACheckpoint,
+    UnsupportedVersion(u32),
+    /// The file ended in the middle of some value
+    UnexpectedEnd,
+    /// A tile that is not inside the image
+    InvalidTile(Tile),
+    /// The checkpoint was made for a different scene, camera or render settings
+    SceneMismatch {
+        expected: u64,
+        found: u64,
+    },
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(err) => write!(f, "could not access checkpoint file: {err}"),
+            CheckpointError::NotACheckpoint => write!(f, "the file is not a render checkpoint"),
+            CheckpointError::UnsupportedVersion(version) => write!(
+                f,
+                "the checkpoint has version {version}, only version {VERSION} is supported"
+            ),
+            CheckpointError::UnexpectedEnd => write!(f, "the checkpoint file is truncated"),
+            CheckpointError::InvalidTile(tile) => {
+                write!(f, "the checkpoint has an invalid tile: {tile:?}")
+            }
+            CheckpointError::SceneMismatch { expected, found } => write!(
+                f,
+                "the checkpoint was made for a different scene or settings \
+                 (checkpoint hash {found:016x}, current hash {expected:016x})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<std::io::Error> for CheckpointError {
+    fn from(err: std::io::Error) -> Self {
+        CheckpointError::Io(err)
+    }
+}
+
+/// Where, and how often, a render saves its checkpoint
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckpointSettings {
+    pub path: PathBuf,
+    /// Minimum time between two saves
+    pub interval: Duration,
+}
+
+impl CheckpointSettings {
+    pub fn new<P: Into<PathBuf>>(path: P, interval: Duration) -> Self {
+        Self {
+            path: path.into(),
+            interval,
+        }
+    }
+}
+
+/// Tiles finished by a render, with the colors of their pixels at full precision
+///
+/// The file is little endian: the magic bytes and version, the scene hash, the size of the image
+/// and the number of tiles, followed by the position and size of every tile with the color and
+/// number of samples of each of its pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// Hash of the scene and settings of the render, see `Renderer::scene_hash`
+    pub scene_hash: u64,
+    pub width: usize,
+    pub height: usize,
+    /// Finished tiles, with their pixels row by row
+    pub tiles: Vec<(Tile, Vec<PixelEstimate>)>,
+}
+
+impl Checkpoint {
+    pub fn new(scene_hash: u64, width: usize, height: usize) -> Self {
+        Self {
+            scene_hash,
+            width,
+            height,
+            tiles: vec![],
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CheckpointError> {
+        let content = std::fs::read(path)?;
+        Self::parse(&content)
+    }
+
+    /// Write the checkpoint to a temporary file which then replaces the old one, so a crash while
+    /// saving never leaves a broken checkpoint behind
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        std::fs::write(&temporary, self.to_bytes())?;
+        std::fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    pub fn parse(input: &[u8]) -> Result<Self, CheckpointError> {
+        if !input.starts_with(MAGIC) {
+            return Err(CheckpointError::NotACheckpoint);
+        }
+        let mut reader = Reader(&input[MAGIC.len()..]);
+
+        let version = u32::from_le_bytes(reader.bytes()?);
+        if version != VERSION {
+            return Err(CheckpointError::UnsupportedVersion(version));
+        }
+
+        let scene_hash = reader.u64()?;
+        let width = reader.usize()?;
+        let height = reader.usize()?;
+        let count = reader.usize()?;
+
+        let mut tiles = vec![];
+        for _ in 0..count {
+            let tile = Tile {
+                x: reader.usize()?,
+                y: reader.usize()?,
+                width: reader.usize()?,
+                height: reader.usize()?,
+            };
+            let inside = tile.x.checked_add(tile.width).is_some_and(|x| x <= width)
+                && tile.y.checked_add(tile.height).is_some_and(|y| y <= height);
+            let Some(area) = tile.width.checked_mul(tile.height).filter(|_| inside) else {
+                return Err(CheckpointError::InvalidTile(tile));
+            };
+
+            let pixels = (0..area)
+                .map(|_| {
+                    Ok(PixelEstimate {
+                        color: Color::new(reader.f64()?, reader.f64()?, reader.f64()?),
+                        samples: reader.usize()?,
+                    })
+                })
+                .collect::<Result<_, CheckpointError>>()?;
+            tiles.push((tile, pixels));
+        }
+
+        Ok(Self {
+            scene_hash,
+            width,
+            height,
+            tiles,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(self.scene_hash.to_le_bytes());
+        for value in [self.width, self.height, self.tiles.len()] {
+            bytes.extend((value as u64).to_le_bytes());
+        }
+
+        for (tile, pixels) in &self.tiles {
+            for value in [tile.x, tile.y, tile.width, tile.height] {
+                bytes.extend((value as u64).to_le_bytes());
+            }
+            for PixelEstimate { color, samples } in pixels {
+                for value in [color.red, color.green, color.blue] {
+                    bytes.extend(value.to_le_bytes());
+                }
+                bytes.extend((*samples as u64).to_le_bytes());
+            }
+        }
+        bytes
+    }
+}
+
+/// 64 bit FNV-1a hash, which unlike the hasher of the standard library is guaranteed to give the
+/// same result in every build
+pub fn hash(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// Hasher of `hash`, for values written a piece at a time. Numbers are written as little endian,
+/// so they give the same hash on every machine
+pub struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = bytes.iter().fold(self.0, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+}
+
+/// Reads little endian values from the start of some bytes
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], CheckpointError> {
+        let (value, rest) = self
+            .0
+            .split_first_chunk()
+            .ok_or(CheckpointError::UnexpectedEnd)?;
+        self.0 = rest;
+        Ok(*value)
+    }
+
+    fn u64(&mut self) -> Result<u64, CheckpointError> {
+        self.bytes().map(u64::from_le_bytes)
+    }
+
+    fn usize(&mut self) -> Result<usize, CheckpointError> {
+        self.u64().map(|value| value as usize)
+    }
+
+    fn f64(&mut self) -> Result<f64, CheckpointError> {
+        self.bytes().map(f64::from_le_bytes)
+    }
+}
+
+#[cfg(test)]
+mod test_checkpoint {
+    use super::*;
+
+    fn checkpoint() -> Checkpoint {
+        let mut checkpoint = Checkpoint::new(0xdeadbeef, 4, 3);
+        checkpoint.tiles.push((
+            Tile {
+                x: 2,
+                y: 0,
+                width: 2,
+                height: 1,
+            },
+            vec![
+                PixelEstimate {
+                    color: Color::new(0.1, 0.2, 0.3),
+                    samples: 1,
+                },
+                PixelEstimate {
+                    color: Color::new(1.5, -0.25, 1e-9),
+                    samples: 64,
+                },
+            ],
+        ));
+        checkpoint
+    }
+
+    #[test]
+    fn round_trip() {
+        let checkpoint = checkpoint();
+        assert_eq!(
+            Checkpoint::parse(&checkpoint.to_bytes()).unwrap(),
+            checkpoint
+        );
+
+        let name = format!("tracer_test_checkpoint_round_trip_{}", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        checkpoint.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), checkpoint);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn invalid_files() {
+        let err = Checkpoint::parse(b"P3\n2 1\n255\n").unwrap_err();
+        assert!(matches!(err, CheckpointError::NotACheckpoint));
+
+        let mut bytes = checkpoint().to_bytes();
+        bytes[MAGIC.len()] = 7;
+        let err = Checkpoint::parse(&bytes).unwrap_err();
+        assert!(matches!(err, CheckpointError::UnsupportedVersion(7)));
+
+        let bytes = checkpoint().to_bytes();
+        let err = Checkpoint::parse(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(matches!(err, CheckpointError::UnexpectedEnd));
+
+        let mut outside = checkpoint();
+        outside.tiles[0].0.x = 3;
+        let err = Checkpoint::parse(&outside.to_bytes()).unwrap_err();
+        assert!(matches!(
+            err,
+            CheckpointError::InvalidTile(Tile { x: 3, .. })
+        ));
+
+        // Too big for the pixels to be counted
+        let mut huge = Checkpoint::new(0, usize::MAX, usize::MAX);
+        huge.tiles.push((
+            Tile {
+                x: 0,
+                y: 0,
+                width: usize::MAX,
+                height: 2,
+            },
+            vec![],
+        ));
+        let err = Checkpoint::parse(&huge.to_bytes()).unwrap_err();
+        assert!(matches!(
+            err,
+            CheckpointError::InvalidTile(Tile { height: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn stable_hash() {
+        assert_eq!(hash(b""), 0xcbf29ce484222325);
+        assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_ne!(hash(b"scene 1"), hash(b"scene 2"));
+    }
+}
@@ -0,0 +1,372 @@
+use std::{hash::Hasher, sync::Arc};
+
+use image::{canvas::Canvas, color::Color, hdr::HdrImage};
+
+use crate::{
+    area_light::{AreaLight, AreaShape},
+    background::{environment::EnvironmentMap, sky::Sky, Background},
+    bsdf::{
+        conductor::Conductor, dielectric::Dielectric, lambertian::Lambertian,
+        principled::Principled, Bsdfs,
+    },
+    camera::Camera,
+    integrator::{EmitterSampling, Integrator, PathTracer},
+    light::{directional::DirectionalLight, spot::SpotLight, Lights},
+    material::Material,
+    matrix::square4::Matrix4x4,
+    point::{coord::Coord, vector::Vector},
+    point_light::PointLight,
+    sampling::{filter::PixelFilter, AdaptiveSampling, SamplePattern, Sampler},
+    shapes::{
+        cone::Cone,
+        csg::{Csg, CsgOperation},
+        cube::Cube,
+        cylinder::Cylinder,
+        group::Group,
+        plane::Plane,
+        smooth_triangle::SmoothTriangle,
+        sphere::Sphere,
+        triangle::Triangle,
+        Shapes,
+    },
+    transformations::TransformationMatrix,
+    world::World,
+};
+
+/// Hashing of the parameters that change a rendered image
+///
+/// Works like `Hash`, but floats can be hashed too, and whatever is computed from the parameters
+/// (hierarchies, emitters, distributions of environment maps) is left out. Each variant of an enum
+/// writes its index before its values, so different variants never hash the same.
+pub trait SceneHash {
+    fn scene_hash<H: Hasher>(&self, state: &mut H);
+}
+
+/// Hash every field of the structs
+macro_rules! hash_fields {
+    ($($type:ty { $($field:ident),* })*) => {
+        $(impl SceneHash for $type {
+            fn scene_hash<H: Hasher>(&self, state: &mut H) {
+                $(self.$field.scene_hash(state);)*
+            }
+        })*
+    };
+}
+
+impl SceneHash for f64 {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.to_bits());
+    }
+}
+
+impl SceneHash for usize {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(*self);
+    }
+}
+
+impl SceneHash for u64 {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(*self);
+    }
+}
+
+impl SceneHash for u8 {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u8(*self);
+    }
+}
+
+impl SceneHash for bool {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u8(*self as u8);
+    }
+}
+
+impl<T: SceneHash + ?Sized> SceneHash for &T {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        (**self).scene_hash(state);
+    }
+}
+
+impl<T: SceneHash + ?Sized> SceneHash for Box<T> {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        (**self).scene_hash(state);
+    }
+}
+
+impl<T: SceneHash + ?Sized> SceneHash for Arc<T> {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        (**self).scene_hash(state);
+    }
+}
+
+impl<T: SceneHash> SceneHash for Option<T> {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            None => state.write_u8(0),
+            Some(value) => {
+                state.write_u8(1);
+                value.scene_hash(state);
+            }
+        }
+    }
+}
+
+/// The length goes first, so the values of consecutive lists cannot be mixed up
+impl<T: SceneHash> SceneHash for [T] {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        self.iter().for_each(|value| value.scene_hash(state));
+    }
+}
+
+impl<T: SceneHash> SceneHash for Vec<T> {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().scene_hash(state);
+    }
+}
+
+impl<T: SceneHash, const N: usize> SceneHash for [T; N] {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        self.iter().for_each(|value| value.scene_hash(state));
+    }
+}
+
+impl<A: SceneHash, B: SceneHash> SceneHash for (A, B) {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        self.0.scene_hash(state);
+        self.1.scene_hash(state);
+    }
+}
+
+impl<A: SceneHash, B: SceneHash, C: SceneHash> SceneHash for (A, B, C) {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        self.0.scene_hash(state);
+        self.1.scene_hash(state);
+        self.2.scene_hash(state);
+    }
+}
+
+impl SceneHash for Matrix4x4 {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        (0..4).for_each(|row| self[row].scene_hash(state));
+    }
+}
+
+impl SceneHash for Canvas {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        self.width.scene_hash(state);
+        self.height.scene_hash(state);
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let pixel = self.get_color_at((row, col)).copied().unwrap_or_default();
+                [pixel.red, pixel.green, pixel.blue, pixel.alpha].scene_hash(state);
+            }
+        }
+    }
+}
+
+impl SceneHash for HdrImage {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        self.width.scene_hash(state);
+        self.height.scene_hash(state);
+        for row in 0..self.height {
+            for col in 0..self.width {
+                self.get_color_at((row, col)).scene_hash(state);
+            }
+        }
+    }
+}
+
+hash_fields! {
+    Color { red, green, blue }
+    Coord { x, y, z }
+    Vector { x, y, z }
+    TransformationMatrix { matrix }
+    Material {
+        color, ambient, diffuse, specular, shininess, reflective, transparency,
+        refractive_index, emissive, texture, bsdf
+    }
+    Lambertian { albedo }
+    Conductor { eta, k, roughness }
+    Dielectric { ior, roughness }
+    Principled { base_color, metallic, roughness }
+    Sphere { transformation, material }
+    Plane { transformation, material }
+    Cube { transformation, material }
+    Cylinder { transformation, material, minimum, maximum, closed }
+    Cone { transformation, material, minimum, maximum, closed }
+    Csg { transformation, material, operation, left, right }
+    PointLight { intensity, position }
+    AreaLight { shape, intensity, samples }
+    SpotLight { position, direction, intensity, angle, inner_angle }
+    DirectionalLight { direction, intensity }
+    Sky { sun_elevation, sun_azimuth, turbidity, intensity, ground }
+    Camera { hsize, vsize, field_of_view, transformation }
+    AdaptiveSampling { batch, max_samples, threshold }
+    Sampler { pattern, samples, filter, seed, adaptive }
+    PathTracer { max_bounces, roulette_after, next_event_estimation, emitter_sampling }
+}
+
+impl SceneHash for Bsdfs {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Bsdfs::Lambertian(bsdf) => (0u8, bsdf).scene_hash(state),
+            Bsdfs::Conductor(bsdf) => (1u8, bsdf).scene_hash(state),
+            Bsdfs::Dielectric(bsdf) => (2u8, bsdf).scene_hash(state),
+            Bsdfs::Principled(bsdf) => (3u8, bsdf).scene_hash(state),
+        }
+    }
+}
+
+impl SceneHash for EnvironmentMap {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        self.image().scene_hash(state);
+        self.intensity.scene_hash(state);
+        self.rotation.scene_hash(state);
+    }
+}
+
+impl SceneHash for Triangle {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        self.transformation.scene_hash(state);
+        self.material.scene_hash(state);
+        self.texture_coords.scene_hash(state);
+        self.points().scene_hash(state);
+    }
+}
+
+impl SceneHash for SmoothTriangle {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        self.transformation.scene_hash(state);
+        self.material.scene_hash(state);
+        self.texture_coords.scene_hash(state);
+        self.points().scene_hash(state);
+        self.normals().scene_hash(state);
+    }
+}
+
+impl SceneHash for Group {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        self.transformation.scene_hash(state);
+        self.material.scene_hash(state);
+        self.children().scene_hash(state);
+    }
+}
+
+impl SceneHash for CsgOperation {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        let index: u8 = match self {
+            CsgOperation::Union => 0,
+            CsgOperation::Intersection => 1,
+            CsgOperation::Difference => 2,
+        };
+        index.scene_hash(state);
+    }
+}
+
+impl SceneHash for Shapes {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Shapes::Sphere(shape) => (0u8, shape).scene_hash(state),
+            Shapes::Plane(shape) => (1u8, shape).scene_hash(state),
+            Shapes::Cube(shape) => (2u8, shape).scene_hash(state),
+            Shapes::Cylinder(shape) => (3u8, shape).scene_hash(state),
+            Shapes::Cone(shape) => (4u8, shape).scene_hash(state),
+            Shapes::Triangle(shape) => (5u8, shape).scene_hash(state),
+            Shapes::SmoothTriangle(shape) => (6u8, shape).scene_hash(state),
+            Shapes::Group(shape) => (7u8, shape).scene_hash(state),
+            Shapes::Csg(shape) => (8u8, shape).scene_hash(state),
+        }
+    }
+}
+
+impl SceneHash for AreaShape {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            AreaShape::Rectangle { corner, u, v } => (0u8, (corner, u, v)).scene_hash(state),
+            AreaShape::Disk {
+                center,
+                normal,
+                radius,
+            } => (1u8, (center, normal, radius)).scene_hash(state),
+            AreaShape::Sphere { center, radius } => (2u8, (center, radius)).scene_hash(state),
+        }
+    }
+}
+
+impl SceneHash for Lights {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Lights::Point(light) => (0u8, light).scene_hash(state),
+            Lights::Area(light) => (1u8, light).scene_hash(state),
+            Lights::Spot(light) => (2u8, light).scene_hash(state),
+            Lights::Directional(light) => (3u8, light).scene_hash(state),
+        }
+    }
+}
+
+impl SceneHash for Background {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Background::Color(color) => (0u8, color).scene_hash(state),
+            Background::Gradient { bottom, top } => (1u8, (bottom, top)).scene_hash(state),
+            Background::Map(map) => (2u8, map).scene_hash(state),
+            Background::Sky(sky) => (3u8, sky).scene_hash(state),
+        }
+    }
+}
+
+impl SceneHash for World {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        self.shapes().scene_hash(state);
+        self.lights.scene_hash(state);
+        self.background.scene_hash(state);
+        self.max_depth.scene_hash(state);
+    }
+}
+
+impl SceneHash for SamplePattern {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        let index: u8 = match self {
+            SamplePattern::Center => 0,
+            SamplePattern::Stratified => 1,
+            SamplePattern::Jittered => 2,
+            SamplePattern::Halton => 3,
+            SamplePattern::Sobol => 4,
+        };
+        index.scene_hash(state);
+    }
+}
+
+impl SceneHash for PixelFilter {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            PixelFilter::Box => 0u8.scene_hash(state),
+            PixelFilter::Tent => 1u8.scene_hash(state),
+            PixelFilter::Gaussian { sigma } => (2u8, sigma).scene_hash(state),
+            PixelFilter::Mitchell { b, c } => (3u8, (b, c)).scene_hash(state),
+        }
+    }
+}
+
+impl SceneHash for EmitterSampling {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        let index: u8 = match self {
+            EmitterSampling::Bsdf => 0,
+            EmitterSampling::Light => 1,
+            EmitterSampling::Mis => 2,
+        };
+        index.scene_hash(state);
+    }
+}
+
+impl SceneHash for Integrator {
+    fn scene_hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Integrator::Whitted => 0u8.scene_hash(state),
+            Integrator::PathTracer(tracer) => (1u8, tracer).scene_hash(state),
+        }
+    }
+}
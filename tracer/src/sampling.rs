@@ -0,0 +1,686 @@
+use std::f64::consts;
+
+use filter::PixelFilter;
+use image::color::Color;
+use rng::Rng;
+
+use crate::point::vector::Vector;
+
+pub mod filter;
+pub mod rng;
+
+/// How the samples of a pixel are spread over it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SamplePattern {
+    /// A single sample through the center of the pixel, whatever the number asked for
+    #[default]
+    Center,
+    /// Centers of the cells of a regular grid
+    Stratified,
+    /// Random point inside each cell of a regular grid
+    Jittered,
+    /// Halton sequence in bases 2 and 3, with its digits randomly scrambled for each pixel
+    Halton,
+    /// First two dimensions of the Sobol sequence, randomly scrambled for each pixel
+    Sobol,
+}
+
+impl SamplePattern {
+    /// Positions of `count` samples in the unit square. The grid patterns use the largest square
+    /// grid that fits in `count`, so they, and `Center`, can return less samples
+    pub fn points(&self, count: usize, rng: &mut Rng) -> Vec<(f64, f64)> {
+        let count = count.max(1);
+        let side = (count as f64).sqrt() as usize;
+        let grid = (0..side * side).map(|i| ((i % side) as f64, (i / side) as f64));
+
+        match self {
+            SamplePattern::Center => vec![(0.5, 0.5)],
+            SamplePattern::Stratified => grid
+                .map(|(i, j)| ((i + 0.5) / side as f64, (j + 0.5) / side as f64))
+                .collect(),
+            SamplePattern::Jittered => grid
+                .map(|(i, j)| {
+                    (
+                        (i + rng.next_f64()) / side as f64,
+                        (j + rng.next_f64()) / side as f64,
+                    )
+                })
+                .collect(),
+            SamplePattern::Halton => {
+                let (x, y) = (DigitScramble::new(2, rng), DigitScramble::new(3, rng));
+                (0..count)
+                    .map(|i| (x.radical_inverse(i), y.radical_inverse(i)))
+                    .collect()
+            }
+            SamplePattern::Sobol => {
+                let scramble = rng.next_u64();
+                let (sx, sy) = (scramble as u32, (scramble >> 32) as u32);
+                (0..count as u32)
+                    .map(|i| {
+                        let to_unit = |bits: u32| bits as f64 / (1u64 << 32) as f64;
+                        (to_unit(i.reverse_bits() ^ sx), to_unit(sobol_y(i) ^ sy))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Random offsets added to each digit of a radical inverse
+///
+/// Changing every digit in the same way for all the points keeps them spread one per stratum,
+/// unlike shifting the points, while still giving each pixel a different pattern.
+struct DigitScramble {
+    base: usize,
+    offsets: Vec<usize>,
+}
+
+impl DigitScramble {
+    fn new(base: usize, rng: &mut Rng) -> Self {
+        // Enough digits for the precision of a f64
+        let digits = (f64::MANTISSA_DIGITS as f64 / (base as f64).log2()).ceil() as usize;
+        let offsets = (0..digits)
+            .map(|_| (rng.next_f64() * base as f64) as usize)
+            .collect();
+        Self { base, offsets }
+    }
+
+    /// Without scrambling, this is the digits of `i` mirrored around the decimal point
+    fn radical_inverse(&self, mut i: usize) -> f64 {
+        let mut result = 0.0;
+        let mut scale = 1.0 / self.base as f64;
+        for offset in &self.offsets {
+            result += ((i % self.base + offset) % self.base) as f64 * scale;
+            i /= self.base;
+            scale /= self.base as f64;
+        }
+        result.min(1.0 - f64::EPSILON)
+    }
+}
+
+/// Second dimension of the Sobol sequence, as the bits of a fraction
+fn sobol_y(mut i: u32) -> u32 {
+    let mut direction = 1u32 << 31;
+    let mut result = 0;
+    while i != 0 {
+        if i & 1 != 0 {
+            result ^= direction;
+        }
+        i >>= 1;
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+/// Two unit vectors that are perpendicular to the normal, and to each other
+pub fn orthonormal_basis(normal: &Vector) -> (Vector, Vector) {
+    // Building the basis without branching on the normal (Duff et al., 2017)
+    let sign = 1.0f64.copysign(normal.z);
+    let a = -1.0 / (sign + normal.z);
+    let b = normal.x * normal.y * a;
+    (
+        Vector::new(
+            1.0 + sign * normal.x * normal.x * a,
+            sign * b,
+            -sign * normal.x,
+        ),
+        Vector::new(b, sign + normal.y * normal.y * a, -normal.y),
+    )
+}
+
+/// Random direction on the hemisphere around the normal, more likely the closer it is to the
+/// normal. The probability density is `cos / PI`, where `cos` is the cosine between the direction
+/// and the normal
+pub fn cosine_hemisphere(normal: &Vector, rng: &mut Rng) -> Vector {
+    // Uniform point on the disk, projected up to the hemisphere
+    let radius = rng.next_f64().sqrt();
+    let angle = 2.0 * consts::PI * rng.next_f64();
+    let (x, y) = (radius * angle.cos(), radius * angle.sin());
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    (tangent * x + bitangent * y + normal.clone() * z).normalize()
+}
+
+/// Random direction inside the cone around `axis` whose half angle has cosine `cos_max`, every
+/// direction with the same probability. The probability density is `1 / (2 PI (1 - cos_max))`
+pub fn uniform_cone(axis: &Vector, cos_max: f64, rng: &mut Rng) -> Vector {
+    let cos = 1.0 - rng.next_f64() * (1.0 - cos_max);
+    let sin = (1.0 - cos * cos).max(0.0).sqrt();
+    let angle = 2.0 * consts::PI * rng.next_f64();
+
+    let (tangent, bitangent) = orthonormal_basis(axis);
+    (tangent * (sin * angle.cos()) + bitangent * (sin * angle.sin()) + axis.clone() * cos)
+        .normalize()
+}
+
+/// Weight of a sample taken with density `f` when the same direction could also have been chosen
+/// with density `g` by another strategy (Veach's power heuristic for multiple importance sampling).
+/// The weights of both strategies add up to 1
+pub fn power_heuristic(f: f64, g: f64) -> f64 {
+    let (f2, g2) = (f * f, g * g);
+    match f2 + g2 {
+        sum if sum > 0.0 => f2 / sum,
+        _ => 0.0,
+    }
+}
+
+/// Relative luminance of a linear color, how bright it looks
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.red + 0.7152 * color.green + 0.0722 * color.blue
+}
+
+/// Piecewise constant density over [0, 1), with one piece per weight, for choosing values in
+/// proportion to the weights. Without any positive weight every piece is equally likely
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution {
+    /// Running sum of the weights, divided by their total
+    cdf: Vec<f64>,
+}
+
+impl Distribution {
+    pub fn new(weights: &[f64]) -> Self {
+        let total: f64 = weights.iter().sum();
+        let mut sum = 0.0;
+        let cdf = weights
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| {
+                sum += match total > 0.0 {
+                    true => weight / total,
+                    false => 1.0 / weights.len() as f64,
+                };
+                // Rounding errors could leave the last value under 1
+                if i + 1 == weights.len() {
+                    1.0
+                } else {
+                    sum
+                }
+            })
+            .collect();
+        Self { cdf }
+    }
+
+    /// Value for a uniform random number `u`, with the piece it falls in and its density
+    pub fn sample(&self, u: f64) -> (f64, usize, f64) {
+        let index = self
+            .cdf
+            .partition_point(|&sum| sum <= u)
+            .min(self.cdf.len().saturating_sub(1));
+        let start = index.checked_sub(1).map_or(0.0, |i| self.cdf[i]);
+        let probability = self.cdf[index] - start;
+        let offset = match probability > 0.0 {
+            true => (u - start) / probability,
+            false => 0.5,
+        };
+        let value = (index as f64 + offset.clamp(0.0, 1.0)) / self.cdf.len() as f64;
+        (value.min(1.0 - f64::EPSILON), index, self.pdf(index))
+    }
+
+    /// Density of the values in a piece
+    pub fn pdf(&self, index: usize) -> f64 {
+        let start = index.checked_sub(1).map_or(0.0, |i| self.cdf[i]);
+        self.cdf
+            .get(index)
+            .map_or(0.0, |end| (end - start) * self.cdf.len() as f64)
+    }
+}
+
+/// Running mean and variance of some values, updated one value at a time (Welford's algorithm)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RunningStats {
+    pub count: usize,
+    pub mean: f64,
+    /// Sum of the squared differences to the mean
+    m2: f64,
+}
+
+impl RunningStats {
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Unbiased variance of the values, 0 until there are two of them
+    pub fn variance(&self) -> f64 {
+        match self.count {
+            0 | 1 => 0.0,
+            count => self.m2 / (count - 1) as f64,
+        }
+    }
+
+    /// Estimated error of the mean. It is infinite until there are two values, as nothing can be
+    /// told about the error from a single one
+    pub fn standard_error(&self) -> f64 {
+        match self.count {
+            0 | 1 => f64::INFINITY,
+            count => (self.variance() / count as f64).sqrt(),
+        }
+    }
+}
+
+/// Keep adding samples to a pixel while its estimated error is too large
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Samples added at a time, once the first `Sampler::samples` are taken
+    pub batch: usize,
+    pub max_samples: usize,
+    /// Largest accepted standard error of the mean luminance of the pixel
+    pub threshold: f64,
+}
+
+impl AdaptiveSampling {
+    pub fn new(batch: usize, max_samples: usize, threshold: f64) -> Self {
+        Self {
+            batch,
+            max_samples,
+            threshold,
+        }
+    }
+}
+
+/// Color of a pixel, with the number of samples it took
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelEstimate {
+    pub color: Color,
+    pub samples: usize,
+}
+
+/// How many rays go through each pixel, where, and how they are combined into its color
+///
+/// The samples of a pixel are spread over the whole support of the filter, which can be wider
+/// than the pixel, and their colors averaged using the weights of the filter. Each pixel has its
+/// own random sequence, derived from the seed, so the image only depends on the seed and not on
+/// how the render is split between threads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sampler {
+    pub pattern: SamplePattern,
+    /// Number of samples per pixel, or the first samples taken when sampling adaptively
+    pub samples: usize,
+    pub filter: PixelFilter,
+    pub seed: u64,
+    pub adaptive: Option<AdaptiveSampling>,
+}
+
+impl Default for Sampler {
+    /// A single ray through the center of each pixel
+    fn default() -> Self {
+        Self {
+            pattern: SamplePattern::Center,
+            samples: 1,
+            filter: PixelFilter::Box,
+            seed: 0,
+            adaptive: None,
+        }
+    }
+}
+
+impl Sampler {
+    pub fn new(pattern: SamplePattern, samples: usize, filter: PixelFilter) -> Self {
+        Self {
+            pattern,
+            samples,
+            filter,
+            ..Default::default()
+        }
+    }
+
+    /// Largest number of samples a pixel can take
+    pub fn max_samples(&self) -> usize {
+        let adaptive = self.adaptive.as_ref().map_or(0, |a| a.max_samples);
+        self.samples.max(adaptive).max(1)
+    }
+
+    /// Positions of the samples a pixel can take, in pixels from the top left corner of the image,
+    /// with their weights, in the order they are taken
+    pub fn pixel_samples(&self, x: usize, y: usize) -> Vec<(f64, f64, f64)> {
+        self.samples_and_rng(x, y).0
+    }
+
+    /// Samples of the pixel, and its random generator to keep using while tracing them
+    fn samples_and_rng(&self, x: usize, y: usize) -> (Vec<(f64, f64, f64)>, Rng) {
+        let mut rng = Rng::for_pixel(self.seed, x, y);
+        let radius = self.filter.radius();
+
+        let mut points = self.pattern.points(self.max_samples(), &mut rng);
+        // Adaptive sampling may stop after any sample, so the cells of grids are visited in a
+        // random order instead of row by row
+        let is_grid = matches!(
+            self.pattern,
+            SamplePattern::Stratified | SamplePattern::Jittered
+        );
+        if self.adaptive.is_some() && is_grid {
+            for i in (1..points.len()).rev() {
+                let j = (rng.next_f64() * (i + 1) as f64) as usize;
+                points.swap(i, j);
+            }
+        }
+
+        let samples = points
+            .into_iter()
+            .map(|(u, v)| {
+                let (dx, dy) = ((u * 2.0 - 1.0) * radius, (v * 2.0 - 1.0) * radius);
+                let weight = self.filter.weight(dx, dy);
+                (x as f64 + 0.5 + dx, y as f64 + 0.5 + dy, weight)
+            })
+            .collect();
+        (samples, rng)
+    }
+
+    /// Color of a pixel, from the color that `trace` gives to each of its samples. `trace` gets the
+    /// random generator of the pixel, for integrators that need random numbers
+    ///
+    /// When sampling adaptively, batches of samples are added until the error of the pixel is
+    /// small enough.
+    pub fn sample_pixel(
+        &self,
+        x: usize,
+        y: usize,
+        mut trace: impl FnMut(f64, f64, &mut Rng) -> Color,
+    ) -> PixelEstimate {
+        let (samples, mut rng) = self.samples_and_rng(x, y);
+        let mut take = self.samples.max(1);
+        let mut stats = RunningStats::default();
+        let (mut weighted, mut total_weight, mut sum) = (Color::black(), 0.0, Color::black());
+
+        for (i, &(px, py, weight)) in samples.iter().enumerate() {
+            if i == take {
+                match &self.adaptive {
+                    Some(adaptive) if stats.standard_error() > adaptive.threshold => {
+                        take += adaptive.batch.max(1)
+                    }
+                    _ => break,
+                }
+            }
+
+            let color = trace(px, py, &mut rng);
+            stats.add(luminance(&color));
+            weighted = weighted + color * weight;
+            total_weight += weight;
+            sum = sum + color;
+        }
+
+        // With negative lobes, few samples can add up to no weight, then a plain average is used
+        let color = match total_weight > 0.0 {
+            true => weighted * (1.0 / total_weight),
+            false => sum * (1.0 / stats.count as f64),
+        };
+        PixelEstimate {
+            color,
+            samples: stats.count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_sampling {
+    use super::*;
+    use crate::approx::approx;
+
+    fn patterns() -> [SamplePattern; 5] {
+        [
+            SamplePattern::Center,
+            SamplePattern::Stratified,
+            SamplePattern::Jittered,
+            SamplePattern::Halton,
+            SamplePattern::Sobol,
+        ]
+    }
+
+    #[test]
+    fn points_in_unit_square() {
+        for pattern in &patterns()[1..] {
+            let points = pattern.points(16, &mut Rng::new(3));
+            assert_eq!(points.len(), 16, "{pattern:?}");
+            assert!(
+                points
+                    .iter()
+                    .all(|(u, v)| (0.0..1.0).contains(u) && (0.0..1.0).contains(v)),
+                "{pattern:?}"
+            );
+        }
+
+        assert_eq!(
+            SamplePattern::Jittered.points(10, &mut Rng::new(0)).len(),
+            9
+        );
+        assert_eq!(
+            SamplePattern::Center.points(10, &mut Rng::new(0)),
+            vec![(0.5, 0.5)]
+        );
+    }
+
+    #[test]
+    fn stratified_points() {
+        let points = SamplePattern::Stratified.points(4, &mut Rng::new(0));
+        assert_eq!(
+            points,
+            vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]
+        );
+    }
+
+    #[test]
+    fn one_point_per_stratum() {
+        // Jittered, and the first 2^k Sobol and 2^i*3^j Halton points, fall one per cell
+        for (pattern, cells) in [
+            (SamplePattern::Jittered, (4, 4)),
+            (SamplePattern::Sobol, (4, 4)),
+            (SamplePattern::Halton, (4, 3)),
+        ] {
+            let points = pattern.points(cells.0 * cells.1, &mut Rng::new(11));
+            let mut seen = vec![false; cells.0 * cells.1];
+            for (u, v) in points {
+                let (i, j) = ((u * cells.0 as f64) as usize, (v * cells.1 as f64) as usize);
+                seen[j * cells.0 + i] = true;
+            }
+            assert!(seen.iter().all(|&cell| cell), "{pattern:?}");
+        }
+    }
+
+    #[test]
+    fn low_discrepancy_sequences() {
+        let unscrambled = |base| DigitScramble {
+            base,
+            offsets: vec![0; 40],
+        };
+        assert_eq!(unscrambled(2).radical_inverse(1), 0.5);
+        assert_eq!(unscrambled(2).radical_inverse(6), 0.375);
+        assert!((unscrambled(3).radical_inverse(5) - 7.0 / 9.0).abs() < 1e-12);
+
+        let to_unit = |bits: u32| bits as f64 / (1u64 << 32) as f64;
+        let ys: Vec<f64> = (0..4).map(|i| to_unit(sobol_y(i))).collect();
+        assert_eq!(ys, vec![0.0, 0.5, 0.75, 0.25]);
+    }
+
+    #[test]
+    fn reproducible_samples() {
+        let mut sampler = Sampler::new(SamplePattern::Jittered, 9, PixelFilter::mitchell());
+        sampler.seed = 5;
+        assert_eq!(sampler.pixel_samples(3, 7), sampler.pixel_samples(3, 7));
+        assert_ne!(sampler.pixel_samples(3, 7), sampler.pixel_samples(7, 3));
+
+        let other = Sampler {
+            seed: 6,
+            ..sampler.clone()
+        };
+        assert_ne!(sampler.pixel_samples(3, 7), other.pixel_samples(3, 7));
+    }
+
+    #[test]
+    fn samples_cover_filter() {
+        for filter in [PixelFilter::Box, PixelFilter::mitchell()] {
+            let sampler = Sampler::new(SamplePattern::Halton, 64, filter);
+            let radius = filter.radius();
+            for (px, py, _) in sampler.pixel_samples(10, 20) {
+                assert!((px - 10.5).abs() <= radius && (py - 20.5).abs() <= radius);
+            }
+        }
+
+        let center = Sampler::default().pixel_samples(4, 2);
+        assert_eq!(center, vec![(4.5, 2.5, 1.0)]);
+    }
+
+    #[test]
+    fn constant_color_is_kept() {
+        let color = Color::new(0.2, 0.5, 0.9);
+        for filter in [
+            PixelFilter::Box,
+            PixelFilter::Tent,
+            PixelFilter::gaussian(),
+            PixelFilter::mitchell(),
+        ] {
+            for pattern in patterns() {
+                let sampler = Sampler::new(pattern, 16, filter);
+                assert_eq!(sampler.sample_pixel(1, 1, |_, _, _| color).color, color);
+            }
+        }
+    }
+
+    #[test]
+    fn antialiased_edge() {
+        // Half of the pixel is white, so its samples average to gray
+        let sampler = Sampler::new(SamplePattern::Stratified, 16, PixelFilter::Box);
+        let pixel = sampler.sample_pixel(0, 0, |x, _, _| match x < 0.5 {
+            true => Color::white(),
+            false => Color::black(),
+        });
+        assert_eq!(pixel.color, Color::new(0.5, 0.5, 0.5));
+        assert_eq!(pixel.samples, 16);
+    }
+
+    #[test]
+    fn running_stats() {
+        let mut stats = RunningStats::default();
+        assert_eq!(stats.standard_error(), f64::INFINITY);
+
+        for value in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.add(value);
+        }
+        assert_eq!(stats.count, 8);
+        assert!((stats.mean - 5.0).abs() < 1e-12);
+        assert!((stats.variance() - 32.0 / 7.0).abs() < 1e-12);
+        assert!((stats.standard_error() - (4.0f64 / 7.0).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn adaptive_sampling_stops_on_flat_pixels() {
+        let sampler = Sampler {
+            samples: 4,
+            adaptive: Some(AdaptiveSampling::new(4, 64, 0.01)),
+            ..Sampler::new(SamplePattern::Sobol, 4, PixelFilter::Box)
+        };
+
+        // A flat pixel has no variance, so the first samples are enough
+        let flat = sampler.sample_pixel(0, 0, |_, _, _| Color::new(0.3, 0.3, 0.3));
+        assert_eq!(flat.samples, 4);
+        assert_eq!(flat.color, Color::new(0.3, 0.3, 0.3));
+
+        // A pixel on an edge keeps sampling until the error is small, or the maximum is reached
+        let edge = |x: f64, _, _: &mut Rng| match x < 0.3 {
+            true => Color::white(),
+            false => Color::black(),
+        };
+        let pixel = sampler.sample_pixel(0, 0, edge);
+        assert_eq!(pixel.samples, 64);
+        assert!((pixel.color.red - 0.3).abs() < 0.05);
+
+        let loose = Sampler {
+            adaptive: Some(AdaptiveSampling::new(4, 64, 0.1)),
+            ..sampler.clone()
+        };
+        let pixel = loose.sample_pixel(0, 0, edge);
+        assert!(pixel.samples > 4 && pixel.samples < 64 && pixel.samples.is_multiple_of(4));
+    }
+
+    #[test]
+    fn adaptive_grid_order() {
+        let sampler = Sampler {
+            adaptive: Some(AdaptiveSampling::new(4, 16, 0.01)),
+            ..Sampler::new(SamplePattern::Stratified, 4, PixelFilter::Box)
+        };
+        let samples = sampler.pixel_samples(2, 2);
+        assert_eq!(samples.len(), 16);
+
+        // The first samples are not all on the top row of the grid
+        assert!(samples[..4].iter().any(|&(_, y, _)| y > 2.25));
+    }
+
+    #[test]
+    fn basis_is_orthonormal() {
+        let normals = [
+            Vector::new(0.0, 0.0, 1.0),
+            Vector::new(0.0, 0.0, -1.0),
+            Vector::new(1.0, 2.0, -3.0).normalize(),
+            Vector::new(-0.3, 0.1, 0.2).normalize(),
+        ];
+        for normal in normals {
+            let (t, b) = orthonormal_basis(&normal);
+            assert!(approx(t.magnitude(), 1.0) && approx(b.magnitude(), 1.0));
+            assert!(approx(t.dot(&b), 0.0));
+            assert!(approx(t.dot(&normal), 0.0) && approx(b.dot(&normal), 0.0));
+        }
+    }
+
+    #[test]
+    fn cosine_weighted_directions() {
+        let normal = Vector::new(1.0, 1.0, 0.0).normalize();
+        let mut rng = Rng::new(1);
+        let cosines: Vec<f64> = (0..20000)
+            .map(|_| cosine_hemisphere(&normal, &mut rng).dot(&normal))
+            .collect();
+
+        // With a density of cos / PI, the mean of the cosine is 2/3
+        assert!(cosines.iter().all(|&cos| cos >= 0.0));
+        let mean = cosines.iter().sum::<f64>() / cosines.len() as f64;
+        assert!((mean - 2.0 / 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn cone_directions() {
+        let axis = Vector::new(0.0, -1.0, 1.0).normalize();
+        let mut rng = Rng::new(2);
+        let cosines: Vec<f64> = (0..20000)
+            .map(|_| uniform_cone(&axis, 0.8, &mut rng).dot(&axis))
+            .collect();
+
+        // Uniform in solid angle means uniform in the cosine
+        assert!(cosines.iter().all(|&cos| cos >= 0.8 - 1e-9));
+        let mean = cosines.iter().sum::<f64>() / cosines.len() as f64;
+        assert!((mean - 0.9).abs() < 0.002);
+    }
+
+    #[test]
+    fn power_heuristic_weights() {
+        assert!(approx(power_heuristic(1.0, 1.0), 0.5));
+        assert!(approx(power_heuristic(3.0, 1.0), 0.9));
+        assert!(approx(
+            power_heuristic(3.0, 1.0) + power_heuristic(1.0, 3.0),
+            1.0
+        ));
+        assert_eq!(power_heuristic(0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn distribution_follows_the_weights() {
+        let distribution = Distribution::new(&[1.0, 0.0, 3.0]);
+        assert!(approx(distribution.pdf(0), 0.75));
+        assert_eq!(distribution.pdf(1), 0.0);
+        assert!(approx(distribution.pdf(2), 2.25));
+
+        let (value, index, pdf) = distribution.sample(0.125);
+        assert_eq!(index, 0);
+        assert!(approx(value, 1.0 / 6.0));
+        assert!(approx(pdf, 0.75));
+        let (value, index, _) = distribution.sample(0.625);
+        assert_eq!(index, 2);
+        assert!(approx(value, 2.5 / 3.0));
+        assert_eq!(distribution.sample(1.0).1, 2);
+
+        // Without weights every piece is equally likely
+        let uniform = Distribution::new(&[0.0, 0.0]);
+        assert!(approx(uniform.pdf(1), 1.0));
+        assert!(approx(uniform.sample(0.75).0, 0.75));
+    }
+}
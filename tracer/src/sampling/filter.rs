@@ -0,0 +1,119 @@
+/// Reconstruction filter, which weights the samples of a pixel by their distance to its center
+///
+/// Every filter is separable: the weight of a sample is the product of the weights of its
+/// horizontal and vertical distances, given in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PixelFilter {
+    /// Every sample inside the pixel has the same weight
+    #[default]
+    Box,
+    /// Weights fall linearly to 0 at one pixel from the center
+    Tent,
+    /// Gaussian curve with the given standard deviation, cut at three of them
+    Gaussian { sigma: f64 },
+    /// Mitchell-Netravali cubic, two pixels wide. Its negative lobes sharpen the image
+    Mitchell { b: f64, c: f64 },
+}
+
+impl PixelFilter {
+    /// Gaussian filter with a standard deviation of half a pixel
+    pub fn gaussian() -> Self {
+        PixelFilter::Gaussian { sigma: 0.5 }
+    }
+
+    /// Mitchell filter with the parameters recommended by its authors
+    pub fn mitchell() -> Self {
+        PixelFilter::Mitchell {
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        }
+    }
+
+    /// Distance from the center of the pixel at which the weight drops to 0
+    pub fn radius(&self) -> f64 {
+        match self {
+            PixelFilter::Box => 0.5,
+            PixelFilter::Tent => 1.0,
+            PixelFilter::Gaussian { sigma } => 3.0 * sigma,
+            PixelFilter::Mitchell { .. } => 2.0,
+        }
+    }
+
+    /// Weight of a sample at `(dx, dy)` pixels from the center of the pixel
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(&self, d: f64) -> f64 {
+        let d = d.abs();
+        if d > self.radius() {
+            return 0.0;
+        }
+
+        match *self {
+            PixelFilter::Box => 1.0,
+            PixelFilter::Tent => 1.0 - d,
+            PixelFilter::Gaussian { sigma } => (-d * d / (2.0 * sigma * sigma)).exp(),
+            PixelFilter::Mitchell { b, c } => {
+                let (d2, d3) = (d * d, d * d * d);
+                let weight = match d < 1.0 {
+                    true => {
+                        (12.0 - 9.0 * b - 6.0 * c) * d3
+                            + (-18.0 + 12.0 * b + 6.0 * c) * d2
+                            + (6.0 - 2.0 * b)
+                    }
+                    false => {
+                        (-b - 6.0 * c) * d3
+                            + (6.0 * b + 30.0 * c) * d2
+                            + (-12.0 * b - 48.0 * c) * d
+                            + (8.0 * b + 24.0 * c)
+                    }
+                };
+                weight / 6.0
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_filter {
+    use super::*;
+    use crate::approx::approx;
+
+    #[test]
+    fn weights_at_center_and_edges() {
+        let filters = [
+            PixelFilter::Box,
+            PixelFilter::Tent,
+            PixelFilter::gaussian(),
+            PixelFilter::mitchell(),
+        ];
+        for filter in filters {
+            let radius = filter.radius();
+            assert!(filter.weight(0.0, 0.0) > 0.0, "{filter:?}");
+            assert_eq!(filter.weight(radius + 0.01, 0.0), 0.0, "{filter:?}");
+            assert_eq!(filter.weight(0.0, -radius - 0.01), 0.0, "{filter:?}");
+            assert!(
+                approx(filter.weight(0.3, -0.2), filter.weight(-0.3, 0.2)),
+                "{filter:?}"
+            );
+        }
+
+        assert_eq!(PixelFilter::Box.weight(0.4, -0.4), 1.0);
+        assert!(approx(PixelFilter::Tent.weight(0.5, 0.5), 0.25));
+        assert!(approx(
+            PixelFilter::gaussian().weight(0.5, 0.0),
+            (-0.5f64).exp()
+        ));
+    }
+
+    #[test]
+    fn mitchell_values() {
+        let filter = PixelFilter::mitchell();
+        assert!(approx(filter.weight(0.0, 0.0), (8.0f64 / 9.0).powi(2)));
+        // Continuous at 1 and 2 pixels, with a negative lobe between them
+        assert!(approx(filter.weight_1d(1.0 - 1e-9), filter.weight_1d(1.0)));
+        assert!(approx(filter.weight_1d(2.0), 0.0));
+        assert!(filter.weight_1d(1.5) < 0.0);
+    }
+}
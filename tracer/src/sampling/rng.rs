@@ -0,0 +1,96 @@
+use crate::point::coord::Coord;
+
+/// Small, fast pseudo random number generator (SplitMix64)
+///
+/// It is not meant for anything but sampling. The same seed always gives the same numbers, so
+/// renders are reproducible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Generator for one pixel of a render. Each pixel has its own sequence, so the result does
+    /// not depend on the order in which pixels are rendered
+    pub fn for_pixel(seed: u64, x: usize, y: usize) -> Self {
+        let mut rng = Self::new(seed);
+        let state = rng.next_u64() ^ (x as u64).wrapping_mul(0x9e3779b97f4a7c15);
+        let mut rng = Self::new(state);
+        Self::new(rng.next_u64() ^ (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f))
+    }
+
+    /// Generator for the light samples of a point, so the same point always gets the same ones
+    pub fn for_point(point: &Coord) -> Self {
+        Self::new(
+            point.x.to_bits()
+                ^ point.y.to_bits().rotate_left(21)
+                ^ point.z.to_bits().rotate_left(42),
+        )
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform number in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        // The 53 upper bits fill the mantissa exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod test_rng {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+        let first: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..5).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(first, (0..5).map(|_| c.next_u64()).collect::<Vec<_>>());
+
+        // Known first value of SplitMix64 with seed 0
+        assert_eq!(Rng::new(0).next_u64(), 0xe220a8397b1dcdaf);
+    }
+
+    #[test]
+    fn generator_for_a_point() {
+        let point = Coord::new(0.5, -2.0, 3.0);
+        let first = Rng::for_point(&point).next_u64();
+        assert_eq!(
+            Rng::for_point(&Coord::new(0.5, -2.0, 3.0)).next_u64(),
+            first
+        );
+        let moved = Coord::new(0.5, -2.0, 3.0 + 1e-9);
+        assert_ne!(Rng::for_point(&moved).next_u64(), first);
+    }
+
+    #[test]
+    fn uniform_numbers() {
+        let mut rng = Rng::new(7);
+        let values: Vec<f64> = (0..10000).map(|_| rng.next_f64()).collect();
+        assert!(values.iter().all(|v| (0.0..1.0).contains(v)));
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!((mean - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn pixels_have_different_sequences() {
+        let first = |x, y| Rng::for_pixel(1, x, y).next_u64();
+        assert_eq!(first(3, 4), first(3, 4));
+        assert_ne!(first(3, 4), first(4, 3));
+        assert_ne!(first(0, 0), first(0, 1));
+        assert_ne!(first(0, 0), Rng::for_pixel(2, 0, 0).next_u64());
+    }
+}
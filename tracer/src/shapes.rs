@@ -1,18 +1,45 @@
+use cone::Cone;
+use csg::Csg;
+use cube::Cube;
+use cylinder::Cylinder;
 use enum_dispatch::enum_dispatch;
+use group::Group;
+use plane::Plane;
+use smooth_triangle::SmoothTriangle;
 use sphere::Sphere;
+use triangle::Triangle;
 
 use crate::{
+    bounding_box::BoundingBox,
     intersection::{intersections::IntersectionTracker, single_intersection::SingleIntersection},
+    material::Material,
     point::{coord::Coord, vector::Vector},
     ray::Ray,
+    transformations::TransformationMatrix,
 };
 
+pub mod cone;
+pub mod csg;
+pub mod cube;
+pub mod cylinder;
+pub mod group;
+pub mod plane;
+pub mod smooth_triangle;
 pub mod sphere;
+pub mod triangle;
 
 #[derive(Debug, PartialEq)]
 #[enum_dispatch(Hittable)]
 pub enum Shapes {
     Sphere(Sphere),
+    Plane(Plane),
+    Cube(Cube),
+    Cylinder(Cylinder),
+    Cone(Cone),
+    Triangle(Triangle),
+    SmoothTriangle(SmoothTriangle),
+    Group(Group),
+    Csg(Csg),
 }
 
 #[enum_dispatch]
@@ -23,14 +50,97 @@ pub trait Hittable {
     /// Return the vector normal to some coord. It is assumed that the coorindate given is a point
     /// in the surface of the shape
     fn normal(&self, at: &Coord) -> Vector;
+
+    /// Return the material that the surface of the shape is made of
+    fn material(&self) -> &Material;
+
+    fn material_mut(&mut self) -> &mut Material;
+
+    /// Box that contains the shape after its transformation, that is, in the space of the group
+    /// that contains it (or of the world)
+    fn bounds(&self) -> BoundingBox;
+
+    /// Same as `hit_times`, but each time also comes with the barycentric coordinates (u, v) of
+    /// the hit, for the shapes that have them
+    fn hit_times_with_uv(&self, ray: &Ray) -> Vec<(f64, Option<(f64, f64)>)> {
+        self.hit_times(ray)
+            .into_iter()
+            .map(|time| (time, None))
+            .collect()
+    }
+
+    /// Same as `normal`, but using the barycentric coordinates of the hit when they are known.
+    /// Shapes that interpolate their normals across the surface need them
+    fn normal_with_uv(&self, at: &Coord, _uv: Option<(f64, f64)>) -> Vector {
+        self.normal(at)
+    }
+
+    /// Coordinates in the texture of the material for a hit with the given barycentric
+    /// coordinates. Only shapes with texture coordinates (triangles from meshes) have them
+    fn texture_coords(&self, _uv: Option<(f64, f64)>) -> Option<(f64, f64)> {
+        None
+    }
 }
 
 impl Shapes {
-    fn get_intersections(&self, ray: &Ray) -> IntersectionTracker {
+    /// Whether this is the shape, or one of the shapes inside of it. Shapes are compared by
+    /// address, as two distinct shapes may be equal
+    pub fn includes(&self, shape: &Shapes) -> bool {
+        match self {
+            Shapes::Group(group) => group.children().iter().any(|child| child.includes(shape)),
+            Shapes::Csg(csg) => csg.left.includes(shape) || csg.right.includes(shape),
+            _ => std::ptr::eq(self, shape),
+        }
+    }
+
+    /// Build the bounding volume hierarchies of every group in this shape
+    pub fn build_bvh(&mut self) {
+        match self {
+            Shapes::Group(group) => group.build_bvh(),
+            Shapes::Csg(csg) => {
+                csg.left.build_bvh();
+                csg.right.build_bvh();
+            }
+            _ => {}
+        }
+    }
+
+    /// Normal of a shape made of others (groups and CSG), which have no surface of their own. It
+    /// is the normal of the part whose bounds are nearest to the point, which is the part that was
+    /// hit unless the bounds of several parts hold the point. The intersections refer to the part
+    /// that was hit, so shading always gets its exact normal
+    fn composite_normal<'a>(
+        transformation: &TransformationMatrix,
+        parts: impl IntoIterator<Item = &'a Shapes>,
+        at: &Coord,
+    ) -> Vector {
+        let inverse = transformation
+            .inverse()
+            .expect("Could not get inverse of transformation");
+        let at = inverse * at;
+        // A shape without parts is never hit, so any normal does
+        let normal = parts
+            .into_iter()
+            .map(|part| (part.bounds().distance(&at), part))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map_or(Vector::new(0.0, 1.0, 0.0), |(_, part)| part.normal(&at));
+        (inverse.transpose() * normal).normalize()
+    }
+
+    pub fn get_intersections(&self, ray: &Ray) -> IntersectionTracker<'_> {
+        match self {
+            Shapes::Group(group) => return group.get_intersections(ray),
+            Shapes::Csg(csg) => return csg.get_intersections(ray),
+            _ => {}
+        }
+
         IntersectionTracker::new(
-            self.hit_times(ray)
+            self.hit_times_with_uv(ray)
                 .into_iter()
-                .map(|time| SingleIntersection::new(time, self))
+                .map(|(time, uv)| match uv {
+                    Some((u, v)) => SingleIntersection::with_uv(time, self, u, v),
+                    None => SingleIntersection::new(time, self),
+                })
                 .collect(),
         )
     }
@@ -63,4 +173,14 @@ mod test_shapes {
         assert_eq!(hits[1].time, 5.0);
         assert_eq!(hits[0].shape, &shape);
     }
+
+    #[test]
+    fn plane_hits() {
+        let shape = Shapes::Plane(plane::Plane::default());
+        let ray = crate::ray::Ray::from(((0, 1, 0), (0, -1, 0)));
+        let hits = shape.get_intersections(&ray);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].time, 1.0);
+        assert_eq!(hits[0].shape, &shape);
+    }
 }
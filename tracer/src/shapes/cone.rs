@@ -0,0 +1,262 @@
+use std::ops::Mul;
+
+use crate::{
+    approx::EPSILON,
+    bounding_box::BoundingBox,
+    material::Material,
+    point::{coord::Coord, vector::Vector},
+    ray::Ray,
+    transformations::TransformationMatrix,
+};
+
+use super::Hittable;
+
+/// Double napped cone around the y axis, before transforming it
+///
+/// The two cones meet at the origin, and the radius of the cone at some height y is |y|. Just
+/// like with cylinders, it can be truncated by the `minimum` and `maximum` values, and `closed`
+/// with caps.
+#[derive(Debug, PartialEq)]
+pub struct Cone {
+    pub transformation: TransformationMatrix,
+    pub material: Material,
+    pub minimum: f64,
+    pub maximum: f64,
+    pub closed: bool,
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self {
+            transformation: TransformationMatrix::default(),
+            material: Material::default(),
+            minimum: f64::NEG_INFINITY,
+            maximum: f64::INFINITY,
+            closed: false,
+        }
+    }
+}
+
+impl Cone {
+    /// Times when the ray (in object space) hits the caps of the cone
+    fn cap_times(&self, ray: &Ray) -> Vec<f64> {
+        // Caps are only worth checking if they exist and the ray is not parallel to them
+        if !self.closed || ray.dir.y.abs() < EPSILON {
+            return vec![];
+        }
+
+        [self.minimum, self.maximum]
+            .into_iter()
+            .map(|cap| (cap, (cap - ray.origin.y) / ray.dir.y))
+            .filter(|&(cap, time)| {
+                let at = ray.position_at(time);
+                at.x * at.x + at.z * at.z <= cap * cap
+            })
+            .map(|(_, time)| time)
+            .collect()
+    }
+}
+
+impl Hittable for Cone {
+    fn hit_times(&self, ray: &Ray) -> Vec<f64> {
+        let ray = self
+            .transformation
+            .inverse()
+            .map(|inverse| inverse.mul(ray))
+            .expect("Could not get inverse of transformation matrix");
+
+        let mut times = self.cap_times(&ray);
+
+        let (o, d) = (&ray.origin, &ray.dir);
+        let a = d.x * d.x - d.y * d.y + d.z * d.z;
+        let b = 2.0 * o.x * d.x - 2.0 * o.y * d.y + 2.0 * o.z * d.z;
+        let c = o.x * o.x - o.y * o.y + o.z * o.z;
+
+        let body_times = match (a.abs() < EPSILON, b.abs() < EPSILON) {
+            // The ray misses the body of the cone
+            (true, true) => vec![],
+            // The ray is parallel to one of the halves, so it only hits the other one
+            (true, false) => vec![-c / (2.0 * b)],
+            _ => {
+                let discriminant = (b * b) - (4.0 * a * c);
+                if discriminant < 0.0 {
+                    return vec![];
+                }
+                let sqrt = discriminant.sqrt();
+                vec![(-b - sqrt) / (2.0 * a), (-b + sqrt) / (2.0 * a)]
+            }
+        };
+
+        for time in body_times {
+            let y = o.y + time * d.y;
+            if self.minimum < y && y < self.maximum {
+                times.push(time);
+            }
+        }
+
+        times.sort_by(f64::total_cmp);
+        times
+    }
+
+    fn normal(&self, at: &Coord) -> Vector {
+        let inverse = self
+            .transformation
+            .inverse()
+            .expect("Could not get inverse of transformation");
+        let at = inverse * at;
+
+        let distance = at.x * at.x + at.z * at.z;
+        let object_normal = if distance < at.y * at.y && at.y >= self.maximum - EPSILON {
+            Vector::new(0.0, 1.0, 0.0)
+        } else if distance < at.y * at.y && at.y <= self.minimum + EPSILON {
+            Vector::new(0.0, -1.0, 0.0)
+        } else {
+            let y = distance.sqrt();
+            let y = if at.y > 0.0 { -y } else { y };
+            Vector::new(at.x, y, at.z)
+        };
+
+        (inverse.transpose() * object_normal).normalize()
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        // The radius of the cone is the largest at one of its ends
+        let radius = self.minimum.abs().max(self.maximum.abs());
+        BoundingBox::new(
+            Coord::new(-radius, self.minimum, -radius),
+            Coord::new(radius, self.maximum, radius),
+        )
+        .transform(&self.transformation.matrix)
+    }
+}
+
+#[cfg(test)]
+mod test_hittable_cone {
+    use super::*;
+    use crate::approx::approx;
+
+    #[test]
+    fn ray_hits() {
+        let cases = [
+            // Perpendicular to the axis, grazing the tip
+            ((0.0, 0.0, -5.0), (0.0, 0.0, 1.0), 5.0, 5.0),
+            ((0.0, 0.0, -5.0), (1.0, 1.0, 1.0), 8.66025, 8.66025),
+            ((1.0, 1.0, -5.0), (-0.5, -1.0, 1.0), 4.55006, 49.44994),
+        ];
+
+        for (origin, dir, t0, t1) in cases {
+            let ray = Ray::new(Coord::from(origin), Vector::from(dir).normalize());
+            let hits = Cone::default().hit_times(&ray);
+            assert_eq!(hits.len(), 2);
+            assert!(approx(hits[0], t0));
+            assert!(approx(hits[1], t1));
+        }
+    }
+
+    #[test]
+    fn ray_parallel_to_one_half() {
+        let ray = Ray::new(Coord::from((0, 0, -1)), Vector::from((0, 1, 1)).normalize());
+        let hits = Cone::default().hit_times(&ray);
+        assert_eq!(hits.len(), 1);
+        assert!(approx(hits[0], 0.35355));
+    }
+
+    #[test]
+    fn ray_through_apex() {
+        // Along the axis, the ray only touches the apex
+        let ray = Ray::new(Coord::from((0, 5, 0)), Vector::from((0, -1, 0)));
+        assert_eq!(Cone::default().hit_times(&ray), vec![5.0, 5.0]);
+
+        // Along the surface, the ray never crosses it, so only the caps are hit
+        let ray = Ray::new(
+            Coord::from((0, -1, -1)),
+            Vector::from((0, 1, 1)).normalize(),
+        );
+        assert!(Cone::default().hit_times(&ray).is_empty());
+        let cone = Cone {
+            minimum: -2.0,
+            maximum: 2.0,
+            closed: true,
+            ..Default::default()
+        };
+        let hits = cone.hit_times(&ray);
+        assert_eq!(hits.len(), 2);
+        assert!(approx(hits[0], -std::f64::consts::SQRT_2));
+        assert!(approx(hits[1], 4.24264));
+    }
+
+    #[test]
+    fn cap_hits() {
+        let cone = Cone {
+            minimum: -0.5,
+            maximum: 0.5,
+            closed: true,
+            ..Default::default()
+        };
+
+        let cases = [
+            ((0.0, 0.0, -5.0), (0.0, 1.0, 0.0), 0),
+            ((0.0, 0.0, -0.25), (0.0, 1.0, 1.0), 2),
+            ((0.0, 0.0, -0.25), (0.0, 1.0, 0.0), 4),
+        ];
+
+        for (origin, dir, count) in cases {
+            let ray = Ray::new(Coord::from(origin), Vector::from(dir).normalize());
+            assert_eq!(cone.hit_times(&ray).len(), count);
+        }
+    }
+
+    #[test]
+    fn body_normals() {
+        let h = 2.0f64.sqrt();
+        let cases = [
+            ((1.0, 1.0, 1.0), (1.0, -h, 1.0)),
+            ((-1.0, -1.0, 0.0), (-1.0, 1.0, 0.0)),
+        ];
+
+        for (at, expected) in cases {
+            let normal = Cone::default().normal(&Coord::from(at));
+            assert_eq!(normal, Vector::from(expected).normalize());
+        }
+    }
+
+    #[test]
+    fn cap_normals() {
+        let cone = Cone {
+            minimum: -1.0,
+            maximum: 2.0,
+            closed: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            cone.normal(&Coord::from((0.5, 2.0, 0.0))),
+            Vector::from((0, 1, 0))
+        );
+        assert_eq!(
+            cone.normal(&Coord::from((0.0, -1.0, 0.5))),
+            Vector::from((0, -1, 0))
+        );
+    }
+
+    #[test]
+    fn bounding_box() {
+        let cone = Cone {
+            minimum: -5.0,
+            maximum: 3.0,
+            ..Default::default()
+        };
+        let bounds = cone.bounds();
+        assert_eq!(bounds.min, Coord::from((-5, -5, -5)));
+        assert_eq!(bounds.max, Coord::from((5, 3, 5)));
+
+        assert!(!Cone::default().bounds().is_finite());
+    }
+}
@@ -0,0 +1,321 @@
+use std::ops::Mul;
+
+use crate::{
+    bounding_box::BoundingBox,
+    intersection::{intersections::IntersectionTracker, single_intersection::SingleIntersection},
+    material::Material,
+    point::{coord::Coord, vector::Vector},
+    ray::Ray,
+    transformations::TransformationMatrix,
+};
+
+use super::{Hittable, Shapes};
+
+/// How the two shapes of a `Csg` are combined
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsgOperation {
+    /// Everything inside of either shape
+    Union,
+    /// Only what is inside of both shapes
+    Intersection,
+    /// What is inside of the left shape, but not inside of the right one
+    Difference,
+}
+
+impl CsgOperation {
+    /// Whether a hit is part of the surface of the combined shape. `left_hit` tells if the hit was
+    /// on the left shape, and `in_left` and `in_right` if the ray was inside of each shape
+    pub fn allows(self, left_hit: bool, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => (left_hit && !in_right) || (!left_hit && !in_left),
+            CsgOperation::Intersection => (left_hit && in_right) || (!left_hit && in_left),
+            CsgOperation::Difference => (left_hit && !in_right) || (!left_hit && in_left),
+        }
+    }
+}
+
+/// Constructive solid geometry, two shapes combined into one
+///
+/// The intersections of both shapes are merged and only the ones on the surface of the combined
+/// shape are kept. Just like with groups, the intersections refer to the shape that was hit, so
+/// the material and normal come from it.
+#[derive(Debug, PartialEq)]
+pub struct Csg {
+    pub transformation: TransformationMatrix,
+    /// Like groups, this has no surface of its own, so the material is never used for shading
+    pub material: Material,
+    pub operation: CsgOperation,
+    pub left: Box<Shapes>,
+    pub right: Box<Shapes>,
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: Shapes, right: Shapes) -> Self {
+        Self {
+            transformation: TransformationMatrix::default(),
+            material: Material::default(),
+            operation,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    /// Intersections of the ray with the surface of the combined shape
+    pub fn get_intersections(&self, ray: &Ray) -> IntersectionTracker<'_> {
+        let ray = self
+            .transformation
+            .inverse()
+            .map(|inverse| inverse.mul(ray))
+            .expect("Could not get inverse of transformation matrix");
+
+        let mut hits = self.left.get_intersections(&ray);
+        hits.merge(self.right.get_intersections(&ray));
+
+        IntersectionTracker::new(self.filter_intersections(hits))
+    }
+
+    /// Keep only the intersections allowed by the operation. Every intersection of the ray is
+    /// needed, sorted, to know whether it is inside of each shape at every hit
+    fn filter_intersections<'a>(
+        &'a self,
+        hits: IntersectionTracker<'a>,
+    ) -> Vec<SingleIntersection<'a>> {
+        let mut in_left = false;
+        let mut in_right = false;
+
+        hits.into_iter()
+            .filter_map(|mut inter| {
+                let left_hit = self.left.includes(inter.shape);
+                let allowed = self.operation.allows(left_hit, in_left, in_right);
+
+                // Every hit means entering or leaving the shape that was hit
+                match left_hit {
+                    true => in_left = !in_left,
+                    false => in_right = !in_right,
+                }
+
+                allowed.then(|| {
+                    inter.parents.insert(0, &self.transformation);
+                    inter
+                })
+            })
+            .collect()
+    }
+}
+
+impl Hittable for Csg {
+    fn hit_times(&self, ray: &Ray) -> Vec<f64> {
+        self.get_intersections(ray)
+            .iter()
+            .map(|inter| inter.time)
+            .collect()
+    }
+
+    /// CSG shapes have no surface of their own, this is the normal of the operand nearest to the
+    /// point
+    fn normal(&self, at: &Coord) -> Vector {
+        Shapes::composite_normal(&self.transformation, [&*self.left, &*self.right], at)
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.left
+            .bounds()
+            .merge(&self.right.bounds())
+            .transform(&self.transformation.matrix)
+    }
+}
+
+#[cfg(test)]
+mod test_hittable_csg {
+    use super::*;
+    use crate::{
+        shapes::{cube::Cube, sphere::Sphere},
+        transformations::Axis,
+    };
+    use std::f64::consts;
+
+    #[test]
+    fn operation_rules() {
+        use CsgOperation::*;
+        // (operation, left hit, inside left, inside right, allowed)
+        let cases = [
+            (Union, true, true, true, false),
+            (Union, true, true, false, true),
+            (Union, true, false, true, false),
+            (Union, true, false, false, true),
+            (Union, false, true, true, false),
+            (Union, false, true, false, false),
+            (Union, false, false, true, true),
+            (Union, false, false, false, true),
+            (Intersection, true, true, true, true),
+            (Intersection, true, true, false, false),
+            (Intersection, true, false, true, true),
+            (Intersection, true, false, false, false),
+            (Intersection, false, true, true, true),
+            (Intersection, false, true, false, true),
+            (Intersection, false, false, true, false),
+            (Intersection, false, false, false, false),
+            (Difference, true, true, true, false),
+            (Difference, true, true, false, true),
+            (Difference, true, false, true, false),
+            (Difference, true, false, false, true),
+            (Difference, false, true, true, true),
+            (Difference, false, true, false, true),
+            (Difference, false, false, true, false),
+            (Difference, false, false, false, false),
+        ];
+
+        for (operation, left_hit, in_left, in_right, expected) in cases {
+            assert_eq!(
+                operation.allows(left_hit, in_left, in_right),
+                expected,
+                "{operation:?} {left_hit} {in_left} {in_right}"
+            );
+        }
+    }
+
+    #[test]
+    fn filter_intersections() {
+        // Which of the four hits (left, right, left, right) are kept by each operation
+        let cases = [
+            (CsgOperation::Union, vec![0, 3]),
+            (CsgOperation::Intersection, vec![1, 2]),
+            (CsgOperation::Difference, vec![0, 1]),
+        ];
+
+        for (operation, expected) in cases {
+            let csg = Csg::new(
+                operation,
+                Shapes::Sphere(Sphere::default()),
+                Shapes::Cube(Cube::default()),
+            );
+            let hits = IntersectionTracker::new(vec![
+                SingleIntersection::new(1.0, &csg.left),
+                SingleIntersection::new(2.0, &csg.right),
+                SingleIntersection::new(3.0, &csg.left),
+                SingleIntersection::new(4.0, &csg.right),
+            ]);
+
+            let times: Vec<f64> = csg
+                .filter_intersections(hits)
+                .iter()
+                .map(|inter| inter.time)
+                .collect();
+            let expected: Vec<f64> = expected.iter().map(|&i| (i + 1) as f64).collect();
+            assert_eq!(times, expected, "{operation:?}");
+        }
+    }
+
+    #[test]
+    fn ray_misses() {
+        let csg = Csg::new(
+            CsgOperation::Union,
+            Shapes::Sphere(Sphere::default()),
+            Shapes::Cube(Cube::default()),
+        );
+        let ray = Ray::from(((0, 2, -5), (0, 0, 1)));
+        assert!(csg.get_intersections(&ray).is_empty());
+    }
+
+    #[test]
+    fn normal_of_nearest_operand() {
+        let mut right = Cube::default();
+        right.transformation.translate((3, 0, 0));
+        let mut csg = Csg::new(
+            CsgOperation::Union,
+            Shapes::Sphere(Sphere::default()),
+            Shapes::Cube(right),
+        );
+        csg.transformation.rotate(Axis::Z, consts::FRAC_PI_2);
+
+        // The left sphere ends up around the origin and the right cube around (0, 3, 0)
+        assert_eq!(
+            csg.normal(&Coord::from((-1, 0, 0))),
+            Vector::from((-1, 0, 0))
+        );
+        assert_eq!(csg.normal(&Coord::from((0, 4, 0))), Vector::from((0, 1, 0)));
+        assert_eq!(
+            csg.normal(&Coord::from((0.5, 3.0, 0.0))),
+            Vector::from((1, 0, 0))
+        );
+    }
+
+    #[test]
+    fn ray_hits_union() {
+        let mut right = Sphere::default();
+        right.transformation.translate((0.0, 0.0, 0.5));
+        let csg = Csg::new(
+            CsgOperation::Union,
+            Shapes::Sphere(Sphere::default()),
+            Shapes::Sphere(right),
+        );
+
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let hits = csg.get_intersections(&ray);
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].time, hits[0].shape), (4.0, csg.left.as_ref()));
+        assert_eq!((hits[1].time, hits[1].shape), (6.5, csg.right.as_ref()));
+    }
+
+    #[test]
+    fn cube_with_spherical_bite() {
+        let mut bite = Sphere::default();
+        bite.transformation
+            .scale((0.5, 0.5, 0.5))
+            .translate((1, 1, 1));
+        let mut csg = Csg::new(
+            CsgOperation::Difference,
+            Shapes::Cube(Cube::default()),
+            Shapes::Sphere(bite),
+        );
+        csg.transformation.translate((0, 0, 10));
+
+        // Away from the bite, the ray goes through the whole cube
+        let ray = Ray::from(((0, 0, 0), (0, 0, 1)));
+        assert_eq!(csg.hit_times(&ray), vec![9.0, 11.0]);
+
+        // Near the corner, the ray leaves the cube through the surface of the bite
+        let ray = Ray::from(((0.9, 0.9, 0.0), (0, 0, 1)));
+        let hits = csg.get_intersections(&ray);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].time, 9.0);
+        assert!((hits[1].time - (10.0 + 1.0 - 0.23f64.sqrt())).abs() < 1e-9);
+        assert_eq!(hits[1].shape, csg.right.as_ref());
+        assert_eq!(hits[1].parents, vec![&csg.transformation]);
+
+        let ray = Ray::from(((0.95, 0.95, 0.0), (0, 0, 1)));
+        assert_eq!(csg.hit_times(&ray).len(), 2);
+    }
+
+    #[test]
+    fn intersection_of_sphere_and_cube() {
+        let mut cube = Cube::default();
+        cube.transformation.scale((0.8, 0.8, 0.8));
+        let csg = Csg::new(
+            CsgOperation::Intersection,
+            Shapes::Sphere(Sphere::default()),
+            Shapes::Cube(cube),
+        );
+
+        // Through the center, the faces of the cube are inside the sphere
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let hits = csg.get_intersections(&ray);
+        let times: Vec<f64> = hits.iter().map(|inter| inter.time).collect();
+        assert_eq!(times, vec![4.2, 5.8]);
+        assert!(hits.iter().all(|inter| inter.shape == csg.right.as_ref()));
+
+        // Near the edge of the cube, the sphere is the one inside the cube
+        let ray = Ray::from(((0.7, 0.7, -5.0), (0, 0, 1)));
+        let hits = csg.get_intersections(&ray);
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|inter| inter.shape == csg.left.as_ref()));
+    }
+}
@@ -0,0 +1,193 @@
+use std::ops::Mul;
+
+use crate::{
+    approx::EPSILON,
+    bounding_box::BoundingBox,
+    material::Material,
+    point::{coord::Coord, vector::Vector},
+    ray::Ray,
+    transformations::TransformationMatrix,
+};
+
+use super::Hittable;
+
+/// Axis aligned cube, before transforming it, it goes from -1 to 1 in every axis
+#[derive(Debug, PartialEq, Default)]
+pub struct Cube {
+    pub transformation: TransformationMatrix,
+    pub material: Material,
+}
+
+/// Times when a ray enters and leaves the slab between the planes at -1 and 1 along one axis
+fn check_axis(origin: f64, direction: f64) -> (f64, f64) {
+    let tmin_numerator = -1.0 - origin;
+    let tmax_numerator = 1.0 - origin;
+
+    // When the ray is parallel to the planes, the times are either -inf and inf (the ray is
+    // between the planes), or both inf with the same sign (the ray never enters the slab)
+    let (tmin, tmax) = match direction.abs() >= EPSILON {
+        true => (tmin_numerator / direction, tmax_numerator / direction),
+        false => (
+            tmin_numerator * f64::INFINITY,
+            tmax_numerator * f64::INFINITY,
+        ),
+    };
+
+    match tmin > tmax {
+        true => (tmax, tmin),
+        false => (tmin, tmax),
+    }
+}
+
+impl Hittable for Cube {
+    fn hit_times(&self, ray: &Ray) -> Vec<f64> {
+        let ray = self
+            .transformation
+            .inverse()
+            .map(|inverse| inverse.mul(ray))
+            .expect("Could not get inverse of transformation matrix");
+
+        let (xtmin, xtmax) = check_axis(ray.origin.x, ray.dir.x);
+        let (ytmin, ytmax) = check_axis(ray.origin.y, ray.dir.y);
+        let (ztmin, ztmax) = check_axis(ray.origin.z, ray.dir.z);
+
+        // The ray is inside the cube only while it is inside the three slabs at once
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+
+        if tmin > tmax {
+            return vec![];
+        }
+        vec![tmin, tmax]
+    }
+
+    fn normal(&self, at: &Coord) -> Vector {
+        let inverse = self
+            .transformation
+            .inverse()
+            .expect("Could not get inverse of transformation");
+        let at = inverse * at;
+
+        // The face that was hit is the one for the axis with the largest absolute value. On
+        // edges and corners there is a tie, and the first axis (x, then y) wins
+        let (absx, absy, absz) = (at.x.abs(), at.y.abs(), at.z.abs());
+        let object_normal = if absx >= absy && absx >= absz {
+            Vector::new(at.x, 0.0, 0.0)
+        } else if absy >= absz {
+            Vector::new(0.0, at.y, 0.0)
+        } else {
+            Vector::new(0.0, 0.0, at.z)
+        };
+
+        (inverse.transpose() * object_normal).normalize()
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Coord::from((-1, -1, -1)), Coord::from((1, 1, 1)))
+            .transform(&self.transformation.matrix)
+    }
+}
+
+#[cfg(test)]
+mod test_hittable_cube {
+    use super::*;
+
+    #[test]
+    fn ray_hits_each_face() {
+        let cases = [
+            ((5.0, 0.5, 0.0), (-1, 0, 0)),
+            ((-5.0, 0.5, 0.0), (1, 0, 0)),
+            ((0.5, 5.0, 0.0), (0, -1, 0)),
+            ((0.5, -5.0, 0.0), (0, 1, 0)),
+            ((0.5, 0.0, 5.0), (0, 0, -1)),
+            ((0.5, 0.0, -5.0), (0, 0, 1)),
+        ];
+
+        for (origin, dir) in cases {
+            let ray = Ray::from((origin, dir));
+            assert_eq!(Cube::default().hit_times(&ray), vec![4.0, 6.0]);
+        }
+    }
+
+    #[test]
+    fn ray_from_inside() {
+        let ray = Ray::from(((0.0, 0.5, 0.0), (0, 0, 1)));
+        assert_eq!(Cube::default().hit_times(&ray), vec![-1.0, 1.0]);
+    }
+
+    #[test]
+    fn ray_misses() {
+        let cases = [
+            ((-2, 0, 0), (0.2673, 0.5345, 0.8018)),
+            ((0, -2, 0), (0.8018, 0.2673, 0.5345)),
+            ((0, 0, -2), (0.5345, 0.8018, 0.2673)),
+            ((2, 0, 2), (0.0, 0.0, -1.0)),
+            ((0, 2, 2), (0.0, -1.0, 0.0)),
+            ((2, 2, 0), (-1.0, 0.0, 0.0)),
+        ];
+
+        for (origin, dir) in cases {
+            let ray = Ray::new(Coord::from(origin), Vector::from(dir));
+            assert!(Cube::default().hit_times(&ray).is_empty());
+        }
+    }
+
+    #[test]
+    fn face_normals() {
+        let cases = [
+            ((1.0, 0.5, -0.8), (1, 0, 0)),
+            ((-1.0, -0.2, 0.9), (-1, 0, 0)),
+            ((-0.4, 1.0, -0.1), (0, 1, 0)),
+            ((0.3, -1.0, -0.7), (0, -1, 0)),
+            ((-0.6, 0.3, 1.0), (0, 0, 1)),
+            ((0.4, 0.4, -1.0), (0, 0, -1)),
+        ];
+
+        for (at, expected) in cases {
+            let normal = Cube::default().normal(&Coord::from(at));
+            assert_eq!(normal, Vector::from(expected));
+        }
+    }
+
+    #[test]
+    fn edge_and_corner_normals() {
+        let cube = Cube::default();
+        assert_eq!(
+            cube.normal(&Coord::from((1, 1, 1))),
+            Vector::from((1, 0, 0))
+        );
+        assert_eq!(
+            cube.normal(&Coord::from((-1, -1, -1))),
+            Vector::from((-1, 0, 0))
+        );
+        assert_eq!(
+            cube.normal(&Coord::from((0, 1, -1))),
+            Vector::from((0, 1, 0))
+        );
+    }
+
+    #[test]
+    fn transformed_cube() {
+        let mut cube = Cube::default();
+        cube.transformation.scale((2, 1, 1)).translate((5, 0, 0));
+
+        let ray = Ray::from(((0, 0, 0), (1, 0, 0)));
+        assert_eq!(cube.hit_times(&ray), vec![3.0, 7.0]);
+        assert_eq!(
+            cube.normal(&Coord::from((3, 0, 0))),
+            Vector::from((-1, 0, 0))
+        );
+        assert_eq!(
+            cube.normal(&Coord::from((5.0, 1.0, 0.5))),
+            Vector::from((0, 1, 0))
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use std::ops::Mul;
+
+use crate::{
+    approx::EPSILON,
+    bounding_box::BoundingBox,
+    material::Material,
+    point::{coord::Coord, vector::Vector},
+    ray::Ray,
+    transformations::TransformationMatrix,
+};
+
+use super::Hittable;
+
+/// Cylinder of radius 1 around the y axis, before transforming it
+///
+/// The cylinder only exists between `minimum` and `maximum` in the y axis (not included), these
+/// are infinite by default. When it is `closed`, the ends of the cylinder are covered by caps.
+#[derive(Debug, PartialEq)]
+pub struct Cylinder {
+    pub transformation: TransformationMatrix,
+    pub material: Material,
+    pub minimum: f64,
+    pub maximum: f64,
+    pub closed: bool,
+}
+
+impl Default for Cylinder {
+    fn default() -> Self {
+        Self {
+            transformation: TransformationMatrix::default(),
+            material: Material::default(),
+            minimum: f64::NEG_INFINITY,
+            maximum: f64::INFINITY,
+            closed: false,
+        }
+    }
+}
+
+impl Cylinder {
+    /// Times when the ray (in object space) hits the caps of the cylinder
+    fn cap_times(&self, ray: &Ray) -> Vec<f64> {
+        // Caps are only worth checking if they exist and the ray is not parallel to them
+        if !self.closed || ray.dir.y.abs() < EPSILON {
+            return vec![];
+        }
+
+        [self.minimum, self.maximum]
+            .into_iter()
+            .map(|cap| (cap - ray.origin.y) / ray.dir.y)
+            .filter(|&time| {
+                let at = ray.position_at(time);
+                at.x * at.x + at.z * at.z <= 1.0
+            })
+            .collect()
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit_times(&self, ray: &Ray) -> Vec<f64> {
+        let ray = self
+            .transformation
+            .inverse()
+            .map(|inverse| inverse.mul(ray))
+            .expect("Could not get inverse of transformation matrix");
+
+        let mut times = self.cap_times(&ray);
+
+        // A ray parallel to the y axis can only hit the caps
+        let a = ray.dir.x * ray.dir.x + ray.dir.z * ray.dir.z;
+        if a.abs() >= EPSILON {
+            let b = 2.0 * ray.origin.x * ray.dir.x + 2.0 * ray.origin.z * ray.dir.z;
+            let c = ray.origin.x * ray.origin.x + ray.origin.z * ray.origin.z - 1.0;
+            let discriminant = (b * b) - (4.0 * a * c);
+            if discriminant < 0.0 {
+                return vec![];
+            }
+
+            let sqrt = discriminant.sqrt();
+            for time in [(-b - sqrt) / (2.0 * a), (-b + sqrt) / (2.0 * a)] {
+                let y = ray.origin.y + time * ray.dir.y;
+                if self.minimum < y && y < self.maximum {
+                    times.push(time);
+                }
+            }
+        }
+
+        times.sort_by(f64::total_cmp);
+        times
+    }
+
+    fn normal(&self, at: &Coord) -> Vector {
+        let inverse = self
+            .transformation
+            .inverse()
+            .expect("Could not get inverse of transformation");
+        let at = inverse * at;
+
+        let distance = at.x * at.x + at.z * at.z;
+        let object_normal = if distance < 1.0 && at.y >= self.maximum - EPSILON {
+            Vector::new(0.0, 1.0, 0.0)
+        } else if distance < 1.0 && at.y <= self.minimum + EPSILON {
+            Vector::new(0.0, -1.0, 0.0)
+        } else {
+            Vector::new(at.x, 0.0, at.z)
+        };
+
+        (inverse.transpose() * object_normal).normalize()
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Coord::new(-1.0, self.minimum, -1.0),
+            Coord::new(1.0, self.maximum, 1.0),
+        )
+        .transform(&self.transformation.matrix)
+    }
+}
+
+#[cfg(test)]
+mod test_hittable_cylinder {
+    use super::*;
+
+    fn truncated(closed: bool) -> Cylinder {
+        Cylinder {
+            minimum: 1.0,
+            maximum: 2.0,
+            closed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ray_misses() {
+        let cases = [
+            ((1, 0, 0), (0, 1, 0)),
+            ((0, 0, 0), (0, 1, 0)),
+            ((0, 0, -5), (1, 1, 1)),
+        ];
+
+        for (origin, dir) in cases {
+            let ray = Ray::new(Coord::from(origin), Vector::from(dir).normalize());
+            assert!(Cylinder::default().hit_times(&ray).is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_hits() {
+        let cases = [
+            // Tangent to the cylinder
+            ((1.0, 0.0, -5.0), (0.0, 0.0, 1.0), 5.0, 5.0),
+            // Through the axis
+            ((0.0, 0.0, -5.0), (0.0, 0.0, 1.0), 4.0, 6.0),
+            // At an angle
+            ((0.5, 0.0, -5.0), (0.1, 1.0, 1.0), 6.80798, 7.08872),
+        ];
+
+        for (origin, dir, t0, t1) in cases {
+            let ray = Ray::new(Coord::from(origin), Vector::from(dir).normalize());
+            let hits = Cylinder::default().hit_times(&ray);
+            assert_eq!(hits.len(), 2);
+            assert!(crate::approx::approx(hits[0], t0));
+            assert!(crate::approx::approx(hits[1], t1));
+        }
+    }
+
+    #[test]
+    fn body_normals() {
+        let cases = [
+            ((1, 0, 0), (1, 0, 0)),
+            ((0, 5, -1), (0, 0, -1)),
+            ((0, -2, 1), (0, 0, 1)),
+            ((-1, 1, 0), (-1, 0, 0)),
+        ];
+
+        for (at, expected) in cases {
+            let normal = Cylinder::default().normal(&Coord::from(at));
+            assert_eq!(normal, Vector::from(expected));
+        }
+    }
+
+    #[test]
+    fn truncated_cylinder() {
+        let cases = [
+            ((0.0, 1.5, 0.0), (0.1, 1.0, 0.0), 0),
+            ((0.0, 3.0, -5.0), (0.0, 0.0, 1.0), 0),
+            ((0.0, 0.0, -5.0), (0.0, 0.0, 1.0), 0),
+            // The ends are not part of the cylinder
+            ((0.0, 2.0, -5.0), (0.0, 0.0, 1.0), 0),
+            ((0.0, 1.0, -5.0), (0.0, 0.0, 1.0), 0),
+            ((0.0, 1.5, -2.0), (0.0, 0.0, 1.0), 2),
+        ];
+
+        for (origin, dir, count) in cases {
+            let ray = Ray::new(Coord::from(origin), Vector::from(dir).normalize());
+            assert_eq!(truncated(false).hit_times(&ray).len(), count);
+        }
+    }
+
+    #[test]
+    fn cap_hits() {
+        let cases = [
+            // Along the axis, through both caps
+            ((0.0, 3.0, 0.0), (0.0, -1.0, 0.0), 2),
+            ((0.0, 3.0, -2.0), (0.0, -1.0, 2.0), 2),
+            // Corner case, leaves through the edge of the lower cap
+            ((0.0, 4.0, -2.0), (0.0, -1.0, 1.0), 2),
+            ((0.0, 0.0, -2.0), (0.0, 1.0, 2.0), 2),
+            // Corner case, enters through the edge of the lower cap
+            ((0.0, -1.0, -2.0), (0.0, 1.0, 1.0), 2),
+        ];
+
+        for (origin, dir, count) in cases {
+            let ray = Ray::new(Coord::from(origin), Vector::from(dir).normalize());
+            assert_eq!(truncated(true).hit_times(&ray).len(), count);
+        }
+
+        let ray = Ray::from(((0, 3, 0), (0, -1, 0)));
+        assert_eq!(truncated(true).hit_times(&ray), vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn cap_normals() {
+        let cases = [
+            ((0.0, 1.0, 0.0), (0, -1, 0)),
+            ((0.5, 1.0, 0.0), (0, -1, 0)),
+            ((0.0, 1.0, 0.5), (0, -1, 0)),
+            ((0.0, 2.0, 0.0), (0, 1, 0)),
+            ((0.5, 2.0, 0.0), (0, 1, 0)),
+            ((0.0, 2.0, 0.5), (0, 1, 0)),
+        ];
+
+        for (at, expected) in cases {
+            let normal = truncated(true).normal(&Coord::from(at));
+            assert_eq!(normal, Vector::from(expected));
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::ops::Mul;
+
+use crate::{
+    bounding_box::BoundingBox,
+    bvh::Bvh,
+    intersection::intersections::IntersectionTracker,
+    material::Material,
+    point::{coord::Coord, vector::Vector},
+    ray::Ray,
+    transformations::TransformationMatrix,
+};
+
+use super::{Hittable, Shapes};
+
+/// Collection of shapes that are transformed together
+///
+/// The transformation of each child places it inside the group, and the transformation of the
+/// group places all of them in the world (or in the group that contains this one).
+#[derive(Debug, PartialEq, Default)]
+pub struct Group {
+    pub transformation: TransformationMatrix,
+    /// Groups have no surface of their own, so this material is never used for shading, only the
+    /// materials of the children are
+    pub material: Material,
+    children: Vec<Shapes>,
+    /// Hierarchy used to intersect the children, when it was built with `build_bvh`
+    bvh: Option<Bvh>,
+}
+
+impl Group {
+    pub fn new(children: Vec<Shapes>) -> Self {
+        Self {
+            children,
+            ..Default::default()
+        }
+    }
+
+    pub fn children(&self) -> &[Shapes] {
+        &self.children
+    }
+
+    /// Mutable access to the children. The hierarchy no longer matches them once they change, so
+    /// it is dropped and has to be built again with `build_bvh`
+    pub fn children_mut(&mut self) -> &mut Vec<Shapes> {
+        self.bvh = None;
+        &mut self.children
+    }
+
+    /// Whether the children are intersected through a bounding volume hierarchy
+    pub fn has_bvh(&self) -> bool {
+        self.bvh.is_some()
+    }
+
+    /// Build the bounding volume hierarchies of this group and of the groups inside it. It has to
+    /// be built again after changing the children
+    pub fn build_bvh(&mut self) {
+        self.children.iter_mut().for_each(Shapes::build_bvh);
+        self.bvh = Some(Bvh::build(&self.children));
+    }
+
+    /// Intersections of the ray with the children. Each intersection refers to the shape that was
+    /// hit, and records the transformation of this group (and of any group in between)
+    pub fn get_intersections(&self, ray: &Ray) -> IntersectionTracker<'_> {
+        let ray = self
+            .transformation
+            .inverse()
+            .map(|inverse| inverse.mul(ray))
+            .expect("Could not get inverse of transformation matrix");
+
+        let hits = match &self.bvh {
+            Some(bvh) => bvh.intersect(&self.children, &ray),
+            None => IntersectionTracker::new(
+                self.children
+                    .iter()
+                    .flat_map(|child| child.get_intersections(&ray))
+                    .collect(),
+            ),
+        };
+
+        IntersectionTracker::new(
+            hits.into_iter()
+                .map(|mut inter| {
+                    inter.parents.insert(0, &self.transformation);
+                    inter
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Hittable for Group {
+    fn hit_times(&self, ray: &Ray) -> Vec<f64> {
+        self.get_intersections(ray)
+            .iter()
+            .map(|inter| inter.time)
+            .collect()
+    }
+
+    /// Groups have no surface, this is the normal of the child nearest to the point
+    fn normal(&self, at: &Coord) -> Vector {
+        Shapes::composite_normal(&self.transformation, &self.children, at)
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.children
+            .iter()
+            .fold(BoundingBox::default(), |bounds, child| {
+                bounds.merge(&child.bounds())
+            })
+            .transform(&self.transformation.matrix)
+    }
+}
+
+#[cfg(test)]
+mod test_hittable_group {
+    use super::*;
+    use crate::{
+        shapes::sphere::Sphere,
+        transformations::{normal_to_world, world_to_object, Axis},
+        world::World,
+    };
+    use std::f64::consts;
+
+    #[test]
+    fn empty_group() {
+        let group = Group::default();
+        let ray = Ray::from(((0, 0, 0), (0, 0, 1)));
+        assert!(group.get_intersections(&ray).is_empty());
+    }
+
+    #[test]
+    fn intersect_children() {
+        let mut s2 = Sphere::default();
+        s2.transformation.translate((0, 0, -3));
+        let mut s3 = Sphere::default();
+        s3.transformation.translate((5, 0, 0));
+        let group = Group::new(vec![
+            Shapes::Sphere(Sphere::default()),
+            Shapes::Sphere(s2),
+            Shapes::Sphere(s3),
+        ]);
+
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let hits = group.get_intersections(&ray);
+        let shapes: Vec<_> = hits.iter().map(|inter| inter.shape).collect();
+        assert_eq!(
+            shapes,
+            vec![
+                &group.children()[1],
+                &group.children()[1],
+                &group.children()[0],
+                &group.children()[0]
+            ]
+        );
+        assert!(hits.iter().all(|inter| inter.parents.len() == 1
+            && std::ptr::eq(inter.parents[0], &group.transformation)));
+        assert_eq!(group.hit_times(&ray), vec![1.0, 3.0, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn intersect_transformed_group() {
+        let mut sphere = Sphere::default();
+        sphere.transformation.translate((5, 0, 0));
+        let mut group = Group::new(vec![Shapes::Sphere(sphere)]);
+        group.transformation.scale((2, 2, 2));
+
+        let ray = Ray::from(((10, 0, -10), (0, 0, 1)));
+        assert_eq!(group.get_intersections(&ray).len(), 2);
+    }
+
+    #[test]
+    fn point_from_world_to_object() {
+        let mut outer = Group::default();
+        outer.transformation.rotate(Axis::Y, consts::FRAC_PI_2);
+        let mut inner = Group::default();
+        inner.transformation.scale((2, 2, 2));
+        let mut sphere = Sphere::default();
+        sphere.transformation.translate((5, 0, 0));
+
+        // The point is given in the space of the innermost group, where the sphere is
+        let point = world_to_object(
+            &[&outer.transformation, &inner.transformation],
+            &Coord::from((-2, 0, -10)),
+        );
+        assert_eq!(point, Coord::from((5, 0, -1)));
+        assert_eq!(sphere.normal(&point), Vector::from((0, 0, -1)));
+    }
+
+    #[test]
+    fn normal_from_object_to_world() {
+        let mut outer = Group::default();
+        outer.transformation.rotate(Axis::Y, consts::FRAC_PI_2);
+        let mut inner = Group::default();
+        inner.transformation.scale((1, 2, 3));
+
+        let component = 3f64.sqrt() / 3.0;
+        let normal = Vector::new(component, component, component);
+        assert_eq!(
+            normal_to_world(&[&outer.transformation, &inner.transformation], normal),
+            Vector::from((0.2857, 0.4286, -0.8571))
+        );
+        assert_eq!(
+            normal_to_world(&[], Vector::from((0, 1, 0))),
+            Vector::from((0, 1, 0))
+        );
+    }
+
+    #[test]
+    fn normal_of_nested_child() {
+        let mut inner = Group::new(vec![{
+            let mut sphere = Sphere::default();
+            sphere.transformation.translate((5, 0, 0));
+            Shapes::Sphere(sphere)
+        }]);
+        inner.transformation.scale((1, 2, 3));
+        let mut outer = Group::new(vec![Shapes::Group(inner)]);
+        outer.transformation.rotate(Axis::Y, consts::FRAC_PI_2);
+
+        let world = World::new(vec![Shapes::Group(outer)]);
+
+        // The sphere ends up centered at (0, 0, -5), scaled 3 times along the x axis and 2 times
+        // along the y axis
+        let ray = Ray::from(((1.7321, 1.1547, -10.0), (0, 0, 1)));
+        let hits = world.intersect(&ray);
+        let hit = hits.hit().unwrap();
+        assert_eq!(hit.parents.len(), 2);
+
+        let comps = hit.prepare(&ray, &hits);
+        assert_eq!(comps.point, Coord::from((1.7321, 1.1547, -5.5774)));
+        assert_eq!(comps.normalv, Vector::from((0.2857, 0.4286, -0.8571)));
+    }
+
+    #[test]
+    fn normal_of_nearest_child() {
+        let mut sphere = Sphere::default();
+        sphere.transformation.translate((5, 0, 0));
+        let mut group = Group::new(vec![
+            Shapes::Sphere(Sphere::default()),
+            Shapes::Sphere(sphere),
+        ]);
+        group.transformation.scale((1, 2, 1));
+
+        assert_eq!(
+            group.normal(&Coord::from((0, 2, 0))),
+            Vector::from((0, 1, 0))
+        );
+        let component = 2f64.sqrt() / 2.0;
+        assert_eq!(
+            group.normal(&Coord::from((5.0, 2.0 * component, -component))),
+            Vector::new(0.0, 0.4472, -0.8944)
+        );
+        assert_eq!(
+            Group::default().normal(&Coord::from((0, 0, 0))),
+            Vector::from((0, 1, 0))
+        );
+    }
+
+    #[test]
+    fn bounding_box_of_children() {
+        let mut sphere = Sphere::default();
+        sphere.transformation.scale((2, 2, 2)).translate((2, 5, -3));
+        let cylinder = crate::shapes::cylinder::Cylinder {
+            minimum: -2.0,
+            maximum: 2.0,
+            ..Default::default()
+        };
+        let mut group = Group::new(vec![Shapes::Sphere(sphere), Shapes::Cylinder(cylinder)]);
+        group.transformation.translate((1, 0, 0));
+
+        let bounds = group.bounds();
+        assert_eq!(bounds.min, Coord::from((0, -2, -5)));
+        assert_eq!(bounds.max, Coord::from((5, 7, 1)));
+    }
+}
@@ -0,0 +1,123 @@
+use std::ops::Mul;
+
+use crate::{
+    approx::EPSILON,
+    bounding_box::BoundingBox,
+    material::Material,
+    point::{coord::Coord, vector::Vector},
+    ray::Ray,
+    transformations::TransformationMatrix,
+};
+
+use super::Hittable;
+
+/// Infinite plane, before transforming it, it is the xz plane (y = 0)
+#[derive(Debug, PartialEq, Default)]
+pub struct Plane {
+    pub transformation: TransformationMatrix,
+    pub material: Material,
+}
+
+impl Hittable for Plane {
+    fn hit_times(&self, ray: &Ray) -> Vec<f64> {
+        let ray = self
+            .transformation
+            .inverse()
+            .map(|inverse| inverse.mul(ray))
+            .expect("Could not get inverse of transformation matrix");
+
+        // A ray parallel to the plane will never hit it, and a coplanar ray would hit it
+        // infinitely many times, in which case we say that it was not hit
+        if ray.dir.y.abs() < EPSILON {
+            return vec![];
+        }
+
+        vec![-ray.origin.y / ray.dir.y]
+    }
+
+    fn normal(&self, _: &Coord) -> Vector {
+        let inverse = self
+            .transformation
+            .inverse()
+            .expect("Could not get inverse of transformation");
+        (inverse.transpose() * Vector::from((0, 1, 0))).normalize()
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Coord::new(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            Coord::new(f64::INFINITY, 0.0, f64::INFINITY),
+        )
+        .transform(&self.transformation.matrix)
+    }
+}
+
+#[cfg(test)]
+mod test_hittable_plane {
+    use super::*;
+    use std::f64::consts;
+
+    #[test]
+    fn constant_normal() {
+        let plane = Plane::default();
+        for at in [(0, 0, 0), (10, 0, -10), (-5, 0, 150)] {
+            assert_eq!(plane.normal(&Coord::from(at)), Vector::from((0, 1, 0)));
+        }
+    }
+
+    #[test]
+    fn parallel_ray() {
+        let ray = Ray::from(((0, 10, 0), (0, 0, 1)));
+        assert!(Plane::default().hit_times(&ray).is_empty());
+    }
+
+    #[test]
+    fn coplanar_ray() {
+        let ray = Ray::from(((0, 0, 0), (0, 0, 1)));
+        assert!(Plane::default().hit_times(&ray).is_empty());
+    }
+
+    #[test]
+    fn ray_from_above() {
+        let ray = Ray::from(((0, 1, 0), (0, -1, 0)));
+        assert_eq!(Plane::default().hit_times(&ray), vec![1.0]);
+    }
+
+    #[test]
+    fn ray_from_below() {
+        let ray = Ray::from(((0, -1, 0), (0, 1, 0)));
+        assert_eq!(Plane::default().hit_times(&ray), vec![1.0]);
+    }
+
+    #[test]
+    fn intersect_translated_plane() {
+        let mut plane = Plane::default();
+        plane.transformation.translate((0, -2, 0));
+        let ray = Ray::from(((0, 1, 0), (0, -1, 0)));
+        assert_eq!(plane.hit_times(&ray), vec![3.0]);
+    }
+
+    #[test]
+    fn normal_rotated() {
+        // Rotating the plane around the x axis turns it into a wall facing the z axis
+        let mut plane = Plane::default();
+        plane
+            .transformation
+            .rotate(crate::transformations::Axis::X, consts::FRAC_PI_2);
+        assert_eq!(
+            plane.normal(&Coord::from((0, 0, 0))),
+            Vector::from((0, 0, 1))
+        );
+
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        assert_eq!(plane.hit_times(&ray), vec![5.0]);
+    }
+}
@@ -0,0 +1,201 @@
+use std::ops::Mul;
+
+use crate::{
+    bounding_box::BoundingBox,
+    material::Material,
+    point::{coord::Coord, vector::Vector},
+    ray::Ray,
+    transformations::TransformationMatrix,
+};
+
+use super::{
+    triangle::{interpolate_texture_coords, moller_trumbore},
+    Hittable,
+};
+
+/// Triangle with a normal for each of its vertices
+///
+/// The normal at some point of the triangle is interpolated from the normals of the vertices,
+/// using the barycentric coordinates of the point. This makes a mesh of triangles look smooth.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmoothTriangle {
+    pub transformation: TransformationMatrix,
+    pub material: Material,
+    /// Coordinates in the texture of the material for each of the points
+    pub texture_coords: Option<[(f64, f64); 3]>,
+    p1: Coord,
+    p2: Coord,
+    p3: Coord,
+    n1: Vector,
+    n2: Vector,
+    n3: Vector,
+    e1: Vector,
+    e2: Vector,
+}
+
+impl SmoothTriangle {
+    pub fn new(
+        (p1, p2, p3): (Coord, Coord, Coord),
+        (n1, n2, n3): (Vector, Vector, Vector),
+    ) -> Self {
+        let e1 = p1.clone().vector_to(&p2);
+        let e2 = p1.clone().vector_to(&p3);
+
+        Self {
+            transformation: TransformationMatrix::default(),
+            material: Material::default(),
+            texture_coords: None,
+            p1,
+            p2,
+            p3,
+            n1,
+            n2,
+            n3,
+            e1,
+            e2,
+        }
+    }
+
+    pub fn points(&self) -> (&Coord, &Coord, &Coord) {
+        (&self.p1, &self.p2, &self.p3)
+    }
+
+    pub fn normals(&self) -> (&Vector, &Vector, &Vector) {
+        (&self.n1, &self.n2, &self.n3)
+    }
+}
+
+impl Hittable for SmoothTriangle {
+    fn hit_times(&self, ray: &Ray) -> Vec<f64> {
+        self.hit_times_with_uv(ray)
+            .into_iter()
+            .map(|(time, _)| time)
+            .collect()
+    }
+
+    /// Intersections always know the barycentric coordinates of the point, here they are found
+    /// from the point itself
+    fn normal(&self, at: &Coord) -> Vector {
+        let inverse = self
+            .transformation
+            .inverse()
+            .expect("Could not get inverse of transformation");
+        let to_point = self.p1.clone().vector_to(&(inverse * at));
+        let (d11, d12, d22) = (
+            self.e1.dot(&self.e1),
+            self.e1.dot(&self.e2),
+            self.e2.dot(&self.e2),
+        );
+        let (d1, d2) = (to_point.dot(&self.e1), to_point.dot(&self.e2));
+        let denominator = d11 * d22 - d12 * d12;
+        let u = (d22 * d1 - d12 * d2) / denominator;
+        let v = (d11 * d2 - d12 * d1) / denominator;
+        self.normal_with_uv(at, Some((u, v)))
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::from_points([&self.p1, &self.p2, &self.p3])
+            .transform(&self.transformation.matrix)
+    }
+
+    fn texture_coords(&self, uv: Option<(f64, f64)>) -> Option<(f64, f64)> {
+        Some(interpolate_texture_coords(
+            self.texture_coords.as_ref()?,
+            uv?,
+        ))
+    }
+
+    fn hit_times_with_uv(&self, ray: &Ray) -> Vec<(f64, Option<(f64, f64)>)> {
+        let ray = self
+            .transformation
+            .inverse()
+            .map(|inverse| inverse.mul(ray))
+            .expect("Could not get inverse of transformation matrix");
+
+        moller_trumbore(&self.p1, &self.e1, &self.e2, &ray)
+            .map(|(time, u, v)| vec![(time, Some((u, v)))])
+            .unwrap_or_default()
+    }
+
+    fn normal_with_uv(&self, at: &Coord, uv: Option<(f64, f64)>) -> Vector {
+        let Some((u, v)) = uv else {
+            return self.normal(at);
+        };
+
+        let inverse = self
+            .transformation
+            .inverse()
+            .expect("Could not get inverse of transformation");
+        let object_normal =
+            self.n2.scalar_mult(u) + self.n3.scalar_mult(v) + self.n1.scalar_mult(1.0 - u - v);
+        (inverse.transpose() * object_normal).normalize()
+    }
+}
+
+#[cfg(test)]
+mod test_hittable_smooth_triangle {
+    use super::*;
+    use crate::{
+        approx::approx,
+        intersection::{
+            intersections::IntersectionTracker, single_intersection::SingleIntersection,
+        },
+        shapes::Shapes,
+    };
+
+    fn make_triangle() -> SmoothTriangle {
+        SmoothTriangle::new(
+            (
+                Coord::from((0, 1, 0)),
+                Coord::from((-1, 0, 0)),
+                Coord::from((1, 0, 0)),
+            ),
+            (
+                Vector::from((0, 1, 0)),
+                Vector::from((-1, 0, 0)),
+                Vector::from((1, 0, 0)),
+            ),
+        )
+    }
+
+    #[test]
+    fn intersection_stores_uv() {
+        let shape = Shapes::SmoothTriangle(make_triangle());
+        let ray = Ray::from(((-0.2, 0.3, -2.0), (0, 0, 1)));
+        let hits = shape.get_intersections(&ray);
+        let (u, v) = hits[0].uv.unwrap();
+        assert!(approx(u, 0.45));
+        assert!(approx(v, 0.25));
+    }
+
+    #[test]
+    fn interpolated_normal() {
+        let t = make_triangle();
+        let normal = t.normal_with_uv(&Coord::from((0, 0, 0)), Some((0.45, 0.25)));
+        assert_eq!(normal, Vector::from((-0.5547, 0.83205, 0.0)));
+
+        // The coordinates of the point give the same normal
+        assert_eq!(t.normal(&Coord::from((-0.2, 0.3, 0.0))), normal);
+        let mut moved = make_triangle();
+        moved.transformation.translate((0, 0, 5));
+        assert_eq!(moved.normal(&Coord::from((-0.2, 0.3, 5.0))), normal);
+    }
+
+    #[test]
+    fn prepare_uses_interpolated_normal() {
+        let shape = Shapes::SmoothTriangle(make_triangle());
+        let ray = Ray::from(((-0.2, 0.3, -2.0), (0, 0, 1)));
+        let xs =
+            IntersectionTracker::new(vec![SingleIntersection::with_uv(1.0, &shape, 0.45, 0.25)]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(comps.normalv, Vector::from((-0.5547, 0.83205, 0.0)));
+    }
+}
@@ -1,10 +1,10 @@
 use std::ops::Mul;
 
 use crate::{
-    matrix::square4::Matrix4x4,
+    bounding_box::BoundingBox,
+    material::Material,
     point::{coord::Coord, vector::Vector},
     ray::Ray,
-    shapes::sphere,
     transformations::TransformationMatrix,
 };
 
@@ -13,6 +13,7 @@ use super::Hittable;
 #[derive(Debug, PartialEq, Default)]
 pub struct Sphere {
     pub transformation: TransformationMatrix,
+    pub material: Material,
 }
 
 impl Hittable for Sphere {
@@ -43,6 +44,19 @@ impl Hittable for Sphere {
         let at = inverse * at;
         (inverse.transpose() * Vector::from(at)).normalize()
     }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Coord::from((-1, -1, -1)), Coord::from((1, 1, 1)))
+            .transform(&self.transformation.matrix)
+    }
 }
 
 #[cfg(test)]
@@ -115,6 +129,7 @@ mod test_hittable_sphere {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn normal_translated() {
         let mut sphere = Sphere::default();
         sphere.transformation.translate((0, 1, 0));
@@ -0,0 +1,227 @@
+use std::ops::Mul;
+
+use crate::{
+    bounding_box::BoundingBox,
+    material::Material,
+    point::{coord::Coord, vector::Vector},
+    ray::Ray,
+    transformations::TransformationMatrix,
+};
+
+use super::Hittable;
+
+/// Flat triangle between three points
+///
+/// The edges and the normal are computed when the triangle is created, as they are needed for
+/// every intersection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Triangle {
+    pub transformation: TransformationMatrix,
+    pub material: Material,
+    /// Coordinates in the texture of the material for each of the points
+    pub texture_coords: Option<[(f64, f64); 3]>,
+    p1: Coord,
+    p2: Coord,
+    p3: Coord,
+    e1: Vector,
+    e2: Vector,
+    normal: Vector,
+}
+
+impl Triangle {
+    pub fn new(p1: Coord, p2: Coord, p3: Coord) -> Self {
+        let e1 = p1.clone().vector_to(&p2);
+        let e2 = p1.clone().vector_to(&p3);
+        let normal = e2.cross_product(&e1).normalize();
+
+        Self {
+            transformation: TransformationMatrix::default(),
+            material: Material::default(),
+            texture_coords: None,
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal,
+        }
+    }
+
+    pub fn points(&self) -> (&Coord, &Coord, &Coord) {
+        (&self.p1, &self.p2, &self.p3)
+    }
+}
+
+/// Möller–Trumbore intersection of a ray with the triangle starting at `p1` with edges `e1` and
+/// `e2`. Returns the time of the hit and its barycentric coordinates (u, v)
+pub(super) fn moller_trumbore(
+    p1: &Coord,
+    e1: &Vector,
+    e2: &Vector,
+    ray: &Ray,
+) -> Option<(f64, f64, f64)> {
+    let dir_cross_e2 = ray.dir.cross_product(e2);
+    let det = e1.dot(&dir_cross_e2);
+
+    // The ray is parallel to the triangle. The determinant grows with the size of the triangle
+    // and the length of the direction, so small triangles are not mistaken for parallel ones
+    if det.abs() <= 1e-12 * e1.magnitude() * e2.magnitude() * ray.dir.magnitude() {
+        return None;
+    }
+
+    let f = 1.0 / det;
+    let p1_to_origin = p1.clone().vector_to(&ray.origin);
+    let u = f * p1_to_origin.dot(&dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = p1_to_origin.cross_product(e1);
+    let v = f * ray.dir.dot(&origin_cross_e1);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    Some((f * e2.dot(&origin_cross_e1), u, v))
+}
+
+/// Texture coordinates at the point with barycentric coordinates (u, v)
+pub(super) fn interpolate_texture_coords(
+    [t1, t2, t3]: &[(f64, f64); 3],
+    (u, v): (f64, f64),
+) -> (f64, f64) {
+    let w = 1.0 - u - v;
+    (
+        t1.0 * w + t2.0 * u + t3.0 * v,
+        t1.1 * w + t2.1 * u + t3.1 * v,
+    )
+}
+
+impl Hittable for Triangle {
+    fn hit_times(&self, ray: &Ray) -> Vec<f64> {
+        self.hit_times_with_uv(ray)
+            .into_iter()
+            .map(|(time, _)| time)
+            .collect()
+    }
+
+    fn normal(&self, _: &Coord) -> Vector {
+        let inverse = self
+            .transformation
+            .inverse()
+            .expect("Could not get inverse of transformation");
+        (inverse.transpose() * &self.normal).normalize()
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::from_points([&self.p1, &self.p2, &self.p3])
+            .transform(&self.transformation.matrix)
+    }
+
+    fn texture_coords(&self, uv: Option<(f64, f64)>) -> Option<(f64, f64)> {
+        Some(interpolate_texture_coords(
+            self.texture_coords.as_ref()?,
+            uv?,
+        ))
+    }
+
+    fn hit_times_with_uv(&self, ray: &Ray) -> Vec<(f64, Option<(f64, f64)>)> {
+        let ray = self
+            .transformation
+            .inverse()
+            .map(|inverse| inverse.mul(ray))
+            .expect("Could not get inverse of transformation matrix");
+
+        moller_trumbore(&self.p1, &self.e1, &self.e2, &ray)
+            .map(|(time, u, v)| vec![(time, Some((u, v)))])
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test_hittable_triangle {
+    use super::*;
+
+    fn make_triangle() -> Triangle {
+        Triangle::new(
+            Coord::from((0, 1, 0)),
+            Coord::from((-1, 0, 0)),
+            Coord::from((1, 0, 0)),
+        )
+    }
+
+    #[test]
+    fn precomputed_values() {
+        let t = make_triangle();
+        assert_eq!(t.e1, Vector::from((-1, -1, 0)));
+        assert_eq!(t.e2, Vector::from((1, -1, 0)));
+        assert_eq!(t.normal, Vector::from((0, 0, -1)));
+    }
+
+    #[test]
+    fn constant_normal() {
+        let t = make_triangle();
+        for at in [(0.0, 0.5, 0.0), (-0.5, 0.75, 0.0), (0.5, 0.25, 0.0)] {
+            assert_eq!(t.normal(&Coord::from(at)), Vector::from((0, 0, -1)));
+        }
+    }
+
+    #[test]
+    fn parallel_ray() {
+        let ray = Ray::from(((0, -1, -2), (0, 1, 0)));
+        assert!(make_triangle().hit_times(&ray).is_empty());
+    }
+
+    #[test]
+    fn ray_misses_edges() {
+        let cases = [(1.0, 1.0, -2.0), (-1.0, 1.0, -2.0), (0.0, -1.0, -2.0)];
+        for origin in cases {
+            let ray = Ray::from((origin, (0, 0, 1)));
+            assert!(make_triangle().hit_times(&ray).is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_hits() {
+        let ray = Ray::from(((0.0, 0.5, -2.0), (0, 0, 1)));
+        assert_eq!(make_triangle().hit_times(&ray), vec![2.0]);
+    }
+
+    #[test]
+    fn ray_hits_small_triangle() {
+        let triangle = Triangle::new(
+            Coord::new(0.0, 0.001, 0.0),
+            Coord::new(-0.001, 0.0, 0.0),
+            Coord::new(0.001, 0.0, 0.0),
+        );
+        let ray = Ray::from(((0.0, 0.0005, -2.0), (0, 0, 1)));
+        assert_eq!(triangle.hit_times(&ray), vec![2.0]);
+    }
+
+    #[test]
+    fn transformed_triangle() {
+        let mut t = make_triangle();
+        t.transformation.translate((0, 0, 3));
+        let ray = Ray::from(((0.0, 0.5, -2.0), (0, 0, 1)));
+        assert_eq!(t.hit_times(&ray), vec![5.0]);
+    }
+
+    #[test]
+    fn interpolated_texture_coords() {
+        let mut t = make_triangle();
+        assert_eq!(t.texture_coords(Some((0.25, 0.5))), None);
+
+        t.texture_coords = Some([(0.5, 1.0), (0.0, 0.0), (1.0, 0.0)]);
+        assert_eq!(t.texture_coords(None), None);
+        assert_eq!(t.texture_coords(Some((0.0, 0.0))), Some((0.5, 1.0)));
+        assert_eq!(t.texture_coords(Some((0.25, 0.5))), Some((0.625, 0.25)));
+    }
+}
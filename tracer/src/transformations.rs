@@ -126,6 +126,28 @@ impl TransformationMatrix {
         self
     }
 
+    /// Generate a matrix that moves the world so that it is seen from `from`, looking towards `to`
+    /// with `up` pointing (roughly) upwards
+    pub fn view_transform(from: &Coord, to: &Coord, up: &Vector) -> Matrix4x4 {
+        let forward = from.clone().vector_to(to).normalize();
+        let left = forward.cross_product(&up.normalize());
+        let true_up = left.cross_product(&forward);
+
+        let orientation = Matrix4x4::from([
+            [left.x, left.y, left.z, 0.0],
+            [true_up.x, true_up.y, true_up.z, 0.0],
+            [-forward.x, -forward.y, -forward.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        orientation * Self::translation(Coord::new(-from.x, -from.y, -from.z))
+    }
+
+    /// Add a view transformation to the current matrix, see `view_transform`
+    pub fn look_at(&mut self, from: &Coord, to: &Coord, up: &Vector) -> &mut Self {
+        self.matrix = Self::view_transform(from, to, up) * self.matrix;
+        self
+    }
+
     /// Apply all of the translations built up to an object
     pub fn apply<A>(&self, object: &A) -> A
     where
@@ -135,6 +157,28 @@ impl TransformationMatrix {
     }
 }
 
+/// Convert a point in world space to the space of a shape inside of groups (or other shapes with
+/// children), given their transformations from the outermost to the innermost one
+pub fn world_to_object(parents: &[&TransformationMatrix], point: &Coord) -> Coord {
+    parents.iter().fold(point.clone(), |point, parent| {
+        let inverse = parent
+            .inverse()
+            .expect("Could not get inverse of transformation");
+        inverse * &point
+    })
+}
+
+/// Convert the normal of a shape inside of groups (or other shapes with children) to world space,
+/// given their transformations from the outermost to the innermost one
+pub fn normal_to_world(parents: &[&TransformationMatrix], normal: Vector) -> Vector {
+    parents.iter().rev().fold(normal, |normal, parent| {
+        let inverse = parent
+            .inverse()
+            .expect("Could not get inverse of transformation");
+        (inverse.transpose() * &normal).normalize()
+    })
+}
+
 pub enum Axis {
     X,
     Y,
@@ -293,4 +337,54 @@ mod test_transformations {
         let op = transformation_matrix.apply(&p);
         assert_eq!(op, np);
     }
+
+    #[test]
+    fn default_view_transform() {
+        let t = TransformationMatrix::view_transform(
+            &Coord::from((0, 0, 0)),
+            &Coord::from((0, 0, -1)),
+            &Vector::from((0, 1, 0)),
+        );
+        assert_eq!(t, Matrix4x4::identity());
+    }
+
+    #[test]
+    fn view_transform_looking_behind() {
+        let t = TransformationMatrix::view_transform(
+            &Coord::from((0, 0, 0)),
+            &Coord::from((0, 0, 1)),
+            &Vector::from((0, 1, 0)),
+        );
+        assert_eq!(t, TransformationMatrix::scaling(Coord::from((-1, 1, -1))));
+    }
+
+    #[test]
+    fn view_transform_moves_world() {
+        let t = TransformationMatrix::view_transform(
+            &Coord::from((0, 0, 8)),
+            &Coord::from((0, 0, 0)),
+            &Vector::from((0, 1, 0)),
+        );
+        assert_eq!(
+            t,
+            TransformationMatrix::translation(Coord::from((0, 0, -8)))
+        );
+    }
+
+    #[test]
+    fn arbitrary_view_transform() {
+        let mut t = TransformationMatrix::default();
+        t.look_at(
+            &Coord::from((1, 3, 2)),
+            &Coord::from((4, -2, 8)),
+            &Vector::from((1, 1, 0)),
+        );
+        let expected = Matrix4x4::from([
+            [-0.50709, 0.50709, 0.67612, -2.36643],
+            [0.76772, 0.60609, 0.12122, -2.82843],
+            [-0.35857, 0.59761, -0.71714, 0.00000],
+            [0.00000, 0.00000, 0.00000, 1.00000],
+        ]);
+        assert_eq!(t.matrix, expected);
+    }
 }
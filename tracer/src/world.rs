@@ -0,0 +1,727 @@
+use std::sync::OnceLock;
+
+use image::color::Color;
+
+use crate::{
+    background::Background,
+    bvh::Bvh,
+    emitter::Emitters,
+    intersection::{computations::Computations, intersections::IntersectionTracker},
+    light::{Light, LightSample, Lights},
+    point::coord::Coord,
+    ray::Ray,
+    shapes::{Hittable, Shapes},
+};
+
+/// Default number of times a ray is allowed to bounce between reflective surfaces
+pub const DEFAULT_MAX_DEPTH: usize = 5;
+
+/// Collection of all the shapes and lights in a scene
+#[derive(Debug)]
+pub struct World {
+    shapes: Vec<Shapes>,
+    pub lights: Vec<Lights>,
+    /// Light of the rays that miss every shape
+    pub background: Background,
+    /// Maximum recursion depth for the rays spawned when shading a point (reflections). Without
+    /// it, two mirrors facing each other would bounce a ray between them forever
+    pub max_depth: usize,
+    /// Hierarchy used to intersect the shapes, when it was built with `build_bvh`
+    bvh: Option<Bvh>,
+    /// Emissive shapes, collected the first time they are needed so every render of the world
+    /// only looks for them once
+    emitters: OnceLock<Emitters>,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self {
+            shapes: vec![],
+            lights: vec![],
+            background: Background::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            bvh: None,
+            emitters: OnceLock::new(),
+        }
+    }
+}
+
+impl World {
+    pub fn new(shapes: Vec<Shapes>) -> Self {
+        Self {
+            shapes,
+            ..Default::default()
+        }
+    }
+
+    pub fn shapes(&self) -> &[Shapes] {
+        &self.shapes
+    }
+
+    /// Mutable access to the shapes. The hierarchy and the emitters no longer match them once they
+    /// change, so they are dropped and have to be built again
+    pub fn shapes_mut(&mut self) -> &mut Vec<Shapes> {
+        self.bvh = None;
+        self.emitters = OnceLock::new();
+        &mut self.shapes
+    }
+
+    /// Whether the shapes are intersected through a bounding volume hierarchy
+    pub fn has_bvh(&self) -> bool {
+        self.bvh.is_some()
+    }
+
+    /// Build the bounding volume hierarchies of the world and of every group in it. It has to be
+    /// built again after changing the shapes
+    pub fn build_bvh(&mut self) {
+        self.shapes.iter_mut().for_each(Shapes::build_bvh);
+        self.bvh = Some(Bvh::build(&self.shapes));
+    }
+
+    /// Emissive shapes of the world, that light the other shapes as well as the lights do
+    pub fn emitters(&self) -> &Emitters {
+        self.emitters
+            .get_or_init(|| Emitters::collect(&self.shapes))
+    }
+
+    /// Intersect a ray with every shape in the world, skipping the ones the ray cannot hit when
+    /// the hierarchy was built
+    pub fn intersect(&self, ray: &Ray) -> IntersectionTracker<'_> {
+        if let Some(bvh) = &self.bvh {
+            return bvh.intersect(&self.shapes, ray);
+        }
+
+        let mut tracker = IntersectionTracker::new(vec![]);
+        self.shapes
+            .iter()
+            .for_each(|shape| tracker.merge(shape.get_intersections(ray)));
+        tracker
+    }
+
+    /// Check if no part of the light reaches the point, because there are shapes in the way
+    pub fn is_shadowed(&self, light: &Lights, point: &Coord) -> bool {
+        self.visibility(light, point) == 0.0
+    }
+
+    /// Fraction of the samples of the light that reach the point, 0 in its shadow and 1 when it
+    /// is fully lit. Area lights are partly visible in their penumbra
+    pub fn visibility(&self, light: &Lights, point: &Coord) -> f64 {
+        let samples = light.samples(point);
+        let visible = samples
+            .iter()
+            .filter(|sample| !self.is_occluded(point, sample))
+            .count();
+        visible as f64 / samples.len() as f64
+    }
+
+    /// Samples of every light and of every emissive shape for the point
+    pub fn light_samples(&self, point: &Coord) -> Vec<LightSample> {
+        self.lights
+            .iter()
+            .flat_map(|light| light.samples(point))
+            .chain(self.emitters().light_samples(point))
+            .collect()
+    }
+
+    /// Check if there is some shape between the point and where the light sample comes from
+    pub fn is_occluded(&self, point: &Coord, sample: &LightSample) -> bool {
+        let ray = Ray::new(point.clone(), sample.direction.clone());
+        self.intersect(&ray)
+            .hit()
+            .is_some_and(|hit| hit.time < sample.distance)
+    }
+
+    /// Color that the eye sees when looking in the direction of the ray
+    pub fn color_at(&self, ray: &Ray) -> Color {
+        self.color_at_depth(ray, self.max_depth)
+    }
+
+    fn color_at_depth(&self, ray: &Ray, remaining: usize) -> Color {
+        let tracker = self.intersect(ray);
+        match tracker.hit() {
+            Some(hit) => self.shade_hit(&hit.prepare(ray, &tracker), remaining),
+            None => self.background.radiance(&ray.dir),
+        }
+    }
+
+    /// Color at the point described by the computations, `remaining` is the number of bounces
+    /// that the ray is still allowed to do
+    pub fn shade_hit(&self, comps: &Computations, remaining: usize) -> Color {
+        let material = comps.shape.material();
+        let surface = self
+            .light_samples(&comps.over_point)
+            .iter()
+            .map(|sample| {
+                material.lighting(
+                    sample,
+                    &comps.eyev,
+                    &comps.normalv,
+                    self.is_occluded(&comps.over_point, sample),
+                    comps.texture_coords,
+                )
+            })
+            .fold(material.emissive, |acc, color| acc + color);
+
+        let reflected = self.reflected_color(comps, remaining);
+        let refracted = self.refracted_color(comps, remaining);
+
+        // Surfaces that both reflect and refract light, blend the two following the Fresnel effect
+        let material = comps.shape.material();
+        if material.reflective > 0.0 && material.transparency > 0.0 {
+            let reflectance = comps.schlick();
+            return surface + reflected * reflectance + refracted * (1.0 - reflectance);
+        }
+
+        surface + reflected + refracted
+    }
+
+    /// Color seen in the mirror image of the surface, weighted by how reflective the surface is
+    pub fn reflected_color(&self, comps: &Computations, remaining: usize) -> Color {
+        let reflective = comps.shape.material().reflective;
+        if remaining == 0 || reflective == 0.0 {
+            return Color::black();
+        }
+
+        let reflect_ray = Ray::new(comps.over_point.clone(), comps.reflectv.clone());
+        self.color_at_depth(&reflect_ray, remaining - 1) * reflective
+    }
+
+    /// Color seen through the surface, weighted by how transparent the surface is
+    pub fn refracted_color(&self, comps: &Computations, remaining: usize) -> Color {
+        let transparency = comps.shape.material().transparency;
+        if remaining == 0 || transparency == 0.0 {
+            return Color::black();
+        }
+
+        // Total internal reflection, no light gets through
+        let Some(direction) = comps.refracted_direction() else {
+            return Color::black();
+        };
+        let refract_ray = Ray::new(comps.under_point.clone(), direction);
+
+        self.color_at_depth(&refract_ray, remaining - 1) * transparency
+    }
+}
+
+#[cfg(test)]
+mod test_world {
+    use super::*;
+    use crate::{
+        approx::approx,
+        area_light::AreaLight,
+        intersection::single_intersection::SingleIntersection,
+        light::{directional::DirectionalLight, spot::SpotLight},
+        material::Material,
+        point::vector::Vector,
+        point_light::PointLight,
+        shapes::{plane::Plane, sphere::Sphere, triangle::Triangle},
+    };
+
+    fn default_world() -> World {
+        let outer = Sphere {
+            material: Material {
+                color: Color::from((0.8, 1.0, 0.6)),
+                diffuse: 0.7,
+                specular: 0.2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut inner = Sphere::default();
+        inner.transformation.scale((0.5, 0.5, 0.5));
+
+        let mut world = World::new(vec![Shapes::Sphere(outer), Shapes::Sphere(inner)]);
+        world.lights = vec![PointLight::new(Coord::from((-10, 10, -10)), Color::white()).into()];
+        world
+    }
+
+    #[test]
+    fn intersect_world() {
+        let world = default_world();
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let hits = world.intersect(&ray);
+        let times: Vec<f64> = hits.iter().map(|hit| hit.time).collect();
+        assert_eq!(times, vec![4.0, 4.5, 5.5, 6.0]);
+    }
+
+    #[test]
+    fn shade_from_outside() {
+        let world = default_world();
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(4.0, &world.shapes()[0])]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(
+            world.shade_hit(&comps, world.max_depth),
+            Color::from((0.38066, 0.47583, 0.2855))
+        );
+    }
+
+    #[test]
+    fn shade_emissive_surface() {
+        let mut world = default_world();
+        world.shapes_mut()[0].material_mut().emissive = Color::new(0.5, 0.25, 0.0);
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(4.0, &world.shapes()[0])]);
+        let comps = xs[0].prepare(&ray, &xs);
+        // The sphere is also a light, whose samples add their ambient term to its own surface
+        assert_eq!(
+            world.shade_hit(&comps, world.max_depth),
+            Color::from((0.95953, 0.77512, 0.2855))
+        );
+    }
+
+    #[test]
+    fn shade_from_inside() {
+        let mut world = default_world();
+        world.lights = vec![PointLight::new(Coord::from((0.0, 0.25, 0.0)), Color::white()).into()];
+        let ray = Ray::from(((0, 0, 0), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(0.5, &world.shapes()[1])]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(
+            world.shade_hit(&comps, world.max_depth),
+            Color::from((0.90498, 0.90498, 0.90498))
+        );
+    }
+
+    #[test]
+    fn color_when_ray_misses() {
+        let world = default_world();
+        let ray = Ray::from(((0, 0, -5), (0, 1, 0)));
+        assert_eq!(world.color_at(&ray), Color::black());
+    }
+
+    #[test]
+    fn color_of_the_background_when_ray_misses() {
+        let mut world = default_world();
+        world.background = Background::Gradient {
+            bottom: Color::black(),
+            top: Color::new(0.5, 0.7, 1.0),
+        };
+        let ray = Ray::from(((0, 0, -5), (0, 1, 0)));
+        assert_eq!(world.color_at(&ray), Color::new(0.5, 0.7, 1.0));
+        let ray = Ray::from(((0, 0, -5), (0, -1, 0)));
+        assert_eq!(world.color_at(&ray), Color::black());
+    }
+
+    #[test]
+    fn color_when_ray_hits() {
+        let world = default_world();
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        assert_eq!(
+            world.color_at(&ray),
+            Color::from((0.38066, 0.47583, 0.2855))
+        );
+    }
+
+    #[test]
+    fn shadows() {
+        let world = default_world();
+        let light = &world.lights[0];
+        // Nothing is collinear with the point and the light
+        assert!(!world.is_shadowed(light, &Coord::from((0, 10, 0))));
+        // The shapes are between the point and the light
+        assert!(world.is_shadowed(light, &Coord::from((10, -10, 10))));
+        // The light is between the point and the shapes
+        assert!(!world.is_shadowed(light, &Coord::from((-20, 20, -20))));
+        // The point is between the light and the shapes
+        assert!(!world.is_shadowed(light, &Coord::from((-2, 2, -2))));
+    }
+
+    #[test]
+    fn reflected_color_non_reflective() {
+        let mut world = default_world();
+        let mut inner = Sphere::default();
+        inner.transformation.scale((0.5, 0.5, 0.5));
+        inner.material.ambient = 1.0;
+        world.shapes_mut()[1] = Shapes::Sphere(inner);
+        let ray = Ray::from(((0, 0, 0), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(1.0, &world.shapes()[1])]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(
+            world.reflected_color(&comps, world.max_depth),
+            Color::black()
+        );
+    }
+
+    /// A half reflective mirror in front of the eye, with a shape that is lit only by its
+    /// ambient term behind the eye
+    fn mirror_world() -> World {
+        let mut mirror = Sphere::default();
+        mirror.material.reflective = 0.5;
+
+        let mut behind = Sphere {
+            material: Material {
+                color: Color::from((0.2, 0.4, 0.6)),
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        behind.transformation.translate((0, 0, -10));
+
+        let mut world = World::new(vec![Shapes::Sphere(mirror), Shapes::Sphere(behind)]);
+        world.lights = vec![PointLight::new(Coord::from((-10, 10, -10)), Color::white()).into()];
+        world
+    }
+
+    #[test]
+    fn reflected_color_reflective() {
+        let world = mirror_world();
+        let ray = Ray::from(((0, 0, -3), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(2.0, &world.shapes()[0])]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(
+            world.reflected_color(&comps, world.max_depth),
+            Color::from((0.1, 0.2, 0.3))
+        );
+    }
+
+    #[test]
+    fn shade_hit_adds_reflection() {
+        let world = mirror_world();
+        let ray = Ray::from(((0, 0, -3), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(2.0, &world.shapes()[0])]);
+        let comps = xs[0].prepare(&ray, &xs);
+
+        let light = &world.lights[0].samples(&comps.over_point)[0];
+        let surface = world.shapes()[0].material().lighting(
+            light,
+            &comps.eyev,
+            &comps.normalv,
+            world.is_occluded(&comps.over_point, light),
+            comps.texture_coords,
+        );
+        assert_eq!(
+            world.shade_hit(&comps, world.max_depth),
+            surface + Color::from((0.1, 0.2, 0.3))
+        );
+    }
+
+    #[test]
+    fn reflected_color_at_max_depth() {
+        let world = mirror_world();
+        let ray = Ray::from(((0, 0, -3), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(2.0, &world.shapes()[0])]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(world.reflected_color(&comps, 0), Color::black());
+    }
+
+    #[test]
+    fn mutually_reflective_surfaces_terminate() {
+        // The eye and the light are inside of a perfect mirror, so the ray bounces forever
+        // unless the recursion is bounded
+        let mut mirror = Sphere::default();
+        mirror.transformation.scale((5, 5, 5));
+        mirror.material.reflective = 1.0;
+
+        let mut world = World::new(vec![Shapes::Sphere(mirror)]);
+        world.lights = vec![PointLight::new(Coord::from((0, 0, 0)), Color::white()).into()];
+
+        let ray = Ray::from(((0, 0, 0), (0, 1, 0)));
+        let color = world.color_at(&ray);
+        assert!(color.red > 0.0 && color.green > 0.0 && color.blue > 0.0);
+    }
+
+    fn glass(material: Material) -> Material {
+        Material {
+            transparency: 1.0,
+            refractive_index: 1.5,
+            ..material
+        }
+    }
+
+    #[test]
+    fn refracted_color_opaque() {
+        let world = default_world();
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![
+            SingleIntersection::new(4.0, &world.shapes()[0]),
+            SingleIntersection::new(6.0, &world.shapes()[0]),
+        ]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(
+            world.refracted_color(&comps, world.max_depth),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn refracted_color_at_max_depth() {
+        let mut world = default_world();
+        world.shapes_mut()[0] = Shapes::Sphere(Sphere {
+            material: glass(Material::default()),
+            ..Default::default()
+        });
+        let ray = Ray::from(((0, 0, -5), (0, 0, 1)));
+        let xs = IntersectionTracker::new(vec![
+            SingleIntersection::new(4.0, &world.shapes()[0]),
+            SingleIntersection::new(6.0, &world.shapes()[0]),
+        ]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(world.refracted_color(&comps, 0), Color::black());
+    }
+
+    #[test]
+    fn refracted_color_total_internal_reflection() {
+        let mut world = default_world();
+        world.shapes_mut()[0] = Shapes::Sphere(Sphere {
+            material: glass(Material::default()),
+            ..Default::default()
+        });
+        let h = 2.0f64.sqrt() / 2.0;
+        let ray = Ray::from(((0.0, 0.0, h), (0, 1, 0)));
+        let xs = IntersectionTracker::new(vec![
+            SingleIntersection::new(-h, &world.shapes()[0]),
+            SingleIntersection::new(h, &world.shapes()[0]),
+        ]);
+        // The ray is inside the sphere, so the second intersection is the one that is seen
+        let comps = xs[1].prepare(&ray, &xs);
+        assert_eq!(
+            world.refracted_color(&comps, world.max_depth),
+            Color::black()
+        );
+    }
+
+    /// A see-through shape in front of the eye, which does not bend the light and whose surface
+    /// is black, with a shape that is lit only by its ambient term behind it
+    fn window_world() -> World {
+        let window = Sphere {
+            material: Material {
+                color: Color::black(),
+                ambient: 0.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                transparency: 0.5,
+                refractive_index: 1.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut behind = Sphere {
+            material: Material {
+                color: Color::from((0.2, 0.4, 0.6)),
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        behind.transformation.translate((0, 0, 10));
+
+        let mut world = World::new(vec![Shapes::Sphere(window), Shapes::Sphere(behind)]);
+        world.lights = vec![PointLight::new(Coord::from((-10, 10, -10)), Color::white()).into()];
+        world
+    }
+
+    #[test]
+    fn refracted_color_transparent() {
+        let world = window_world();
+        let ray = Ray::from(((0, 0, -3), (0, 0, 1)));
+        let xs = world.intersect(&ray);
+        let comps = xs.hit().unwrap().prepare(&ray, &xs);
+        // The light goes through the two surfaces of the window, each letting half of it through
+        assert_eq!(
+            world.refracted_color(&comps, world.max_depth),
+            Color::from((0.05, 0.1, 0.15))
+        );
+    }
+
+    #[test]
+    fn shade_hit_blends_with_schlick() {
+        let mut world = default_world();
+        let mut floor = Plane {
+            material: Material {
+                reflective: 0.5,
+                transparency: 0.5,
+                refractive_index: 1.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        floor.transformation.translate((0, -1, 0));
+        let mut ball = Sphere {
+            material: Material {
+                color: Color::new(1.0, 0.0, 0.0),
+                ambient: 0.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        ball.transformation.translate((0.0, -3.5, -0.5));
+        world.shapes_mut().push(Shapes::Plane(floor));
+        world.shapes_mut().push(Shapes::Sphere(ball));
+
+        let h = 2.0f64.sqrt() / 2.0;
+        let ray = Ray::from(((0.0, 0.0, -3.0), (0.0, -h, h)));
+        let xs = IntersectionTracker::new(vec![SingleIntersection::new(
+            2.0f64.sqrt(),
+            &world.shapes()[2],
+        )]);
+        let comps = xs[0].prepare(&ray, &xs);
+        assert_eq!(
+            world.shade_hit(&comps, world.max_depth),
+            Color::from((0.93391, 0.69643, 0.69243))
+        );
+    }
+
+    #[test]
+    fn reflective_floor() {
+        let mut world = default_world();
+        let mut floor = Plane::default();
+        floor.material.reflective = 0.5;
+        floor.transformation.translate((0, -1, 0));
+        world.shapes_mut().push(Shapes::Plane(floor));
+
+        let h = 2.0f64.sqrt() / 2.0;
+        let ray = Ray::from(((0.0, 0.0, -3.0), (0.0, -h, h)));
+        let xs = world.intersect(&ray);
+        let comps = xs.hit().unwrap().prepare(&ray, &xs);
+        assert_eq!(comps.shape, &world.shapes()[2]);
+        assert_eq!(
+            world.reflected_color(&comps, world.max_depth),
+            Color::from((0.19032, 0.2379, 0.14274))
+        );
+        assert_eq!(
+            world.shade_hit(&comps, world.max_depth),
+            Color::from((0.87677, 0.92436, 0.82918))
+        );
+    }
+
+    /// A square light 1 unit wide at a height of 4, above a sphere of radius 1 at the origin
+    fn area_light_world() -> World {
+        let light = AreaLight::rectangle(
+            Coord::new(-0.5, 4.0, -0.5),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+            Color::white(),
+        );
+        let mut world = World::new(vec![Shapes::Sphere(Sphere::default())]);
+        world.lights = vec![light.into()];
+        world
+    }
+
+    #[test]
+    fn area_light_visibility() {
+        let world = area_light_world();
+        let light = &world.lights[0];
+
+        // Nothing between the light and points above the sphere or far to its side
+        assert_eq!(world.visibility(light, &Coord::new(0.0, 1.5, 0.0)), 1.0);
+        assert_eq!(world.visibility(light, &Coord::new(5.0, -1.0, 0.0)), 1.0);
+        // Right under the sphere
+        assert_eq!(world.visibility(light, &Coord::new(0.0, -2.0, 0.0)), 0.0);
+
+        // In the penumbra part of the light is hidden
+        let visibility = world.visibility(light, &Coord::new(1.3, -2.0, 0.0));
+        assert!(visibility > 0.0 && visibility < 1.0, "{visibility}");
+    }
+
+    #[test]
+    fn area_light_soft_shadow() {
+        let mut world = area_light_world();
+        let mut floor = Plane::default();
+        floor.transformation.translate((0, -2, 0));
+        world.shapes_mut().push(Shapes::Plane(floor));
+
+        // The floor gets darker towards the center of the shadow of the sphere
+        let brightness: Vec<f64> = [1.8, 1.6, 1.45, 0.0]
+            .iter()
+            .map(|&x| {
+                let ray = Ray::new(
+                    Coord::new(x, 0.0, -5.0),
+                    Vector::new(0.0, -2.0, 5.0).normalize(),
+                );
+                world.color_at(&ray).red
+            })
+            .collect();
+        assert!(
+            brightness.windows(2).all(|pair| pair[0] > pair[1]),
+            "{brightness:?}"
+        );
+        assert!(approx(brightness[3], Material::default().ambient));
+    }
+
+    #[test]
+    fn directional_light_shadows_are_parallel() {
+        let mut world = World::new(vec![Shapes::Sphere(Sphere::default())]);
+        world.lights =
+            vec![DirectionalLight::new(Vector::new(0.0, -1.0, 0.0), Color::white()).into()];
+        let light = &world.lights[0];
+
+        // The shadow is as wide as the sphere, however far it is
+        assert!(world.is_shadowed(light, &Coord::new(0.9, -2.0, 0.0)));
+        assert!(world.is_shadowed(light, &Coord::new(0.9, -1000.0, 0.0)));
+        assert!(!world.is_shadowed(light, &Coord::new(1.1, -1000.0, 0.0)));
+        assert!(!world.is_shadowed(light, &Coord::new(0.0, 2.0, 0.0)));
+    }
+
+    #[test]
+    fn spot_light_on_floor() {
+        let light = SpotLight::new(
+            Coord::new(0.0, 4.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            std::f64::consts::FRAC_PI_6,
+            Color::new(16.0, 16.0, 16.0),
+        );
+        let mut world = World::new(vec![Shapes::Plane(Plane::default())]);
+        world.lights = vec![light.into()];
+
+        let brightness: Vec<f64> = [0.0, 2.0, 2.5]
+            .iter()
+            .map(|&x| {
+                let ray = Ray::new(Coord::new(x, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+                world.color_at(&ray).red
+            })
+            .collect();
+        // Right under the light the floor gets the intensity at 4 units, and then it gets darker
+        // until it is out of the cone (at 2.31 units from the center)
+        assert!(approx(brightness[0], 0.1 + 0.9 + 0.9));
+        assert!(brightness[1] > 0.0 && brightness[1] < brightness[0]);
+        assert_eq!(brightness[2], 0.0);
+    }
+
+    #[test]
+    fn emissive_shapes_light_the_world() {
+        let mut panel = Triangle::new(
+            Coord::new(-1.0, 2.0, -1.0),
+            Coord::new(1.0, 2.0, -1.0),
+            Coord::new(0.0, 2.0, 1.0),
+        );
+        panel.material.emissive = Color::new(3.0, 3.0, 3.0);
+        let floor = Plane {
+            material: Material {
+                ambient: 0.0,
+                specular: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut world = World::new(vec![Shapes::Triangle(panel), Shapes::Plane(floor)]);
+        let ray = Ray::new(
+            Coord::new(0.0, 1.0, -3.0),
+            Vector::new(0.0, -1.0, 3.0).normalize(),
+        );
+
+        // The emissive shapes are only collected once, until the shapes change
+        let collected = world.emitters();
+        assert_eq!(collected.emitters.len(), 1);
+        assert!(std::ptr::eq(collected, world.emitters()));
+        let samples = world.light_samples(&Coord::new(0.0, 0.0, 0.0));
+        assert_eq!(samples.len(), 16);
+        assert!(samples.iter().all(|sample| sample.direction.y > 0.0));
+
+        // The floor gets the light of the panel over its solid angle, and is not in its shadow
+        let color = world.color_at(&ray);
+        assert!((color.red - 0.3475).abs() < 0.03, "{color:?}");
+
+        // Changing the shapes collects them again
+        world.shapes_mut()[0].material_mut().emissive = Color::black();
+        assert!(world.emitters().is_empty());
+        assert_eq!(world.color_at(&ray), Color::black());
+    }
+}